@@ -0,0 +1,156 @@
+use crate::data::{parse_color, DataError, Document};
+use crate::moves::Move;
+
+use macroquad::color::Color;
+
+use std::path::Path;
+use std::str::FromStr;
+
+/// What one step of a move's animation draws.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum StepKind {
+    /// Particles bursting out of the target.
+    Burst,
+    /// A ball flying from the attacker to the target.
+    Projectile,
+    /// The whole screen flashing.
+    Flash,
+    /// The target's sprite changing colour.
+    Tint,
+}
+
+impl FromStr for StepKind {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "burst" => Ok(StepKind::Burst),
+            "projectile" => Ok(StepKind::Projectile),
+            "flash" => Ok(StepKind::Flash),
+            "tint" => Ok(StepKind::Tint),
+            _ => Err(()),
+        }
+    }
+}
+
+/// One part of a move's animation, written as `kind:seconds:#rrggbb` such as `burst:0.5:#ff8800`.
+/// The colour is optional and defaults to white.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct AnimationStep {
+    pub kind: StepKind,
+    pub seconds: f32,
+    pub color: Color,
+}
+
+impl FromStr for AnimationStep {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split(':').map(str::trim);
+        let kind = parts.next().ok_or(())?.parse()?;
+        let seconds: f32 = parts.next().ok_or(())?.parse().map_err(|_| ())?;
+        let color = match parts.next() {
+            Some(hex) => parse_color(hex)?,
+            None => Color::new(1.0, 1.0, 1.0, 1.0),
+        };
+
+        if parts.next().is_some() || seconds.is_nan() || seconds <= 0.0 {
+            return Err(());
+        }
+        Ok(Self {
+            kind,
+            seconds,
+            color,
+        })
+    }
+}
+
+/// The steps played one after another when a move is used.
+#[derive(PartialEq, Clone, Debug)]
+pub struct MoveAnimation {
+    pub mv: Move,
+    pub steps: Vec<AnimationStep>,
+}
+
+impl MoveAnimation {
+    /// Seconds the whole animation takes.
+    pub fn seconds(&self) -> f32 {
+        self.steps.iter().map(|step| step.seconds).sum()
+    }
+}
+
+/// The animation for every move that has one, loaded from the animations file.
+#[derive(Clone, Debug, Default)]
+pub struct AnimationBook {
+    animations: Vec<MoveAnimation>,
+}
+
+impl AnimationBook {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, DataError> {
+        Self::from_document(&Document::load(path)?)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, DataError> {
+        Self::from_document(&Document::parse(text)?)
+    }
+
+    fn from_document(document: &Document) -> Result<Self, DataError> {
+        let mut animations: Vec<MoveAnimation> = Vec::new();
+        for section in document.sections_named("animation") {
+            let mv: Move = section.parse("move")?;
+            if animations.iter().any(|animation| animation.mv == mv) {
+                return Err(section.invalid("move", section.require("move")?));
+            }
+
+            let steps = section
+                .get_all("step")
+                .into_iter()
+                .map(|step| step.parse().map_err(|_| section.invalid("step", step)))
+                .collect::<Result<Vec<AnimationStep>, DataError>>()?;
+            animations.push(MoveAnimation { mv, steps });
+        }
+
+        Ok(Self { animations })
+    }
+
+    pub fn get(&self, mv: Move) -> Option<&MoveAnimation> {
+        self.animations.iter().find(|animation| animation.mv == mv)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_animations() {
+        let book = AnimationBook::parse(
+            "
+[animation]
+move = Async
+step = projectile:0.4:#ff8800
+step = burst:0.5
+",
+        )
+        .unwrap();
+
+        let animation = book.get(Move::Async).unwrap();
+        assert_eq!(animation.steps.len(), 2);
+        assert_eq!(animation.steps[0].kind, StepKind::Projectile);
+        assert_eq!(animation.steps[0].color, Color::from_hex(0xff8800));
+        assert!((animation.seconds() - 0.9).abs() < 0.001);
+        assert!(book.get(Move::Math).is_none());
+
+        for step in [
+            "burst",
+            "burst:0",
+            "wobble:1",
+            "flash:1:orange",
+            "tint:1:#fff",
+        ] {
+            assert!(step.parse::<AnimationStep>().is_err(), "{}", step);
+        }
+        let duplicate = "[animation]\nmove = Math\n[animation]\nmove = Math\n";
+        assert!(AnimationBook::parse(duplicate).is_err());
+    }
+}
@@ -0,0 +1,454 @@
+use crate::data::{DataError, Document};
+use crate::entity::EntityType;
+use crate::layout;
+
+use macroquad::prelude::*;
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Size of a placeholder when the manifest doesn't give one.
+const DEFAULT_SIZE: (u16, u16) = (64, 64);
+
+/// Pixels along each side of a placeholder's checkers.
+const CHECKER_SIZE: u32 = 16;
+
+/// A texture listed in the manifest.
+#[derive(PartialEq, Clone, Debug)]
+pub struct TextureEntry {
+    pub key: String,
+    pub path: PathBuf,
+    /// Size of the placeholder drawn if the file can't be loaded.
+    pub size: (u16, u16),
+}
+
+/// A region cut out of an atlas image, in pixels.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct AtlasRegion {
+    pub x: u16,
+    pub y: u16,
+    pub width: u16,
+    pub height: u16,
+}
+
+impl AtlasRegion {
+    fn fits(&self, image: &Image) -> bool {
+        self.x as u32 + self.width as u32 <= image.width as u32
+            && self.y as u32 + self.height as u32 <= image.height as u32
+    }
+
+    fn rect(&self) -> Rect {
+        Rect::new(
+            self.x as f32,
+            self.y as f32,
+            self.width as f32,
+            self.height as f32,
+        )
+    }
+}
+
+/// One image holding many small textures, such as the UI art.
+/// Each region is written as `key:x,y,width,height` and becomes a texture of its own when loaded.
+#[derive(PartialEq, Clone, Debug)]
+pub struct AtlasEntry {
+    pub path: PathBuf,
+    pub regions: Vec<(String, AtlasRegion)>,
+}
+
+/// A music track or sound effect listed in the manifest.
+#[derive(PartialEq, Clone, Debug)]
+pub struct SoundEntry {
+    pub key: String,
+    pub path: PathBuf,
+}
+
+/// Every texture and sound the game needs, loaded from the asset manifest.
+#[derive(Clone, Debug, Default)]
+pub struct AssetManifest {
+    textures: Vec<TextureEntry>,
+    atlases: Vec<AtlasEntry>,
+    sounds: Vec<SoundEntry>,
+}
+
+impl AssetManifest {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, DataError> {
+        Self::from_document(&Document::load(path)?)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, DataError> {
+        Self::from_document(&Document::parse(text)?)
+    }
+
+    fn from_document(document: &Document) -> Result<Self, DataError> {
+        let mut manifest = Self::default();
+        for section in document.sections_named("texture") {
+            let key = section.require("key")?;
+            if manifest.textures.iter().any(|entry| entry.key == key) {
+                return Err(section.invalid("key", key));
+            }
+
+            let size = match section.get("size") {
+                Some(size) => parse_size(size).ok_or_else(|| section.invalid("size", size))?,
+                None => DEFAULT_SIZE,
+            };
+            manifest.textures.push(TextureEntry {
+                key: String::from(key),
+                path: PathBuf::from(section.require("path")?),
+                size,
+            });
+        }
+
+        for section in document.sections_named("atlas") {
+            let mut regions = Vec::new();
+            for value in section.get_all("region") {
+                let (key, region) =
+                    parse_region(value).ok_or_else(|| section.invalid("region", value))?;
+                if manifest.has_key(&key) || regions.iter().any(|(k, _)| *k == key) {
+                    return Err(section.invalid("region", value));
+                }
+                regions.push((key, region));
+            }
+            manifest.atlases.push(AtlasEntry {
+                path: PathBuf::from(section.require("path")?),
+                regions,
+            });
+        }
+
+        for section in document.sections_named("sound") {
+            let key = section.require("key")?;
+            if manifest.sound(key).is_some() {
+                return Err(section.invalid("key", key));
+            }
+            manifest.sounds.push(SoundEntry {
+                key: String::from(key),
+                path: PathBuf::from(section.require("path")?),
+            });
+        }
+
+        Ok(manifest)
+    }
+
+    pub fn textures(&self) -> &[TextureEntry] {
+        &self.textures
+    }
+
+    pub fn atlases(&self) -> &[AtlasEntry] {
+        &self.atlases
+    }
+
+    pub fn sounds(&self) -> &[SoundEntry] {
+        &self.sounds
+    }
+
+    /// The path of a sound, if the manifest lists it.
+    pub fn sound(&self, key: &str) -> Option<&Path> {
+        self.sounds
+            .iter()
+            .find(|entry| entry.key == key)
+            .map(|entry| entry.path.as_path())
+    }
+
+    fn has_key(&self, key: &str) -> bool {
+        self.textures.iter().any(|entry| entry.key == key)
+            || self
+                .atlases
+                .iter()
+                .any(|atlas| atlas.regions.iter().any(|(k, _)| k == key))
+    }
+
+    /// Add a texture that isn't in the manifest file, such as a trainer's sprite.
+    /// A key that is already listed keeps its first path.
+    pub fn add_texture(&mut self, key: &str, path: impl Into<PathBuf>) {
+        if self.has_key(key) {
+            return;
+        }
+        self.textures.push(TextureEntry {
+            key: String::from(key),
+            path: path.into(),
+            size: DEFAULT_SIZE,
+        });
+    }
+}
+
+/// Parse a size written as `240x60`.
+fn parse_size(s: &str) -> Option<(u16, u16)> {
+    let (width, height) = s.split_once('x')?;
+    let width = width.trim().parse().ok()?;
+    let height = height.trim().parse().ok()?;
+    match (width, height) {
+        (0, _) | (_, 0) => None,
+        size => Some(size),
+    }
+}
+
+/// Parse an atlas region written as `key:x,y,width,height`.
+fn parse_region(s: &str) -> Option<(String, AtlasRegion)> {
+    let (key, rect) = s.split_once(':')?;
+    let numbers = rect
+        .split(',')
+        .map(|n| n.trim().parse().ok())
+        .collect::<Option<Vec<u16>>>()?;
+    match numbers[..] {
+        [x, y, width, height] if width > 0 && height > 0 => Some((
+            String::from(key.trim()),
+            AtlasRegion {
+                x,
+                y,
+                width,
+                height,
+            },
+        )),
+        _ => None,
+    }
+}
+
+/// A magenta and black checkerboard, so a missing asset is obvious without crashing the game.
+pub fn placeholder_image(width: u16, height: u16) -> Image {
+    let mut image = Image::gen_image_color(width, height, BLACK);
+    for y in 0..height as u32 {
+        for x in 0..width as u32 {
+            if (x / CHECKER_SIZE + y / CHECKER_SIZE).is_multiple_of(2) {
+                image.set_pixel(x, y, MAGENTA);
+            }
+        }
+    }
+    image
+}
+
+/// The textures loaded from the manifest, looked up by key.
+pub struct Assets {
+    textures: HashMap<String, Texture2D>,
+    /// Keys whose file couldn't be loaded and have a placeholder instead.
+    missing: Vec<String>,
+    /// Handed out for keys that aren't in the manifest at all.
+    placeholder: Texture2D,
+}
+
+impl Assets {
+    fn new() -> Self {
+        let (width, height) = DEFAULT_SIZE;
+        Self {
+            textures: HashMap::new(),
+            missing: Vec::new(),
+            placeholder: Texture2D::from_image(&placeholder_image(width, height)),
+        }
+    }
+
+    /// The texture for a key, or a placeholder if nothing was loaded for it.
+    pub fn texture(&self, key: &str) -> &Texture2D {
+        self.textures.get(key).unwrap_or(&self.placeholder)
+    }
+
+    /// The texture for a key, only if the manifest has it.
+    pub fn get(&self, key: &str) -> Option<&Texture2D> {
+        self.textures.get(key)
+    }
+
+    pub fn missing(&self) -> &[String] {
+        &self.missing
+    }
+
+    /// The sprite for every entity type, keyed the way entities are built.
+    pub fn entity_textures(&self) -> HashMap<EntityType, Texture2D> {
+        EntityType::ALL
+            .into_iter()
+            .map(|entity_type| {
+                let texture = self.texture(entity_type.texture_key()).clone();
+                (entity_type, texture)
+            })
+            .collect()
+    }
+}
+
+/// Loads the manifest's textures and atlases a frame at a time so a loading screen can be drawn in between.
+pub struct AssetLoader {
+    manifest: AssetManifest,
+    loaded: usize,
+    assets: Assets,
+}
+
+impl AssetLoader {
+    pub fn new(manifest: AssetManifest) -> Self {
+        Self {
+            manifest,
+            loaded: 0,
+            assets: Assets::new(),
+        }
+    }
+
+    /// Files to load, each texture and atlas is one.
+    fn total(&self) -> usize {
+        self.manifest.textures.len() + self.manifest.atlases.len()
+    }
+
+    pub fn finished(&self) -> bool {
+        self.loaded >= self.total()
+    }
+
+    /// How much has been loaded, from 0 to 1.
+    pub fn progress(&self) -> f32 {
+        match self.total() {
+            0 => 1.0,
+            total => self.loaded as f32 / total as f32,
+        }
+    }
+
+    /// The name of the file loaded next, shown under the progress bar.
+    fn next_name(&self) -> Option<String> {
+        let textures = &self.manifest.textures;
+        match textures.get(self.loaded) {
+            Some(entry) => Some(entry.key.clone()),
+            None => self
+                .manifest
+                .atlases
+                .get(self.loaded - textures.len())
+                .map(|atlas| atlas.path.display().to_string()),
+        }
+    }
+
+    /// Load the next texture or atlas, falling back to placeholders if its file is missing or broken.
+    pub async fn load_next(&mut self) {
+        let textures = self.manifest.textures.len();
+        if self.loaded >= textures {
+            if let Some(atlas) = self.manifest.atlases.get(self.loaded - textures).cloned() {
+                self.loaded += 1;
+                self.load_atlas(&atlas).await;
+            }
+            return;
+        }
+        let entry = &self.manifest.textures[self.loaded];
+        self.loaded += 1;
+
+        let texture = match load_image(&entry.path.to_string_lossy()).await {
+            Ok(image) => Texture2D::from_image(&image),
+            Err(e) => {
+                eprintln!(
+                    "Using a placeholder for '{}', could not load {}: {}",
+                    entry.key,
+                    entry.path.display(),
+                    e
+                );
+                self.assets.missing.push(entry.key.clone());
+                let (width, height) = entry.size;
+                Texture2D::from_image(&placeholder_image(width, height))
+            }
+        };
+        self.assets.textures.insert(entry.key.clone(), texture);
+    }
+
+    /// Cut every region out of an atlas image.
+    async fn load_atlas(&mut self, atlas: &AtlasEntry) {
+        let image = match load_image(&atlas.path.to_string_lossy()).await {
+            Ok(image) => Some(image),
+            Err(e) => {
+                eprintln!(
+                    "Using placeholders for the atlas {}: {}",
+                    atlas.path.display(),
+                    e
+                );
+                None
+            }
+        };
+
+        for (key, region) in atlas.regions.iter() {
+            let texture = match image {
+                Some(ref image) if region.fits(image) => {
+                    Texture2D::from_image(&image.sub_image(region.rect()))
+                }
+                _ => {
+                    if image.is_some() {
+                        eprintln!(
+                            "Using a placeholder for '{}', it is outside of {}",
+                            key,
+                            atlas.path.display()
+                        );
+                    }
+                    self.assets.missing.push(key.clone());
+                    Texture2D::from_image(&placeholder_image(region.width, region.height))
+                }
+            };
+            self.assets.textures.insert(key.clone(), texture);
+        }
+    }
+
+    /// Draw a progress bar with the next asset to load.
+    pub fn draw(&self) {
+        let (width, height) = (800.0, 30.0);
+        let x = (layout::width() - width) / 2.0;
+        let y = layout::height() / 2.0;
+
+        draw_text("Loading...", x, y - 30.0, 40.0, WHITE);
+        draw_rectangle_lines(x, y, width, height, 2.0, WHITE);
+        draw_rectangle(x, y, width * self.progress(), height, WHITE);
+        if let Some(name) = self.next_name() {
+            draw_text(name.as_str(), x, y + height + 35.0, 30.0, GRAY);
+        }
+    }
+
+    pub fn finish(self) -> Assets {
+        self.assets
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_manifest() {
+        let mut manifest = AssetManifest::parse(
+            "
+[texture]
+key = button/empty
+path = assets/empty-button.png
+size = 240x60
+
+[texture]
+key = entity/go
+path = assets/go.png
+
+[atlas]
+path = assets/ui-atlas.png
+region = ui/health-bar:0,60,240,18
+
+[sound]
+key = sound/click
+path = assets/audio/click.wav
+",
+        )
+        .unwrap();
+
+        assert_eq!(manifest.textures()[0].size, (240, 60));
+        assert_eq!(manifest.textures()[1].size, DEFAULT_SIZE);
+        manifest.add_texture("entity/go", "elsewhere.png");
+        manifest.add_texture("trainer/intern", "intern.png");
+        assert_eq!(manifest.textures().len(), 3);
+        assert_eq!(manifest.textures()[1].path, PathBuf::from("assets/go.png"));
+        assert_eq!(manifest.atlases()[0].regions[0].1.y, 60);
+        manifest.add_texture("ui/health-bar", "health-bar.png");
+        assert_eq!(manifest.textures().len(), 3);
+        assert_eq!(
+            manifest.sound("sound/click"),
+            Some(Path::new("assets/audio/click.wav"))
+        );
+        assert_eq!(manifest.sound("sound/hit"), None);
+
+        for text in [
+            "[texture]\nkey = a\npath = a.png\nsize = 0x10",
+            "[texture]\nkey = a\npath = a.png\nsize = big",
+            "[texture]\nkey = a",
+            "[texture]\nkey = a\npath = a.png\n[texture]\nkey = a\npath = b.png",
+            "[atlas]\npath = ui.png\nregion = a:0,0,10",
+            "[texture]\nkey = a\npath = a.png\n[atlas]\npath = ui.png\nregion = a:0,0,10,10",
+            "[sound]\nkey = a",
+            "[sound]\nkey = a\npath = a.wav\n[sound]\nkey = a\npath = b.wav",
+        ] {
+            assert!(AssetManifest::parse(text).is_err(), "{}", text);
+        }
+
+        let image = placeholder_image(40, 20);
+        assert_eq!(image.get_pixel(0, 0), MAGENTA);
+        assert_eq!(image.get_pixel(16, 0), BLACK);
+        assert_eq!(image.get_pixel(16, 16), MAGENTA);
+    }
+}
@@ -0,0 +1,205 @@
+use crate::assets::AssetManifest;
+
+use macroquad::audio::{load_sound, play_sound, stop_sound, PlaySoundParams, Sound};
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+/// Music looped in the background of a scene.
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+pub enum Music {
+    Overworld,
+    Battle,
+    Victory,
+}
+
+impl Music {
+    const ALL: [Music; 3] = [Music::Overworld, Music::Battle, Music::Victory];
+
+    /// The key of its file in the asset manifest.
+    fn key(&self) -> &'static str {
+        match self {
+            Music::Overworld => "music/overworld",
+            Music::Battle => "music/battle",
+            Music::Victory => "music/victory",
+        }
+    }
+}
+
+/// Short sounds played once when something happens.
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+pub enum SoundEffect {
+    Click,
+    Hit,
+    Miss,
+    SuperEffective,
+    Faint,
+}
+
+impl SoundEffect {
+    const ALL: [SoundEffect; 5] = [
+        SoundEffect::Click,
+        SoundEffect::Hit,
+        SoundEffect::Miss,
+        SoundEffect::SuperEffective,
+        SoundEffect::Faint,
+    ];
+
+    /// The key of its file in the asset manifest.
+    fn key(&self) -> &'static str {
+        match self {
+            SoundEffect::Click => "sound/click",
+            SoundEffect::Hit => "sound/hit",
+            SoundEffect::Miss => "sound/miss",
+            SoundEffect::SuperEffective => "sound/super-effective",
+            SoundEffect::Faint => "sound/faint",
+        }
+    }
+}
+
+thread_local! {
+    static QUEUED: RefCell<Vec<SoundEffect>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Ask for a sound effect to be played this frame, from anywhere in the game.
+pub fn queue(effect: SoundEffect) {
+    QUEUED.with_borrow_mut(|queued| queued.push(effect));
+}
+
+/// Take every sound effect queued since last time, without repeats.
+fn take_queued() -> Vec<SoundEffect> {
+    let mut queued = QUEUED.with_borrow_mut(std::mem::take);
+    let mut seen = Vec::new();
+    queued.retain(|effect| match seen.contains(effect) {
+        true => false,
+        false => {
+            seen.push(*effect);
+            true
+        }
+    });
+    queued
+}
+
+/// Plays music and sound effects listed in the asset manifest.
+/// Any sound that is missing is skipped, so the game still runs with some or none of them.
+#[derive(Default)]
+pub struct Audio {
+    music: HashMap<Music, Sound>,
+    effects: HashMap<SoundEffect, Sound>,
+    playing: Option<Music>,
+    music_volume: f32,
+    effects_volume: f32,
+}
+
+impl Audio {
+    pub async fn load(manifest: &AssetManifest) -> Self {
+        let mut audio = Self {
+            music_volume: 0.6,
+            effects_volume: 0.8,
+            ..Default::default()
+        };
+        // without a backend every sound would print a warning when played
+        if !cfg!(feature = "audio") {
+            return audio;
+        }
+
+        let mut missing = Vec::new();
+        for music in Music::ALL {
+            match load(manifest, music.key()).await {
+                Some(sound) => {
+                    audio.music.insert(music, sound);
+                }
+                None => missing.push(music.key()),
+            }
+        }
+        for effect in SoundEffect::ALL {
+            match load(manifest, effect.key()).await {
+                Some(sound) => {
+                    audio.effects.insert(effect, sound);
+                }
+                None => missing.push(effect.key()),
+            }
+        }
+        if !missing.is_empty() {
+            eprintln!("Playing without these sounds: {}", missing.join(", "));
+        }
+        audio
+    }
+
+    /// Volumes from 0 to 1, the music changes straight away.
+    pub fn set_volumes(&mut self, music: f32, effects: f32) {
+        self.music_volume = music.clamp(0.0, 1.0);
+        self.effects_volume = effects.clamp(0.0, 1.0);
+        if let Some(sound) = self.playing.and_then(|music| self.music.get(&music)) {
+            macroquad::audio::set_sound_volume(sound, self.music_volume);
+        }
+    }
+
+    /// Switch to different music, or silence. The same music carries on rather than restarting.
+    pub fn play_music(&mut self, music: Option<Music>) {
+        if music == self.playing {
+            return;
+        }
+        if let Some(sound) = self.playing.and_then(|music| self.music.get(&music)) {
+            stop_sound(sound);
+        }
+        self.playing = music;
+        if let Some(sound) = music.and_then(|music| self.music.get(&music)) {
+            play_sound(
+                sound,
+                PlaySoundParams {
+                    looped: true,
+                    volume: self.music_volume,
+                },
+            );
+        }
+    }
+
+    /// Play the sound effects queued this frame, called once a frame.
+    pub fn update(&mut self) {
+        for effect in take_queued() {
+            if let Some(sound) = self.effects.get(&effect) {
+                play_sound(
+                    sound,
+                    PlaySoundParams {
+                        looped: false,
+                        volume: self.effects_volume,
+                    },
+                );
+            }
+        }
+    }
+}
+
+/// Load the sound the manifest lists for a key.
+async fn load(manifest: &AssetManifest, key: &str) -> Option<Sound> {
+    let path = manifest.sound(key)?;
+    load_sound(&path.to_string_lossy()).await.ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn queue_plays_each_effect_once() {
+        queue(SoundEffect::Hit);
+        queue(SoundEffect::Click);
+        queue(SoundEffect::Hit);
+        assert_eq!(take_queued(), vec![SoundEffect::Hit, SoundEffect::Click]);
+        assert!(take_queued().is_empty());
+    }
+
+    #[test]
+    fn manifest_lists_every_sound() {
+        let manifest = AssetManifest::load("assets/assets.data").unwrap();
+        let keys = Music::ALL
+            .iter()
+            .map(Music::key)
+            .chain(SoundEffect::ALL.iter().map(SoundEffect::key));
+        for key in keys {
+            let path = manifest.sound(key).unwrap();
+            assert!(path.exists(), "{}", path.display());
+        }
+    }
+}
@@ -0,0 +1,1324 @@
+use crate::animation::AnimationBook;
+use crate::audio::{self, SoundEffect};
+use crate::battle_log::BattleLog;
+use crate::entity::Entity;
+use crate::inventory::Inventory;
+use crate::item::{Effect, ItemCatalog};
+use crate::layout::Anchor;
+use crate::moves::{Effectiveness, Move};
+use crate::net::{Command, NetError, Session};
+use crate::progression::Progression;
+use crate::sprite::{SpriteAction, SpriteBook, View};
+use crate::ui::{
+    self, advance_pressed, AnimatedSprite, AnimationPlayer, Button, DialogueBox, DialogueSettings,
+    EntityImageParams, EntityStats, HealthTween, HitEffect, NavInput,
+};
+use crate::Team;
+
+use ::rand::rngs::StdRng;
+use ::rand::{Rng, SeedableRng};
+use macroquad::prelude::*;
+
+use std::collections::VecDeque;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Who chooses the actions for a side of the battle.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum Controller {
+    Human,
+    Computer,
+    /// The other player in a networked battle.
+    Remote,
+}
+
+/// How a computer controlled side picks its moves.
+#[derive(PartialEq, Clone, Copy, Debug, Default)]
+pub enum Strategy {
+    #[default]
+    Random,
+    /// Prefer moves the target is weak to and avoid ones it resists.
+    Smart,
+}
+
+impl FromStr for Strategy {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "random" => Ok(Strategy::Random),
+            "smart" => Ok(Strategy::Smart),
+            _ => Err(()),
+        }
+    }
+}
+
+/// One of the two teams taking part in a battle.
+pub struct Side {
+    pub name: String,
+    pub team: Team,
+    pub controller: Controller,
+    pub strategy: Strategy,
+}
+
+impl Side {
+    pub fn new(name: &str, team: Team, controller: Controller) -> Self {
+        Self {
+            name: String::from(name),
+            team,
+            controller,
+            strategy: Strategy::default(),
+        }
+    }
+}
+
+/// The battle state machine, every choosing state holds the index of the side choosing.
+#[derive(PartialEq, Clone, Debug)]
+pub enum State {
+    Wait(usize),
+    Move(usize),
+    Switch(usize),
+    /// Picking an item to use on the side's active entity.
+    Bag(usize),
+    Handoff(usize),
+    Opponent,
+    Dialogue(Box<State>),
+    End,
+}
+
+/// Chance that an entity above the level cap ignores an order.
+const DISOBEY_CHANCE: f64 = 0.5;
+
+/// Highest chance of recruiting a wild entity, however many tools are used.
+const MAX_RECRUIT_CHANCE: f64 = 0.95;
+
+/// What a side does with its turn.
+#[derive(PartialEq, Clone, Copy, Debug)]
+enum Action {
+    Attack(Move),
+    /// The turn was spent on something else, such as a failed recruit or using an item.
+    Pass,
+}
+
+/// Textures used to draw the battle ui, and the animations played for moves.
+pub struct BattleTextures<'a> {
+    pub empty_button: &'a Texture2D,
+    pub attack_button: &'a Texture2D,
+    pub switch_button: &'a Texture2D,
+    pub forfeit_button: &'a Texture2D,
+    pub health_bar: &'a Texture2D,
+    pub animations: &'a AnimationBook,
+    pub sprites: &'a SpriteBook,
+}
+
+/// Screen positions for the side drawn closest to the viewer and the side drawn furthest away.
+/// The near side keeps to the bottom left corner and the far side to the top right.
+struct SideLayout {
+    anchor: Anchor,
+    stats: Vec2,
+    sprite: Vec2,
+}
+
+const NEAR_LAYOUT: SideLayout = SideLayout {
+    anchor: Anchor::BottomLeft,
+    stats: Vec2::new(100.0, -480.0),
+    sprite: Vec2::new(400.0, -530.0),
+};
+
+const FAR_LAYOUT: SideLayout = SideLayout {
+    anchor: Anchor::TopRight,
+    stats: Vec2::new(-1320.0, 100.0),
+    sprite: Vec2::new(-1020.0, 50.0),
+};
+
+/// The menu buttons are in a column this far left of the right edge of the screen.
+const MENU_X: f32 = -820.0;
+
+/// A menu button in the column on the right.
+fn menu_button(texture: &Texture2D, y: f32) -> Button<'_> {
+    Button::anchored(texture, Anchor::TopRight, vec2(MENU_X, y))
+}
+
+/// A button in the menu column sized to fit its label.
+fn text_menu_button(texture: &Texture2D, label: impl Into<String>, y: f32) -> Button<'_> {
+    Button::text_anchored(texture, label, Anchor::TopRight, vec2(MENU_X, y))
+}
+
+/// What is drawn for one side, which lags behind the real team until the dialogue catches up.
+struct SideDisplay {
+    /// Index of the entity shown.
+    active: usize,
+    health: HealthTween,
+    hit: Option<HitEffect>,
+    /// Made from the shown entity's sprite sheet the first time it is drawn.
+    sprite: Option<AnimatedSprite>,
+}
+
+impl SideDisplay {
+    fn new(team: &Team) -> Self {
+        let health = team.peek_active().map_or(0, |entity| entity.health);
+        Self {
+            active: team.get_active_index(),
+            health: HealthTween::new(health),
+            hit: None,
+            sprite: None,
+        }
+    }
+
+    /// Show an entity with the given health, sliding the bar if the same entity is still out.
+    fn show(&mut self, active: usize, health: u32) {
+        if active != self.active {
+            self.active = active;
+            self.health = HealthTween::new(health);
+            self.hit = None;
+            self.sprite = None;
+            return;
+        }
+        let change = health as i64 - self.health.target() as i64;
+        if change != 0 {
+            self.health.set(health);
+            self.hit = Some(HitEffect::new(change));
+        }
+        if change < 0 {
+            self.play(SpriteAction::Hurt);
+        }
+    }
+
+    fn play(&mut self, action: SpriteAction) {
+        if let Some(ref mut sprite) = self.sprite {
+            sprite.play(action);
+        }
+    }
+
+    fn update(&mut self, delta: f32, view: View) {
+        self.health.update(delta);
+        if let Some(ref mut sprite) = self.sprite {
+            sprite.update(delta, view);
+        }
+        if let Some(ref mut hit) = self.hit {
+            hit.update(delta);
+            if hit.finished() {
+                self.hit = None;
+            }
+        }
+    }
+}
+
+/// The health a side should show once the dialogue reaches a message.
+struct HealthMark {
+    /// Number of the message counting every message since the battle began.
+    message: usize,
+    side: usize,
+    active: usize,
+    health: u32,
+}
+
+/// Lines describing a move for its tooltip, with how well it would work against the target if there is one.
+fn move_details(mv: Move, user: &Entity, target: Option<&Entity>) -> Vec<(String, Color)> {
+    let power = match mv.power() {
+        Some(power) => format!("Power: {}", power),
+        None => String::from("Power: -"),
+    };
+    let priority = match mv.get_priority() {
+        0 => String::from("Priority: normal"),
+        priority => format!("Priority: {} (goes first)", priority),
+    };
+
+    let theme = ui::theme();
+    let mut lines = vec![
+        (format!("{}", mv), theme.highlight_color),
+        (power, theme.text_color),
+        (format!("Accuracy: {}%", user.accuracy), theme.text_color),
+        (priority, theme.text_color),
+        (String::from(mv.description()), theme.muted_color),
+    ];
+    if let Some(target) = target {
+        let effectiveness = mv.effectiveness(target);
+        let color = match effectiveness {
+            Effectiveness::Super => GREEN,
+            Effectiveness::Normal => theme.text_color,
+            Effectiveness::NotVery => ORANGE,
+            Effectiveness::None => RED,
+        };
+        lines.push((format!("Against {}: {}", target, effectiveness), color));
+    }
+    lines
+}
+
+/// Lines describing a team member for its tooltip in the switch menu.
+fn switch_details(entity: &Entity) -> Vec<(String, Color)> {
+    let theme = ui::theme();
+    let mut lines = vec![
+        (
+            format!("{} Lv. {}", entity, entity.level),
+            theme.highlight_color,
+        ),
+        (
+            format!("Health: {}/{}", entity.health, entity.max_health),
+            theme.text_color,
+        ),
+    ];
+    if entity.health == 0 {
+        lines.push((String::from("Fainted, it can't battle"), theme.error_color));
+    }
+    lines
+}
+
+/// Where exported battle logs are written.
+const LOG_DIR: &str = "battle-logs";
+
+/// What one move did, noted while the turn is resolved.
+struct MoveOutcome {
+    /// 0 if the first side moved, 1 for the second.
+    mover: usize,
+    mv: Option<Move>,
+    /// Length of the text queue before and after the move.
+    start: usize,
+    end: usize,
+    health: [u32; 2],
+    sound: Option<SoundEffect>,
+}
+
+/// A move to animate once the dialogue moves past the message saying it was used.
+struct AnimationMark {
+    message: usize,
+    attacker: usize,
+    mv: Move,
+}
+
+pub struct Battle<'a> {
+    sides: [Side; 2],
+    pending: [Option<Action>; 2],
+    perspective: usize,
+    winner: Option<usize>,
+    /// Set for battles against wild entities, which can be recruited or run from.
+    wild: bool,
+    recruited: Option<Entity>,
+    /// Entities on human controlled sides that this progression hasn't earned may ignore orders.
+    progression: Option<Progression>,
+    /// Set when entities on human sides earn experience for knocking out opponents.
+    experience: bool,
+    /// The player's items, for battles started from the overworld.
+    inventory: Option<Inventory>,
+    items: Option<&'a ItemCatalog>,
+    turn: u32,
+    rng: StdRng,
+    session: Option<Session>,
+    state: State,
+    /// States to go back to when a menu is cancelled, the most recent last.
+    menus: Vec<State>,
+    debounce: bool,
+    debounce_step: bool,
+    text_queue: VecDeque<String>,
+    dialogue_box: DialogueBox,
+    /// Messages already read and taken off the queue.
+    messages_shown: usize,
+    display: [SideDisplay; 2],
+    health_marks: VecDeque<HealthMark>,
+    /// Sounds to play once the dialogue reaches a message.
+    sound_marks: VecDeque<(usize, SoundEffect)>,
+    animations: &'a AnimationBook,
+    animation_marks: VecDeque<AnimationMark>,
+    sprites: &'a SpriteBook,
+    /// The animation playing and the side it is aimed at.
+    animation: Option<(AnimationPlayer, usize)>,
+    /// Whether move animations are shown at all.
+    play_animations: bool,
+    near_ui: EntityStats<'a>,
+    far_ui: EntityStats<'a>,
+    attack_button: Button<'a>,
+    switch_button: Button<'a>,
+    forfeit_button: Button<'a>,
+    recruit_button: Button<'a>,
+    bag_button: Button<'a>,
+    back_button: Button<'a>,
+    log: BattleLog,
+    log_open: bool,
+    log_button: Button<'a>,
+    empty_button_texture: &'a Texture2D,
+}
+
+impl<'a> Battle<'a> {
+    pub fn new(sides: [Side; 2], textures: BattleTextures<'a>) -> Self {
+        let display = [
+            SideDisplay::new(&sides[0].team),
+            SideDisplay::new(&sides[1].team),
+        ];
+        let mut battle = Self {
+            sides,
+            pending: [None, None],
+            perspective: 0,
+            winner: None,
+            wild: false,
+            recruited: None,
+            progression: None,
+            experience: false,
+            inventory: None,
+            items: None,
+            turn: 0,
+            rng: StdRng::from_entropy(),
+            session: None,
+            state: State::End,
+            menus: Vec::new(),
+            debounce: false,
+            debounce_step: false,
+            text_queue: VecDeque::new(),
+            dialogue_box: DialogueBox::default(),
+            messages_shown: 0,
+            display,
+            health_marks: VecDeque::new(),
+            sound_marks: VecDeque::new(),
+            animations: textures.animations,
+            animation_marks: VecDeque::new(),
+            sprites: textures.sprites,
+            animation: None,
+            play_animations: true,
+            near_ui: EntityStats::anchored(
+                NEAR_LAYOUT.anchor,
+                NEAR_LAYOUT.stats,
+                textures.health_bar,
+            ),
+            far_ui: EntityStats::anchored(FAR_LAYOUT.anchor, FAR_LAYOUT.stats, textures.health_bar),
+            attack_button: menu_button(textures.attack_button, 600.0),
+            switch_button: menu_button(textures.switch_button, 675.0),
+            forfeit_button: menu_button(textures.forfeit_button, 750.0),
+            recruit_button: text_menu_button(textures.empty_button, "Fork the repo", 525.0),
+            bag_button: text_menu_button(textures.empty_button, "Bag", 825.0),
+            back_button: text_menu_button(textures.empty_button, "Back", 450.0),
+            log: BattleLog::new(),
+            log_open: false,
+            log_button: Button::text_anchored(
+                textures.empty_button,
+                "Log (L)",
+                Anchor::TopLeft,
+                vec2(50.0, 30.0),
+            ),
+            empty_button_texture: textures.empty_button,
+        };
+        battle.state = battle.turn_start();
+        let first_human = battle.human_sides().next();
+        if let Some(side) = first_human {
+            battle.perspective = side;
+        }
+        battle
+    }
+
+    /// Create a battle against another game instance.
+    /// The sides must be in the same order on both instances, the session's seed keeps them in lockstep.
+    pub fn networked(sides: [Side; 2], textures: BattleTextures<'a>, session: Session) -> Self {
+        let mut battle = Self::new(sides, textures);
+        battle.rng = StdRng::seed_from_u64(session.seed());
+        battle.session = Some(session);
+        battle
+    }
+
+    /// Make this a battle against a wild entity on the second side.
+    /// The player can try to recruit it, and forfeiting runs away without losing.
+    pub fn set_wild(&mut self) {
+        self.wild = true;
+    }
+
+    /// Entities above the player's level cap won't always obey the people controlling them.
+    pub fn set_progression(&mut self, progression: Progression) {
+        self.progression = Some(progression);
+    }
+
+    /// Give experience to human controlled entities that knock out an opponent.
+    pub fn set_experience(&mut self) {
+        self.experience = true;
+    }
+
+    pub fn set_dialogue_settings(&mut self, settings: DialogueSettings) {
+        self.dialogue_box.set_settings(settings);
+    }
+
+    /// Turn move animations on or off, health bars still slide when they are off.
+    pub fn set_animations(&mut self, enabled: bool) {
+        self.play_animations = enabled;
+    }
+
+    /// Let the human side use items from an inventory, and recruit tools when the battle is wild.
+    /// Items aren't shared over the network, so this is only for battles against the computer.
+    pub fn set_inventory(&mut self, inventory: Inventory, catalog: &'a ItemCatalog) {
+        self.inventory = Some(inventory);
+        self.items = Some(catalog);
+    }
+
+    /// Take back whatever is left of the inventory given to the battle.
+    pub fn take_inventory(&mut self) -> Option<Inventory> {
+        self.inventory.take()
+    }
+
+    /// Take the entity recruited during a wild battle.
+    pub fn take_recruited(&mut self) -> Option<Entity> {
+        self.recruited.take()
+    }
+
+    /// Every message read so far, for exporting once the battle is over.
+    pub fn log(&self) -> &BattleLog {
+        &self.log
+    }
+
+    /// The index of the side that won, if the battle is over.
+    /// There is no winner if the player ran away or the connection was lost.
+    pub fn winner(&self) -> Option<usize> {
+        self.winner
+    }
+
+    pub fn sides(&self) -> &[Side; 2] {
+        &self.sides
+    }
+
+    /// Take the sides back out of a finished battle.
+    pub fn into_sides(self) -> [Side; 2] {
+        self.sides
+    }
+
+    /// Draw and update the battle.
+    /// Returns true once the battle has ended.
+    pub fn update(&mut self) -> bool {
+        self.poll_session();
+
+        // the battle waits while the log is open
+        if self.log_open {
+            self.draw_entities();
+            if self
+                .log
+                .update(self.empty_button_texture, Path::new(LOG_DIR))
+            {
+                self.log_open = false;
+            }
+            return false;
+        }
+
+        // health changes wait for the move's animation to finish
+        match self.state {
+            State::Dialogue(_) if self.animation.is_some() => {}
+            State::Dialogue(_) => self.apply_marks(),
+            _ => self.sync_display(),
+        }
+        let delta = get_frame_time();
+        for side in 0..self.display.len() {
+            let view = self.view(side);
+            self.display[side].update(delta, view);
+        }
+        if let Some((ref mut player, _)) = self.animation {
+            player.update(delta);
+        }
+
+        if let State::Handoff(side) = self.state {
+            self.handoff_state(side);
+        } else {
+            self.draw_entities();
+            self.log_button.draw();
+            if self.log_button.clicked() || is_key_pressed(KeyCode::L) {
+                self.log_open = true;
+                return false;
+            }
+        }
+
+        match self.state {
+            State::Dialogue(ref transition_state) => {
+                let state_clone = *transition_state.clone();
+                self.dialogue(state_clone);
+            }
+            State::Wait(side) => {
+                self.wait_state(side);
+            }
+            State::Move(side) => {
+                self.move_state(side);
+            }
+            State::Switch(side) => {
+                self.switch_state(side);
+            }
+            State::Bag(side) => {
+                self.bag_state(side);
+            }
+            State::Handoff(_) => {}
+            State::Opponent => {
+                self.opponent_state();
+            }
+            State::End => {
+                return true;
+            }
+        }
+
+        if self.debounce && self.debounce_step {
+            self.debounce = false;
+            self.debounce_step = false;
+        } else if self.debounce {
+            self.debounce_step = true
+        }
+
+        false
+    }
+
+    /// Read the other player's commands for this turn, if playing over the network.
+    fn poll_session(&mut self) {
+        let session = match self.session {
+            Some(ref mut session) => session,
+            None => return,
+        };
+
+        if let Err(e) = session.poll() {
+            self.lost_connection(e);
+            return;
+        }
+
+        // commands for the next turn wait until this turn's dialogue has been read.
+        if matches!(self.state, State::Dialogue(_) | State::End) {
+            return;
+        }
+
+        let remote = 1 - session.role().side();
+        while self.pending[remote].is_none() {
+            let command = match session.next_command(self.turn) {
+                Some(command) => command,
+                None => break,
+            };
+
+            match command {
+                Command::Move(mv) => self.pending[remote] = Some(Action::Attack(mv)),
+                Command::Switch(index) => {
+                    // a fainted or already active entity could never be picked from the switch menu
+                    let team = &mut self.sides[remote].team;
+                    if !team.can_switch_to(index) || team.set_active(index).is_err() {
+                        self.lost_connection(NetError::Unexpected(crate::net::Message::Command {
+                            turn: self.turn,
+                            command,
+                        }));
+                        return;
+                    }
+                }
+                Command::Forfeit => {
+                    self.text_queue.push_back(format!(
+                        "{} decided that the battle was futile and quit early",
+                        self.sides[remote].name
+                    ));
+                    self.text_queue
+                        .push_back(String::from("You won the battle, congratulations!"));
+                    self.winner = Some(1 - remote);
+                    self.state = State::Dialogue(Box::new(State::End));
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Send a command made on this machine to the other player.
+    fn send_command(&mut self, command: Command) {
+        let result = match self.session {
+            Some(ref mut session) => session.send_command(self.turn, command),
+            None => return,
+        };
+
+        if let Err(e) = result {
+            self.lost_connection(e);
+        }
+    }
+
+    /// End the battle without a winner after a network failure.
+    fn lost_connection(&mut self, error: NetError) {
+        self.session = None;
+        self.text_queue
+            .push_back(format!("The battle was stopped: {}", error));
+        self.winner = None;
+        self.state = State::Dialogue(Box::new(State::End));
+    }
+
+    /// Sides controlled by people sitting at this machine.
+    fn human_sides(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.sides.len()).filter(|&i| self.sides[i].controller == Controller::Human)
+    }
+
+    /// True when more than one person is playing on this machine.
+    fn hot_seat(&self) -> bool {
+        self.human_sides().count() > 1
+    }
+
+    /// The state a side starts its turn in, hiding the screen first when sharing the machine.
+    fn choosing_state(&self, side: usize) -> State {
+        match self.hot_seat() {
+            true => State::Handoff(side),
+            false => State::Wait(side),
+        }
+    }
+
+    /// The state for the first side to choose at the start of a turn.
+    fn turn_start(&self) -> State {
+        match self.human_sides().next() {
+            Some(side) => self.choosing_state(side),
+            None => State::End,
+        }
+    }
+
+    fn draw_entities(&mut self) {
+        let near = self.perspective;
+        let far = 1 - near;
+
+        for (side, layout) in [(near, &NEAR_LAYOUT), (far, &FAR_LAYOUT)] {
+            let ui = match side == near {
+                true => &self.near_ui,
+                false => &self.far_ui,
+            };
+            let display = &mut self.display[side];
+            let entity = match self.sides[side].team.entities.get(display.active) {
+                Some(entity) => entity,
+                None => continue,
+            };
+            if display.sprite.is_none() {
+                display.sprite = Some(AnimatedSprite::new(self.sprites.get(entity.entity_type)));
+            }
+            let display = &self.display[side];
+
+            let sprite = layout.anchor.at(layout.sprite);
+            let (shake, color) = match (display.hit, &self.animation) {
+                (_, Some((player, target))) if *target == side => {
+                    (Vec2::ZERO, player.target_tint())
+                }
+                (Some(hit), _) => (hit.shake(), hit.tint()),
+                _ => (Vec2::ZERO, WHITE),
+            };
+            ui.update(
+                display.health.value(),
+                entity.max_health,
+                EntityImageParams {
+                    texture: &entity.texture,
+                    x: sprite.x + shake.x,
+                    y: sprite.y + shake.y,
+                    color,
+                    sprite: display
+                        .sprite
+                        .as_ref()
+                        .map(|sprite| (sprite, self.view(side))),
+                },
+                format!("{}", entity).as_str(),
+            );
+            if let Some(hit) = display.hit {
+                hit.draw_number(sprite.x + 100.0, sprite.y);
+            }
+        }
+    }
+
+    /// The number the next message pushed onto the queue will have.
+    fn message_count(&self) -> usize {
+        self.messages_shown + self.text_queue.len()
+    }
+
+    /// Remember the health of both sides, to be shown when the dialogue reaches the given message.
+    fn mark_health(&mut self, message: usize) {
+        for side in 0..self.sides.len() {
+            let team = &self.sides[side].team;
+            if let Ok(entity) = team.peek_active() {
+                self.health_marks.push_back(HealthMark {
+                    message,
+                    side,
+                    active: team.get_active_index(),
+                    health: entity.health,
+                });
+            }
+        }
+    }
+
+    /// Show the health and play the sounds marked for every message read so far.
+    fn apply_marks(&mut self) {
+        while let Some(&(message, sound)) = self.sound_marks.front() {
+            if message > self.messages_shown {
+                break;
+            }
+            audio::queue(sound);
+            self.sound_marks.pop_front();
+        }
+        while let Some(mark) = self.health_marks.front() {
+            if mark.message > self.messages_shown {
+                break;
+            }
+            self.display[mark.side].show(mark.active, mark.health);
+            self.health_marks.pop_front();
+        }
+    }
+
+    /// Show the teams as they are, once there is no dialogue left to keep up with.
+    fn sync_display(&mut self) {
+        self.animation = None;
+        self.animation_marks.clear();
+        self.sound_marks.clear();
+        for mark in self.health_marks.drain(..) {
+            self.display[mark.side].show(mark.active, mark.health);
+        }
+        for (display, side) in self.display.iter_mut().zip(self.sides.iter()) {
+            if let Ok(entity) = side.team.peek_active() {
+                display.show(side.team.get_active_index(), entity.health);
+            }
+        }
+    }
+
+    fn handoff_state(&mut self, side: usize) {
+        draw_multiline_text(
+            format!(
+                "Pass the controls to {}.\nClick when you are ready.",
+                self.sides[side].name
+            )
+            .as_str(),
+            50.0,
+            300.0,
+            40.0,
+            Some(1.5),
+            WHITE,
+        );
+
+        if advance_pressed() && !self.debounce {
+            self.perspective = side;
+            self.state = State::Wait(side);
+            self.debounce = true;
+        }
+    }
+
+    fn wait_state(&mut self, side: usize) {
+        self.perspective = side;
+
+        // draw buttons.
+        self.attack_button.draw();
+        self.switch_button.draw();
+        self.forfeit_button.draw();
+        if self.wild {
+            self.recruit_button.draw();
+        }
+        if self.inventory.is_some() {
+            self.bag_button.draw();
+        }
+
+        // check for button presses and change the state accordingly
+        if self.attack_button.clicked() && !self.debounce {
+            self.open_menu(State::Move(side));
+            self.debounce = true;
+        }
+
+        if self.switch_button.clicked() && !self.debounce {
+            self.open_menu(State::Switch(side));
+            self.debounce = true;
+        }
+
+        if self.inventory.is_some() && self.bag_button.clicked() && !self.debounce {
+            self.open_menu(State::Bag(side));
+            self.debounce = true;
+        }
+
+        if self.wild && self.recruit_button.clicked() && !self.debounce {
+            self.debounce = true;
+            self.recruit(side);
+            return;
+        }
+
+        if self.forfeit_button.clicked() && !self.debounce {
+            self.debounce = true;
+            self.state = State::Dialogue(Box::new(State::End));
+
+            // running from a wild entity isn't a loss
+            if self.wild {
+                self.text_queue
+                    .push_back(String::from("You got away safely."));
+                self.winner = None;
+                return;
+            }
+
+            let message = match self.hot_seat() {
+                true => format!(
+                    "{} decided that the battle was futile and quit early",
+                    self.sides[side].name
+                ),
+                false => String::from("You decided that the battle was futile and quit early"),
+            };
+            self.text_queue.push_back(message);
+            self.winner = Some(1 - side);
+            self.send_command(Command::Forfeit);
+        }
+    }
+
+    /// Enter a menu, remembering the current state so it can be cancelled.
+    fn open_menu(&mut self, menu: State) {
+        let from = std::mem::replace(&mut self.state, menu);
+        self.menus.push(from);
+    }
+
+    /// Go back to the state the current menu was opened from.
+    fn close_menu(&mut self, side: usize) {
+        self.state = self.menus.pop().unwrap_or(State::Wait(side));
+    }
+
+    /// Draw the back button for a menu, returns true if it or the back key was pressed.
+    fn back_pressed(&mut self) -> bool {
+        self.back_button.draw();
+
+        let pressed = (self.back_button.clicked() || NavInput::Back.pressed()) && !self.debounce;
+        if pressed {
+            self.debounce = true;
+        }
+        pressed
+    }
+
+    fn move_state(&mut self, side: usize) {
+        if self.back_pressed() {
+            self.close_menu(side);
+            return;
+        }
+
+        let mut chosen_mv: Option<Move> = None;
+        let entity = match self.sides[side].team.peek_active() {
+            Ok(entity) => entity,
+            Err(_) => return,
+        };
+        let target = self.sides[1 - side].team.peek_active().ok();
+
+        for (i, &mv) in entity.get_moves().iter().enumerate() {
+            let mut button = text_menu_button(
+                self.empty_button_texture,
+                mv.to_string(),
+                550.0 + (75.0 * (i as f32)),
+            );
+            button.set_tooltip(move_details(mv, entity, target));
+            if button.clicked() && !self.debounce {
+                self.debounce = true;
+                chosen_mv = Some(mv);
+            }
+            button.draw();
+        }
+
+        if let Some(mv) = chosen_mv {
+            self.pending[side] = Some(Action::Attack(mv));
+            self.send_command(Command::Move(mv));
+            if self.state == State::Move(side) {
+                self.next_chooser();
+            }
+        }
+    }
+
+    /// Try to recruit the wild entity, the chance of success rises as its health falls.
+    /// A failed attempt uses up the side's turn.
+    fn recruit(&mut self, side: usize) {
+        let target = 1 - side;
+        let mut chance = match self.sides[target].team.get_active() {
+            Ok(entity) => entity.recruit_chance(),
+            Err(_) => return,
+        };
+
+        let name = self.sides[side].name.clone();
+        let entity = match self.sides[target].team.get_active() {
+            Ok(entity) => entity,
+            Err(_) => return,
+        };
+        self.text_queue
+            .push_back(format!("{} forked {}'s repo...", name, entity));
+
+        // the best recruit tool carried is used up on every attempt
+        if let (Some(inventory), Some(catalog)) = (self.inventory.as_mut(), self.items) {
+            if let Some((tool, bonus)) = inventory.best_recruit_tool(catalog) {
+                if inventory.remove(&tool.id, 1).is_ok() {
+                    self.text_queue
+                        .push_back(format!("...using a {} to help it along...", tool.name));
+                    chance = (chance + bonus).min(MAX_RECRUIT_CHANCE);
+                }
+            }
+        }
+
+        if !self.rng.gen_bool(chance) {
+            self.text_queue
+                .push_back(String::from("...but the fork failed to build!"));
+            self.pending[side] = Some(Action::Pass);
+            self.next_chooser();
+            return;
+        }
+
+        self.text_queue
+            .push_back(format!("{} was recruited!", entity));
+        let index = self.sides[target].team.get_active_index();
+        self.recruited = self.sides[target].team.remove(index).ok();
+        self.winner = Some(side);
+        self.state = State::Dialogue(Box::new(State::End));
+    }
+
+    /// List the items that can be used in battle, using one takes the side's turn.
+    fn bag_state(&mut self, side: usize) {
+        if self.back_pressed() || self.inventory.is_none() {
+            self.close_menu(side);
+            return;
+        }
+        let (inventory, catalog) = match (self.inventory.as_mut(), self.items) {
+            (Some(inventory), Some(catalog)) => (inventory, catalog),
+            _ => return,
+        };
+
+        let usable = inventory
+            .items()
+            .iter()
+            .filter_map(|stack| Some((catalog.get(&stack.id)?, stack.count)))
+            .filter(|(item, _)| matches!(item.effect, Effect::Heal(_) | Effect::Cure));
+
+        let mut chosen = None;
+        for (i, (item, count)) in usable.enumerate() {
+            let mut button = text_menu_button(
+                self.empty_button_texture,
+                format!("{} x{}", item.name, count),
+                525.0 + (75.0 * (i as f32)),
+            );
+            if button.clicked() && !self.debounce {
+                self.debounce = true;
+                chosen = Some(item);
+            }
+            button.draw();
+        }
+
+        let item = match chosen {
+            Some(item) => item,
+            None => return,
+        };
+        let entity = match self.sides[side].team.get_active() {
+            Ok(entity) => entity,
+            Err(_) => return,
+        };
+
+        match item.use_on(entity) {
+            Ok(message) => {
+                let _ = inventory.remove(&item.id, 1);
+                self.text_queue
+                    .push_back(format!("{} used a {}.", self.sides[side].name, item.name));
+                self.text_queue.push_back(message);
+                self.mark_health(self.message_count() - 1);
+                self.pending[side] = Some(Action::Pass);
+                self.next_chooser();
+            }
+            Err(error) => {
+                self.text_queue.push_back(format!("{}.", error));
+                self.state = State::Dialogue(Box::new(State::Bag(side)));
+            }
+        }
+    }
+
+    /// Hand the turn to the next side still to choose, or resolve the turn once everyone has.
+    fn next_chooser(&mut self) {
+        self.menus.clear();
+        let waiting = self.human_sides().find(|&i| self.pending[i].is_none());
+
+        match waiting {
+            Some(side) => self.state = self.choosing_state(side),
+            None => self.opponent_state(),
+        }
+    }
+
+    /// Wait for the other player's move, then resolve the turn.
+    fn opponent_state(&mut self) {
+        let waiting = (0..self.sides.len())
+            .any(|i| self.sides[i].controller == Controller::Remote && self.pending[i].is_none());
+
+        if !waiting {
+            self.resolve_turn();
+            return;
+        }
+
+        self.state = State::Opponent;
+        let position = Anchor::TopRight.at(vec2(MENU_X, 640.0));
+        draw_text(
+            "Waiting for the other player...",
+            position.x,
+            position.y,
+            30.0,
+            WHITE,
+        );
+    }
+
+    fn resolve_turn(&mut self) {
+        for i in 0..self.sides.len() {
+            let action = match self.pending[i].take() {
+                Some(action) => action,
+                None => {
+                    let (side, target) = (&self.sides[i], &self.sides[1 - i]);
+                    match (side.team.peek_active(), target.team.peek_active()) {
+                        (Ok(entity), Ok(target)) => Action::Attack(crate::choose_move(
+                            side.strategy,
+                            entity,
+                            target,
+                            &mut self.rng,
+                        )),
+                        _ => continue,
+                    }
+                }
+            };
+
+            let human = self.sides[i].controller == Controller::Human;
+            let entity = match self.sides[i].team.get_active() {
+                Ok(entity) => entity,
+                Err(_) => continue,
+            };
+
+            // only an order to attack can be ignored, a turn spent on an item gave none
+            let attacking = matches!(action, Action::Attack(_));
+            let disobeys = self
+                .progression
+                .as_ref()
+                .is_some_and(|progression| !progression.obeys(entity));
+            if human && attacking && disobeys && self.rng.gen_bool(DISOBEY_CHANCE) {
+                self.text_queue.push_back(format!(
+                    "{} ignored your orders and went to read the news instead.",
+                    entity
+                ));
+                entity.clear_move();
+                continue;
+            }
+
+            match action {
+                Action::Attack(mv) => entity.queue_move(mv),
+                Action::Pass => entity.clear_move(),
+            }
+        }
+
+        // show the damage from each move along with the last message about it
+        let mut moves: Vec<MoveOutcome> = Vec::new();
+        let (first, second) = self.sides.split_at_mut(1);
+        if let (Ok(a), Ok(b)) = (first[0].team.get_active(), second[0].team.get_active()) {
+            let mut start = self.text_queue.len();
+            let mut before = [a.health, b.health];
+            crate::execute_moves_with(
+                a,
+                b,
+                &mut self.text_queue,
+                &mut self.rng,
+                |mover, a, b, text| {
+                    let (user, target) = ([a, b][mover], [a, b][1 - mover]);
+                    let health = [a.health, b.health];
+                    let mv = user.queued_move();
+                    let missed = text.back() == Some(&format!("{} missed", user));
+                    let sound = match mv {
+                        _ if missed => Some(SoundEffect::Miss),
+                        Some(mv) if health[1 - mover] < before[1 - mover] => {
+                            match mv.effectiveness(target) {
+                                Effectiveness::Super => Some(SoundEffect::SuperEffective),
+                                _ => Some(SoundEffect::Hit),
+                            }
+                        }
+                        _ => None,
+                    };
+                    moves.push(MoveOutcome {
+                        mover,
+                        mv,
+                        start,
+                        end: text.len(),
+                        health,
+                        sound,
+                    });
+                    start = text.len();
+                    before = health;
+                },
+            );
+        }
+        for outcome in moves {
+            // the first message a move adds says it was used
+            if let (Some(mv), true) = (outcome.mv, outcome.end > outcome.start) {
+                self.animation_marks.push_back(AnimationMark {
+                    message: self.messages_shown + outcome.start,
+                    attacker: outcome.mover,
+                    mv,
+                });
+            }
+            let message = (self.messages_shown + outcome.end)
+                .saturating_sub(1)
+                .max(self.messages_shown);
+            for (side, health) in outcome.health.into_iter().enumerate() {
+                self.health_marks.push_back(HealthMark {
+                    message,
+                    side,
+                    active: self.sides[side].team.get_active_index(),
+                    health,
+                });
+            }
+            if let Some(sound) = outcome.sound {
+                self.sound_marks.push_back((message, sound));
+            }
+        }
+
+        self.state = State::Dialogue(Box::new(self.turn_start()));
+
+        for i in 0..self.sides.len() {
+            if self.check_fainted(i) {
+                break;
+            }
+        }
+        self.finish_turn();
+    }
+
+    /// Move on to the next turn, comparing states with the other player first.
+    fn finish_turn(&mut self) {
+        let hash = crate::net::state_hash([&self.sides[0].team, &self.sides[1].team]);
+        let result = match self.session {
+            Some(ref mut session) => session.submit_hash(self.turn, hash),
+            None => Ok(()),
+        };
+        self.turn += 1;
+
+        if let Err(e) = result {
+            self.lost_connection(e);
+        }
+    }
+
+    /// Report a fainted active entity for the given side and bring in the next one.
+    /// Returns true if the side has no entities left and the battle is over.
+    fn check_fainted(&mut self, side: usize) -> bool {
+        let name = self.sides[side].name.clone();
+        let (entity, reward) = match self.sides[side].team.get_active() {
+            Ok(entity) if entity.health == 0 => (format!("{}", entity), entity.xp_reward()),
+            _ => return false,
+        };
+
+        let message = match self.wild && side == 1 {
+            true => format!("The wild {} has fallen.", entity),
+            false => format!("{}'s {} has fallen.", name, entity),
+        };
+        self.sound_marks
+            .push_back((self.message_count(), SoundEffect::Faint));
+        self.text_queue.push_back(message);
+
+        let winner = 1 - side;
+        if self.experience && self.sides[winner].controller == Controller::Human {
+            if let Ok(victor) = self.sides[winner].team.get_active() {
+                let levels = victor.gain_xp(reward);
+                self.text_queue
+                    .push_back(format!("{} gained {} XP.", victor, reward));
+                if levels > 0 {
+                    self.text_queue
+                        .push_back(format!("{} grew to level {}!", victor, victor.level));
+                }
+            }
+        }
+
+        if !crate::active_died(&mut self.sides[side].team) {
+            // the next entity comes out once everything about the last one has been read
+            self.mark_health(self.message_count());
+            return false;
+        }
+
+        let message = match (self.hot_seat(), self.sides[winner].controller) {
+            (true, _) => format!(
+                "{} won the battle, congratulations!",
+                self.sides[winner].name
+            ),
+            (false, Controller::Human) => String::from("You won the battle, congratulations!"),
+            (false, _) => String::from("You lost the battle"),
+        };
+        self.text_queue.push_back(message);
+        self.winner = Some(winner);
+        self.state = State::Dialogue(Box::new(State::End));
+        true
+    }
+
+    fn dialogue(&mut self, transition_state: State) {
+        if let Some((ref mut player, _)) = self.animation {
+            player.draw();
+            if advance_pressed() || NavInput::Back.pressed() {
+                player.skip();
+            }
+            if player.finished() {
+                self.animation = None;
+            }
+            return;
+        }
+
+        let s = match self.text_queue.front() {
+            Some(s) => s,
+            None => {
+                self.state = transition_state;
+                return;
+            }
+        };
+        if self.dialogue_box.update(s) {
+            self.log.push(self.turn, s);
+            self.text_queue.pop_front();
+            self.start_animation(self.messages_shown);
+            self.messages_shown += 1;
+        }
+    }
+
+    /// Play the animation for a move if the message just read said it was used.
+    fn start_animation(&mut self, message: usize) {
+        while let Some(mark) = self.animation_marks.front() {
+            if mark.message > message {
+                return;
+            }
+            let mark = self.animation_marks.pop_front().unwrap();
+            if mark.message < message {
+                continue;
+            }
+            self.display[mark.attacker].play(SpriteAction::Attack);
+            if !self.play_animations {
+                continue;
+            }
+            if let Some(animation) = self.animations.get(mark.mv) {
+                let target = 1 - mark.attacker;
+                let player = AnimationPlayer::new(
+                    animation,
+                    self.sprite_centre(mark.attacker),
+                    self.sprite_centre(target),
+                );
+                self.animation = Some((player, target));
+            }
+        }
+    }
+
+    /// The middle of the sprite drawn for a side.
+    fn sprite_centre(&self, side: usize) -> Vec2 {
+        let layout = match side == self.perspective {
+            true => &NEAR_LAYOUT,
+            false => &FAR_LAYOUT,
+        };
+        let display = &self.display[side];
+        let texture = self.sides[side]
+            .team
+            .entities
+            .get(display.active)
+            .and_then(|entity| entity.texture.as_ref());
+        let size = match (texture, &display.sprite) {
+            (Some(texture), Some(sprite)) => sprite.frame(self.view(side), texture).0.size(),
+            (Some(texture), None) => texture.size(),
+            (None, _) => Vec2::ZERO,
+        };
+        layout.anchor.at(layout.sprite) + size / 2.0
+    }
+
+    /// The near side is seen from behind and the far side from the front.
+    fn view(&self, side: usize) -> View {
+        match side == self.perspective {
+            true => View::Back,
+            false => View::Front,
+        }
+    }
+
+    fn switch_state(&mut self, side: usize) {
+        if self.back_pressed() {
+            self.close_menu(side);
+            return;
+        }
+
+        let team = &mut self.sides[side].team;
+        let mut switched: Option<usize> = None;
+        for i in 0..team.entities.len() {
+            let entity = &team.entities[i];
+            let mut button = text_menu_button(
+                self.empty_button_texture,
+                entity.to_string(),
+                600.0 + (75.0 * (i as f32)),
+            );
+            // fainted entities stay in the list so the team keeps its order
+            button.set_disabled(!team.can_switch_to(i));
+            button.set_tooltip(switch_details(entity));
+            if button.clicked() && !self.debounce {
+                team.set_active(i).unwrap();
+                self.debounce = true;
+                switched = Some(i);
+            }
+            button.draw();
+        }
+
+        if let Some(index) = switched {
+            self.close_menu(side);
+            self.send_command(Command::Switch(index));
+        }
+    }
+}
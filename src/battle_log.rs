@@ -0,0 +1,241 @@
+use crate::layout::{self, Anchor};
+use crate::ui::{Button, NavInput};
+
+use macroquad::prelude::*;
+
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A message read during a battle and the turn it came up on, 0 for before the first turn.
+#[derive(PartialEq, Clone, Debug)]
+pub struct LogEntry {
+    pub turn: u32,
+    pub text: String,
+}
+
+/// Buttons along the top of the log panel.
+#[derive(PartialEq, Clone, Copy)]
+enum LogAction {
+    Export,
+    Close,
+}
+
+/// Every message read in the current battle, shown in a panel that can be scrolled back through.
+#[derive(Default)]
+pub struct BattleLog {
+    entries: Vec<LogEntry>,
+    /// Lines scrolled up from the newest one.
+    scroll: usize,
+    /// Where the log was last exported, or why it couldn't be.
+    status: Option<Result<PathBuf, String>>,
+}
+
+impl BattleLog {
+    const TOP: f32 = 150.0;
+    const LINE_HEIGHT: f32 = 35.0;
+    const SCROLL_KEYS: [(KeyCode, i32); 4] = [
+        (KeyCode::Up, 1),
+        (KeyCode::Down, -1),
+        (KeyCode::PageUp, 10),
+        (KeyCode::PageDown, -10),
+    ];
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, turn: u32, text: &str) {
+        self.entries.push(LogEntry {
+            turn,
+            text: String::from(text),
+        });
+    }
+
+    pub fn entries(&self) -> &[LogEntry] {
+        &self.entries
+    }
+
+    /// The log as lines of text, with a heading whenever the turn changes.
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        let mut turn = None;
+        for entry in self.entries.iter() {
+            if turn != Some(entry.turn) {
+                turn = Some(entry.turn);
+                lines.push(match entry.turn {
+                    0 => String::from("Start"),
+                    turn => format!("Turn {}", turn),
+                });
+            }
+            let text = entry
+                .text
+                .split_whitespace()
+                .collect::<Vec<&str>>()
+                .join(" ");
+            lines.push(format!("  {}", text));
+        }
+        lines
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = self.lines().join("\n");
+        text.push('\n');
+        text
+    }
+
+    /// Write the log to a new file in a directory, returning the file's path.
+    /// Files are never overwritten, a second export in the same second gets a numbered name.
+    pub fn export(&self, dir: impl AsRef<Path>) -> io::Result<PathBuf> {
+        let seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_secs());
+        fs::create_dir_all(dir.as_ref())?;
+        for n in 1.. {
+            let name = match n {
+                1 => format!("battle-{}.txt", seconds),
+                n => format!("battle-{}-{}.txt", seconds, n),
+            };
+            let path = dir.as_ref().join(name);
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(mut file) => {
+                    file.write_all(self.to_text().as_bytes())?;
+                    return Ok(path);
+                }
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e),
+            }
+        }
+        unreachable!()
+    }
+
+    /// Scroll up through older lines, or down with a negative amount.
+    fn scroll_by(&mut self, lines: i32, visible: usize) {
+        let max = self.lines().len().saturating_sub(visible);
+        self.scroll = self.scroll.saturating_add_signed(lines as isize).min(max);
+    }
+
+    /// Draw the panel over the battle and handle scrolling, exporting and closing.
+    /// Returns true once the player closes it.
+    pub fn update(&mut self, empty_button_texture: &Texture2D, export_dir: &Path) -> bool {
+        let bottom = layout::height() - 250.0;
+        let visible = ((bottom - Self::TOP) / Self::LINE_HEIGHT).max(1.0) as usize;
+
+        let wheel = mouse_wheel().1;
+        if wheel != 0.0 {
+            self.scroll_by(wheel.signum() as i32 * 3, visible);
+        }
+        for (key, lines) in Self::SCROLL_KEYS {
+            if is_key_pressed(key) {
+                self.scroll_by(lines, visible);
+            }
+        }
+
+        draw_rectangle(
+            50.0,
+            50.0,
+            layout::width() - 100.0,
+            bottom,
+            Color::new(0.0, 0.0, 0.0, 0.9),
+        );
+        draw_text("Battle log", 100.0, 110.0, 40.0, WHITE);
+
+        let lines = self.lines();
+        let end = lines.len() - self.scroll.min(lines.len());
+        let start = end.saturating_sub(visible);
+        for (i, line) in lines[start..end].iter().enumerate() {
+            let color = match line.starts_with(' ') {
+                true => WHITE,
+                false => GOLD,
+            };
+            draw_text(
+                line.as_str(),
+                100.0,
+                Self::TOP + 30.0 + Self::LINE_HEIGHT * i as f32,
+                30.0,
+                color,
+            );
+        }
+        if self.scroll > 0 {
+            draw_text("(scrolled back)", 400.0, 110.0, 30.0, GRAY);
+        }
+
+        let status = match self.status {
+            Some(Ok(ref path)) => Some((format!("Saved to {}", path.display()), WHITE)),
+            Some(Err(ref error)) => Some((format!("Could not save the log: {}", error), RED)),
+            None => None,
+        };
+        if let Some((text, color)) = status {
+            draw_text(text.as_str(), 100.0, bottom + 30.0, 30.0, color);
+        }
+
+        let actions = [(LogAction::Export, "Export"), (LogAction::Close, "Close")];
+        let mut clicked = None;
+        for (i, (action, label)) in actions.into_iter().enumerate() {
+            let offset = vec2(-750.0 + 350.0 * i as f32, 70.0);
+            let mut button =
+                Button::text_anchored(empty_button_texture, label, Anchor::TopRight, offset);
+            if button.clicked() {
+                clicked = Some(action);
+            }
+            button.draw();
+        }
+        if NavInput::Back.pressed() || is_key_pressed(KeyCode::L) {
+            clicked = Some(LogAction::Close);
+        }
+
+        match clicked {
+            Some(LogAction::Export) => {
+                self.status = Some(self.export(export_dir).map_err(|e| e.to_string()));
+                false
+            }
+            Some(LogAction::Close) => {
+                self.scroll = 0;
+                self.status = None;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines_by_turn() {
+        let mut log = BattleLog::new();
+        log.push(0, "A wild Go appeared!");
+        log.push(1, "Rust used Deadline...");
+        log.push(1, "Go took\ndamage");
+        log.push(2, "Go has fallen.");
+
+        assert_eq!(
+            log.to_text(),
+            "Start\n  A wild Go appeared!\nTurn 1\n  Rust used Deadline...\n  Go took damage\nTurn 2\n  Go has fallen.\n"
+        );
+
+        // scrolling stops at the oldest line
+        log.scroll_by(10, 4);
+        assert_eq!(log.scroll, 3);
+        log.scroll_by(-1, 4);
+        assert_eq!(log.scroll, 2);
+        log.scroll_by(-10, 4);
+        assert_eq!(log.scroll, 0);
+    }
+
+    #[test]
+    fn exports_never_overwrite() {
+        let dir = std::env::temp_dir().join(format!("battle-logs-{}", std::process::id()));
+        let mut log = BattleLog::new();
+        log.push(0, "A wild Go appeared!");
+
+        let first = log.export(&dir).unwrap();
+        let second = log.export(&dir).unwrap();
+        assert_ne!(first, second);
+        assert_eq!(fs::read_to_string(&second).unwrap(), log.to_text());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
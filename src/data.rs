@@ -0,0 +1,283 @@
+use macroquad::color::Color;
+
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+#[derive(Debug)]
+pub enum DataError {
+    Io {
+        path: String,
+        error: std::io::Error,
+    },
+    Syntax {
+        line: usize,
+        message: String,
+    },
+    MissingSection(String),
+    Missing {
+        section: String,
+        key: String,
+    },
+    Invalid {
+        line: usize,
+        key: String,
+        value: String,
+    },
+}
+
+impl fmt::Display for DataError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DataError::Io { path, error } => write!(f, "could not read {}: {}", path, error),
+            DataError::Syntax { line, message } => write!(f, "line {}: {}", line, message),
+            DataError::MissingSection(section) => write!(f, "the [{}] section is missing", section),
+            DataError::Missing { section, key } => {
+                write!(f, "[{}] is missing the key '{}'", section, key)
+            }
+            DataError::Invalid { line, key, value } => {
+                write!(f, "line {}: '{}' is not a valid {}", line, value, key)
+            }
+        }
+    }
+}
+
+impl std::error::Error for DataError {}
+
+/// A `[section]` and everything under it.
+#[derive(Clone, Debug)]
+pub struct Section {
+    pub name: String,
+    /// Line number of the section header, used in error messages.
+    pub line: usize,
+    entries: Vec<(String, String, usize)>,
+    pub lines: Vec<String>,
+}
+
+impl Section {
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|(k, _, _)| k == key)
+            .map(|(_, value, _)| value.as_str())
+    }
+
+    /// Every value for a key that appears more than once, in order.
+    pub fn get_all(&self, key: &str) -> Vec<&str> {
+        self.entries
+            .iter()
+            .filter(|(k, _, _)| k == key)
+            .map(|(_, value, _)| value.as_str())
+            .collect()
+    }
+
+    /// Get a value that has to be present.
+    pub fn require(&self, key: &str) -> Result<&str, DataError> {
+        self.get(key).ok_or_else(|| DataError::Missing {
+            section: self.name.clone(),
+            key: String::from(key),
+        })
+    }
+
+    /// Parse a value that has to be present.
+    pub fn parse<T: FromStr>(&self, key: &str) -> Result<T, DataError> {
+        let value = self.require(key)?;
+        value.parse().map_err(|_| self.invalid(key, value))
+    }
+
+    /// Parse a value, using a default if it isn't present.
+    pub fn parse_or<T: FromStr>(&self, key: &str, default: T) -> Result<T, DataError> {
+        match self.get(key) {
+            Some(value) => value.parse().map_err(|_| self.invalid(key, value)),
+            None => Ok(default),
+        }
+    }
+
+    /// Parse a comma separated list of values.
+    pub fn parse_list<T: FromStr>(&self, key: &str) -> Result<Vec<T>, DataError> {
+        match self.get(key) {
+            Some(value) => value
+                .split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(|item| item.parse().map_err(|_| self.invalid(key, item)))
+                .collect(),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Parse a colour written as `#rrggbb` or `#rrggbbaa`, using a default if it isn't present.
+    pub fn color_or(&self, key: &str, default: Color) -> Result<Color, DataError> {
+        match self.get(key) {
+            Some(value) => parse_color(value).map_err(|_| self.invalid(key, value)),
+            None => Ok(default),
+        }
+    }
+
+    /// Build an error for a value that couldn't be used.
+    pub fn invalid(&self, key: &str, value: &str) -> DataError {
+        let line = self
+            .entries
+            .iter()
+            .find(|(k, _, _)| k == key)
+            .map(|(_, _, line)| *line)
+            .unwrap_or(self.line);
+        DataError::Invalid {
+            line,
+            key: String::from(key),
+            value: String::from(value),
+        }
+    }
+}
+
+/// Parse a colour written as `#rrggbb`, or `#rrggbbaa` with an alpha.
+pub(crate) fn parse_color(s: &str) -> Result<Color, ()> {
+    let hex = s.strip_prefix('#').ok_or(())?;
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(());
+    }
+    let value = u32::from_str_radix(hex, 16).map_err(|_| ())?;
+    match hex.len() {
+        6 => Ok(Color::from_hex(value)),
+        8 => Ok(Color::from_rgba(
+            (value >> 24) as u8,
+            (value >> 16) as u8,
+            (value >> 8) as u8,
+            value as u8,
+        )),
+        _ => Err(()),
+    }
+}
+
+/// A parsed data file.
+/// Data files are a list of `[section]` headers, the lines in a section are either
+/// `key = value` entries or raw lines such as the rows of a map. Lines starting with `;` are comments.
+#[derive(Clone, Debug, Default)]
+pub struct Document {
+    pub sections: Vec<Section>,
+}
+
+impl Document {
+    pub fn parse(text: &str) -> Result<Self, DataError> {
+        let mut sections: Vec<Section> = Vec::new();
+
+        for (i, raw) in text.lines().enumerate() {
+            let line_number = i + 1;
+            let line = raw.trim_end();
+            let trimmed = line.trim_start();
+
+            if trimmed.is_empty() || trimmed.starts_with(';') {
+                continue;
+            }
+
+            if trimmed.starts_with('[') && trimmed.ends_with(']') {
+                sections.push(Section {
+                    name: String::from(trimmed[1..trimmed.len() - 1].trim()),
+                    line: line_number,
+                    entries: Vec::new(),
+                    lines: Vec::new(),
+                });
+                continue;
+            }
+
+            let section = sections.last_mut().ok_or_else(|| DataError::Syntax {
+                line: line_number,
+                message: String::from("expected a [section] header first"),
+            })?;
+
+            match trimmed.split_once('=') {
+                Some((key, value)) => section.entries.push((
+                    String::from(key.trim()),
+                    String::from(value.trim()),
+                    line_number,
+                )),
+                None => section.lines.push(String::from(line)),
+            }
+        }
+
+        Ok(Self { sections })
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, DataError> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path).map_err(|error| DataError::Io {
+            path: path.display().to_string(),
+            error,
+        })?;
+        Self::parse(&text)
+    }
+
+    /// The first section with the given name.
+    pub fn section(&self, name: &str) -> Option<&Section> {
+        self.sections.iter().find(|section| section.name == name)
+    }
+
+    /// The first section with the given name, which has to be present.
+    pub fn require_section(&self, name: &str) -> Result<&Section, DataError> {
+        self.section(name)
+            .ok_or_else(|| DataError::MissingSection(String::from(name)))
+    }
+
+    /// Every section with the given name.
+    pub fn sections_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Section> {
+        self.sections
+            .iter()
+            .filter(move |section| section.name == name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEXT: &str = "
+; a comment
+[map]
+name = Compiler Town
+size = 3
+
+[tiles]
+#.#
+#H#
+
+[door]
+to = route
+line = Hello
+line = Bye
+[door]
+to = lab
+";
+
+    #[test]
+    fn parse_sections() {
+        let document = Document::parse(TEXT).unwrap();
+        let map = document.section("map").unwrap();
+        assert_eq!(map.get("name"), Some("Compiler Town"));
+        assert_eq!(map.parse::<u32>("size").unwrap(), 3);
+        assert_eq!(map.parse_or::<u32>("missing", 7).unwrap(), 7);
+        assert_eq!(document.section("tiles").unwrap().lines, vec!["#.#", "#H#"]);
+        assert_eq!(document.sections_named("door").count(), 2);
+        assert_eq!(
+            document.section("door").unwrap().get_all("line"),
+            vec!["Hello", "Bye"]
+        );
+    }
+
+    #[test]
+    fn errors_point_at_lines() {
+        let document = Document::parse(TEXT).unwrap();
+        let map = document.section("map").unwrap();
+        assert!(matches!(
+            map.parse::<u32>("name"),
+            Err(DataError::Invalid { line: 4, .. })
+        ));
+        assert!(matches!(
+            map.require("width"),
+            Err(DataError::Missing { .. })
+        ));
+        assert!(matches!(
+            Document::parse("key = value"),
+            Err(DataError::Syntax { line: 1, .. })
+        ));
+    }
+}
@@ -1,4 +1,5 @@
 use std::fmt;
+use std::str::FromStr;
 
 mod cpp_entity;
 pub use cpp_entity::CppEntity;
@@ -28,7 +29,7 @@ pub trait EntityBuilder {
     fn build(level: u32, texture: Option<Texture2D>) -> Entity;
 }
 
-#[derive(PartialEq, Clone, Debug)]
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Debug)]
 pub enum EntityType {
     Rust,
     Cpp,
@@ -37,6 +38,63 @@ pub enum EntityType {
     Go,
 }
 
+impl EntityType {
+    /// Every entity type, in the order they are offered to players.
+    pub const ALL: [EntityType; 5] = [
+        EntityType::Rust,
+        EntityType::Cpp,
+        EntityType::Python,
+        EntityType::Js,
+        EntityType::Go,
+    ];
+
+    /// Build an entity of this type using its builder.
+    pub fn build(&self, level: u32, texture: Option<Texture2D>) -> Entity {
+        match self {
+            EntityType::Rust => RustEntity::build(level, texture),
+            EntityType::Cpp => CppEntity::build(level, texture),
+            EntityType::Python => PythonEntity::build(level, texture),
+            EntityType::Js => JsEntity::build(level, texture),
+            EntityType::Go => GoEntity::build(level, texture),
+        }
+    }
+
+    /// Key of this type's sprite in the asset manifest.
+    pub fn texture_key(&self) -> &'static str {
+        match self {
+            EntityType::Rust => "entity/rust",
+            EntityType::Cpp => "entity/cpp",
+            EntityType::Python => "entity/python",
+            EntityType::Js => "entity/js",
+            EntityType::Go => "entity/go",
+        }
+    }
+}
+
+/// Parse an entity type from its identifier, such as `Rust` or `Cpp`.
+impl FromStr for EntityType {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        EntityType::ALL
+            .into_iter()
+            .find(|entity_type| format!("{:?}", entity_type) == s)
+            .ok_or(())
+    }
+}
+
+impl fmt::Display for EntityType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EntityType::Rust => write!(f, "Rust"),
+            EntityType::Cpp => write!(f, "C++"),
+            EntityType::Python => write!(f, "Python"),
+            EntityType::Js => write!(f, "JavaScript"),
+            EntityType::Go => write!(f, "Go"),
+        }
+    }
+}
+
 /// holds stats for entities for battles.
 #[derive(Clone)]
 pub struct Entity {
@@ -44,6 +102,8 @@ pub struct Entity {
     pub max_health: u32,
     pub entity_type: EntityType,
     pub level: u32,
+    /// Experience towards the next level.
+    pub xp: u32,
     pub attack: u32,
     pub defense: u32,
     pub accuracy: u32,
@@ -69,17 +129,15 @@ pub enum Stat {
 
 impl fmt::Display for Entity {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self.entity_type {
-            EntityType::Rust => write!(f, "Rust"),
-            EntityType::Cpp => write!(f, "C++"),
-            EntityType::Python => write!(f, "Python"),
-            EntityType::Js => write!(f, "JavaScript"),
-            EntityType::Go => write!(f, "Go"),
-        }
+        write!(f, "{}", self.entity_type)
     }
 }
 
 impl Entity {
+    /// The most moves an entity can know.
+    pub const MAX_MOVES: usize = 4;
+
+    #[allow(clippy::too_many_arguments)]
     fn new(
         entity_type: EntityType,
         max_health: u32,
@@ -98,6 +156,7 @@ impl Entity {
             health: max_health,
             max_health,
             level,
+            xp: 0,
             attack,
             defense,
             accuracy,
@@ -115,8 +174,8 @@ impl Entity {
         defended_damage as u32
     }
 
-    fn accuracy_roll(&self) -> bool {
-        rand::thread_rng().gen_ratio(self.accuracy, 100)
+    fn accuracy_roll(&self, rng: &mut impl Rng) -> bool {
+        rng.gen_ratio(self.accuracy, 100)
     }
 
     pub fn change_stat(&mut self, stat: Stat, amount: i32) {
@@ -139,6 +198,26 @@ impl Entity {
         *stat_to_change = final_amount as u32;
     }
 
+    /// Undo every stat change, returns false if there were none.
+    pub fn reset_stats(&mut self) -> bool {
+        let base = self.entity_type.build(self.level, None);
+        let changed = (self.attack, self.defense, self.accuracy)
+            != (base.attack, base.defense, base.accuracy);
+        self.attack = base.attack;
+        self.defense = base.defense;
+        self.accuracy = base.accuracy;
+        changed
+    }
+
+    /// Learn a new move, returns false if it is already known or there is no room for it.
+    pub fn learn_move(&mut self, mv: Move) -> bool {
+        if self.moves.contains(&mv) || self.moves.len() >= Self::MAX_MOVES {
+            return false;
+        }
+        self.moves.push(mv);
+        true
+    }
+
     pub fn queue_move(&mut self, mv: Move) {
         self.queued_move = Some(MoveData {
             move_type: mv,
@@ -146,6 +225,16 @@ impl Entity {
         });
     }
 
+    /// Forget the queued move, for turns spent doing something other than attacking.
+    pub fn clear_move(&mut self) {
+        self.queued_move = None;
+    }
+
+    /// The move the entity will use this turn, if any.
+    pub fn queued_move(&self) -> Option<Move> {
+        Some(self.queued_move?.move_type)
+    }
+
     pub fn get_move_priority(&self) -> Option<u8> {
         Some(self.queued_move?.priority)
     }
@@ -177,7 +266,12 @@ impl Entity {
         }
     }
 
-    pub fn execute_move(&mut self, target: &mut Entity, text_queue: &mut VecDeque<String>) {
+    pub fn execute_move(
+        &mut self,
+        target: &mut Entity,
+        text_queue: &mut VecDeque<String>,
+        rng: &mut impl Rng,
+    ) {
         // get the move from the queue
         let mv = match self.queued_move {
             Some(mv) => mv.move_type,
@@ -187,7 +281,7 @@ impl Entity {
         text_queue.push_back(format!("{} used {}...", self, mv));
 
         // roll to check if a miss occured,
-        if !self.accuracy_roll() {
+        if !self.accuracy_roll(rng) {
             text_queue.push_back(format!("{} missed", self));
             return;
         }
@@ -208,6 +302,43 @@ impl Entity {
         );
     }
 
+    /// Chance between 0 and 1 that forking this entity's repo recruits it.
+    /// Entities on low health are much easier to recruit.
+    pub fn recruit_chance(&self) -> f64 {
+        let missing = 1.0 - self.health as f64 / self.max_health.max(1) as f64;
+        0.1 + 0.8 * missing
+    }
+
+    /// Experience needed to reach the next level.
+    pub fn xp_to_next_level(&self) -> u32 {
+        50 * (self.level + 1)
+    }
+
+    /// Experience given to whoever knocks this entity out.
+    pub fn xp_reward(&self) -> u32 {
+        20 + 10 * self.level
+    }
+
+    /// Gain experience, returns the number of levels gained.
+    pub fn gain_xp(&mut self, amount: u32) -> u32 {
+        self.xp += amount;
+        let mut levels = 0;
+        while self.xp >= self.xp_to_next_level() {
+            self.xp -= self.xp_to_next_level();
+            self.level += 1;
+            levels += 1;
+        }
+        levels
+    }
+
+    /// Replace the moves the entity knows, used for trainers with a custom moveset.
+    /// An empty list keeps the current moves since an entity always needs one.
+    pub fn set_moves(&mut self, moves: Vec<Move>) {
+        if !moves.is_empty() {
+            self.moves = moves;
+        }
+    }
+
     pub fn get_moves(&self) -> &Vec<Move> {
         &self.moves
     }
@@ -229,4 +360,36 @@ mod tests {
         assert_eq!(format!("{}", RustEntity::build(0, None)), "Rust");
         assert_eq!(format!("{}", CppEntity::build(0, None)), "C++");
     }
+
+    #[test]
+    fn build_from_type() {
+        for entity_type in EntityType::ALL {
+            assert_eq!(entity_type.build(0, None).entity_type, entity_type);
+        }
+    }
+
+    #[test]
+    fn recruit_chance_rises_as_health_falls() {
+        let mut entity = GoEntity::build(0, None);
+        let full = entity.recruit_chance();
+        entity.health = entity.max_health / 2;
+        let half = entity.recruit_chance();
+        entity.health = 1;
+        let low = entity.recruit_chance();
+
+        assert!(full < half && half < low);
+        assert!(full > 0.0 && low < 1.0);
+    }
+
+    #[test]
+    fn gain_xp_levels_up() {
+        let mut entity = GoEntity::build(1, None);
+        assert_eq!(entity.gain_xp(99), 0);
+        assert_eq!(entity.gain_xp(1), 1);
+        assert_eq!((entity.level, entity.xp), (2, 0));
+
+        // enough for two levels at once
+        assert_eq!(entity.gain_xp(150 + 200 + 10), 2);
+        assert_eq!((entity.level, entity.xp), (4, 10));
+    }
 }
@@ -0,0 +1,117 @@
+use crate::ui::NavInput;
+
+use std::cell::RefCell;
+
+/// Menu controls from every connected gamepad.
+#[derive(Default)]
+struct Buttons {
+    /// Went down since the last frame.
+    pressed: Vec<NavInput>,
+    /// Down until they are let go.
+    held: Vec<NavInput>,
+}
+
+// only gilrs events press and release buttons
+#[cfg_attr(not(feature = "gamepad"), allow(dead_code))]
+impl Buttons {
+    fn press(&mut self, input: NavInput) {
+        self.pressed.push(input);
+        if !self.held.contains(&input) {
+            self.held.push(input);
+        }
+    }
+
+    fn release(&mut self, input: NavInput) {
+        self.held.retain(|held| *held != input);
+    }
+}
+
+thread_local! {
+    static BUTTONS: RefCell<Buttons> = RefCell::new(Buttons::default());
+}
+
+#[cfg(feature = "gamepad")]
+thread_local! {
+    static GILRS: RefCell<Option<gilrs::Gilrs>> = const { RefCell::new(None) };
+}
+
+/// Start listening for gamepads, called once before the game loop.
+/// Without the gamepad feature, or if no backend is available, only the keyboard and mouse work.
+pub fn init() {
+    #[cfg(feature = "gamepad")]
+    match gilrs::Gilrs::new() {
+        Ok(gilrs) => GILRS.with_borrow_mut(|current| *current = Some(gilrs)),
+        Err(e) => eprintln!("Playing without a gamepad: {}", e),
+    }
+}
+
+/// Read what the gamepads did since last frame, called at the start of every frame.
+pub fn update() {
+    BUTTONS.with_borrow_mut(|buttons| buttons.pressed.clear());
+
+    #[cfg(feature = "gamepad")]
+    GILRS.with_borrow_mut(|gilrs| {
+        let Some(gilrs) = gilrs else {
+            return;
+        };
+        while let Some(gilrs::Event { event, .. }) = gilrs.next_event() {
+            BUTTONS.with_borrow_mut(|buttons| match event {
+                gilrs::EventType::ButtonPressed(button, _) => {
+                    if let Some(input) = nav_input(button) {
+                        buttons.press(input);
+                    }
+                }
+                gilrs::EventType::ButtonReleased(button, _) => {
+                    if let Some(input) = nav_input(button) {
+                        buttons.release(input);
+                    }
+                }
+                gilrs::EventType::Disconnected => buttons.held.clear(),
+                _ => {}
+            });
+        }
+    });
+}
+
+/// The d-pad moves, the bottom face button (A on most pads) confirms and the right one (B) goes back.
+#[cfg(feature = "gamepad")]
+fn nav_input(button: gilrs::Button) -> Option<NavInput> {
+    match button {
+        gilrs::Button::DPadUp => Some(NavInput::Up),
+        gilrs::Button::DPadDown => Some(NavInput::Down),
+        gilrs::Button::DPadLeft => Some(NavInput::Left),
+        gilrs::Button::DPadRight => Some(NavInput::Right),
+        gilrs::Button::South => Some(NavInput::Confirm),
+        gilrs::Button::East => Some(NavInput::Back),
+        _ => None,
+    }
+}
+
+/// True on the frame a gamepad button for the control went down.
+pub fn pressed(input: NavInput) -> bool {
+    BUTTONS.with_borrow(|buttons| buttons.pressed.contains(&input))
+}
+
+/// True for as long as a gamepad button for the control is down.
+pub fn held(input: NavInput) -> bool {
+    BUTTONS.with_borrow(|buttons| buttons.held.contains(&input))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presses_last_one_frame() {
+        BUTTONS.with_borrow_mut(|buttons| buttons.press(NavInput::Confirm));
+        assert!(pressed(NavInput::Confirm));
+        assert!(held(NavInput::Confirm));
+
+        update();
+        assert!(!pressed(NavInput::Confirm));
+        assert!(held(NavInput::Confirm));
+
+        BUTTONS.with_borrow_mut(|buttons| buttons.release(NavInput::Confirm));
+        assert!(!held(NavInput::Confirm));
+    }
+}
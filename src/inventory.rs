@@ -0,0 +1,249 @@
+use crate::item::{Effect, Item, ItemCatalog};
+
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum InventoryError {
+    NotEnoughMoney {
+        price: u32,
+        money: u32,
+    },
+    NotEnoughItems,
+    /// The price or the money afterwards is more than can be counted.
+    TooMuchMoney,
+    /// There would be more of one item than can be counted.
+    TooMany,
+}
+
+impl fmt::Display for InventoryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InventoryError::NotEnoughMoney { price, money } => {
+                write!(f, "That costs ${} but you only have ${}", price, money)
+            }
+            InventoryError::NotEnoughItems => write!(f, "You don't have enough of those"),
+            InventoryError::TooMuchMoney => write!(f, "That's more money than can be counted"),
+            InventoryError::TooMany => write!(f, "You can't carry any more of those"),
+        }
+    }
+}
+
+impl std::error::Error for InventoryError {}
+
+/// A number of one item, written as `id:count` such as `coffee:3`.
+#[derive(PartialEq, Clone, Debug)]
+pub struct ItemStack {
+    pub id: String,
+    pub count: u32,
+}
+
+impl FromStr for ItemStack {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (id, count) = s.split_once(':').ok_or(())?;
+        let count = count.trim().parse().map_err(|_| ())?;
+        match (id.trim(), count) {
+            ("", _) | (_, 0) => Err(()),
+            (id, count) => Ok(Self {
+                id: String::from(id),
+                count,
+            }),
+        }
+    }
+}
+
+impl fmt::Display for ItemStack {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.id, self.count)
+    }
+}
+
+/// The player's money and the items they are carrying.
+#[derive(PartialEq, Clone, Debug, Default)]
+pub struct Inventory {
+    pub money: u32,
+    /// Items in the order they were first picked up, never with a count of zero.
+    items: Vec<ItemStack>,
+}
+
+impl Inventory {
+    pub fn new(money: u32, items: Vec<ItemStack>) -> Self {
+        let mut inventory = Self {
+            money,
+            items: Vec::new(),
+        };
+        for stack in items {
+            // more of one item than can be counted can't be carried, so the extra is dropped
+            let _ = inventory.add(&stack.id, stack.count);
+        }
+        inventory
+    }
+
+    pub fn items(&self) -> &[ItemStack] {
+        &self.items
+    }
+
+    pub fn count(&self, id: &str) -> u32 {
+        self.items
+            .iter()
+            .find(|stack| stack.id == id)
+            .map_or(0, |stack| stack.count)
+    }
+
+    /// Add some of an item, nothing changes if the stack would hold more than can be counted.
+    pub fn add(&mut self, id: &str, count: u32) -> Result<(), InventoryError> {
+        if count == 0 {
+            return Ok(());
+        }
+        match self.items.iter_mut().find(|stack| stack.id == id) {
+            Some(stack) => {
+                stack.count = stack
+                    .count
+                    .checked_add(count)
+                    .ok_or(InventoryError::TooMany)?
+            }
+            None => self.items.push(ItemStack {
+                id: String::from(id),
+                count,
+            }),
+        }
+        Ok(())
+    }
+
+    pub fn remove(&mut self, id: &str, count: u32) -> Result<(), InventoryError> {
+        let index = self
+            .items
+            .iter()
+            .position(|stack| stack.id == id && stack.count >= count)
+            .ok_or(InventoryError::NotEnoughItems)?;
+        self.items[index].count -= count;
+        if self.items[index].count == 0 {
+            self.items.remove(index);
+        }
+        Ok(())
+    }
+
+    pub fn buy(&mut self, item: &Item, count: u32) -> Result<(), InventoryError> {
+        let price = item
+            .price
+            .checked_mul(count)
+            .ok_or(InventoryError::TooMuchMoney)?;
+        if price > self.money {
+            return Err(InventoryError::NotEnoughMoney {
+                price,
+                money: self.money,
+            });
+        }
+        self.add(&item.id, count)?;
+        self.money -= price;
+        Ok(())
+    }
+
+    pub fn sell(&mut self, item: &Item, count: u32) -> Result<(), InventoryError> {
+        let money = item
+            .sell_price()
+            .checked_mul(count)
+            .and_then(|sale| self.money.checked_add(sale))
+            .ok_or(InventoryError::TooMuchMoney)?;
+        self.remove(&item.id, count)?;
+        self.money = money;
+        Ok(())
+    }
+
+    /// The carried item that helps recruiting the most, if any.
+    pub fn best_recruit_tool<'a>(&self, catalog: &'a ItemCatalog) -> Option<(&'a Item, f64)> {
+        self.items
+            .iter()
+            .filter_map(|stack| catalog.get(&stack.id))
+            .filter_map(|item| match item.effect {
+                Effect::Recruit(bonus) => Some((item, bonus)),
+                _ => None,
+            })
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEXT: &str = "
+[item]
+id = coffee
+name = Coffee
+effect = heal:50
+price = 100
+
+[item]
+id = git_clone
+name = Git Clone
+effect = recruit:0.15
+price = 200
+
+[item]
+id = git_clone_deep
+name = Deep Clone
+effect = recruit:0.3
+price = 500
+";
+
+    #[test]
+    fn buy_and_sell() {
+        let catalog = ItemCatalog::parse(TEXT).unwrap();
+        let coffee = catalog.get("coffee").unwrap();
+        let mut inventory = Inventory::new(250, Vec::new());
+
+        inventory.buy(coffee, 2).unwrap();
+        assert_eq!(inventory.money, 50);
+        assert_eq!(inventory.count("coffee"), 2);
+        assert_eq!(
+            inventory.buy(coffee, 1),
+            Err(InventoryError::NotEnoughMoney {
+                price: 100,
+                money: 50
+            })
+        );
+
+        inventory.sell(coffee, 2).unwrap();
+        assert_eq!(inventory.money, 150);
+        assert!(inventory.items().is_empty());
+        assert_eq!(
+            inventory.sell(coffee, 1),
+            Err(InventoryError::NotEnoughItems)
+        );
+
+        // huge counts are refused rather than overflowing
+        assert_eq!(
+            inventory.buy(coffee, u32::MAX),
+            Err(InventoryError::TooMuchMoney)
+        );
+        inventory.add("coffee", u32::MAX).unwrap();
+        assert_eq!(
+            inventory.sell(coffee, u32::MAX),
+            Err(InventoryError::TooMuchMoney)
+        );
+        assert_eq!(inventory.count("coffee"), u32::MAX);
+        assert_eq!(inventory.buy(coffee, 1), Err(InventoryError::TooMany));
+        assert_eq!(inventory.money, 150);
+        assert_eq!(inventory.count("coffee"), u32::MAX);
+    }
+
+    #[test]
+    fn stacks_and_recruit_tools() {
+        let catalog = ItemCatalog::parse(TEXT).unwrap();
+        let items: Vec<ItemStack> =
+            vec!["coffee:2".parse().unwrap(), "git_clone:1".parse().unwrap()];
+        let mut inventory = Inventory::new(0, items);
+        assert_eq!(inventory.items()[1].to_string(), "git_clone:1");
+        assert!("coffee:0".parse::<ItemStack>().is_err());
+
+        let (tool, bonus) = inventory.best_recruit_tool(&catalog).unwrap();
+        assert_eq!((tool.id.as_str(), bonus), ("git_clone", 0.15));
+
+        inventory.add("git_clone_deep", 1).unwrap();
+        let (tool, _) = inventory.best_recruit_tool(&catalog).unwrap();
+        assert_eq!(tool.id, "git_clone_deep");
+    }
+}
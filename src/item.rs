@@ -0,0 +1,281 @@
+use crate::data::{DataError, Document};
+use crate::entity::Entity;
+use crate::moves::Move;
+
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+/// What an item does when it is used, written as `heal:50`, `cure`, `recruit:0.2` or `teach:Async`.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum Effect {
+    /// Restore some health.
+    Heal(u32),
+    /// Undo every stat change.
+    Cure,
+    /// Added to the chance of recruiting a wild entity, used up on each attempt.
+    Recruit(f64),
+    /// Teach an entity a move.
+    Teach(Move),
+}
+
+impl FromStr for Effect {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, value) = match s.split_once(':') {
+            Some((name, value)) => (name.trim(), Some(value.trim())),
+            None => (s.trim(), None),
+        };
+
+        match (name, value) {
+            ("heal", Some(amount)) => Ok(Effect::Heal(amount.parse().map_err(|_| ())?)),
+            ("cure", None) => Ok(Effect::Cure),
+            ("recruit", Some(bonus)) => {
+                let bonus: f64 = bonus.parse().map_err(|_| ())?;
+                match (0.0..=1.0).contains(&bonus) {
+                    true => Ok(Effect::Recruit(bonus)),
+                    false => Err(()),
+                }
+            }
+            ("teach", Some(mv)) => Ok(Effect::Teach(mv.parse()?)),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Why an item couldn't be used.
+#[derive(PartialEq, Clone, Debug)]
+pub enum UseError {
+    FullHealth,
+    /// Healing items can't bring back a fallen entity, resting can.
+    Fainted,
+    NoStatChanges,
+    AlreadyKnows(Move),
+    TooManyMoves,
+    /// The item only works during a battle.
+    BattleOnly,
+}
+
+impl fmt::Display for UseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            UseError::FullHealth => write!(f, "It's already at full health"),
+            UseError::Fainted => write!(f, "It has fallen, it needs to rest first"),
+            UseError::NoStatChanges => write!(f, "There's nothing to fix"),
+            UseError::AlreadyKnows(mv) => write!(f, "It already knows {}", mv),
+            UseError::TooManyMoves => {
+                write!(f, "It already knows {} moves", Entity::MAX_MOVES)
+            }
+            UseError::BattleOnly => write!(f, "That can only be used in a battle"),
+        }
+    }
+}
+
+impl std::error::Error for UseError {}
+
+/// Something the player can buy, carry and use.
+#[derive(PartialEq, Clone, Debug)]
+pub struct Item {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub effect: Effect,
+    pub price: u32,
+}
+
+impl Item {
+    /// What a shop pays for the item.
+    pub fn sell_price(&self) -> u32 {
+        self.price / 2
+    }
+
+    /// Use the item on an entity, returning a message describing what happened.
+    pub fn use_on(&self, entity: &mut Entity) -> Result<String, UseError> {
+        match self.effect {
+            Effect::Heal(_) if entity.health == 0 => Err(UseError::Fainted),
+            Effect::Heal(_) if entity.health == entity.max_health => Err(UseError::FullHealth),
+            Effect::Heal(amount) => {
+                let before = entity.health;
+                entity.heal(amount);
+                Ok(format!(
+                    "{} recovered {} HP",
+                    entity,
+                    entity.health - before
+                ))
+            }
+            Effect::Cure => match entity.reset_stats() {
+                true => Ok(format!("{}'s stats went back to normal", entity)),
+                false => Err(UseError::NoStatChanges),
+            },
+            Effect::Recruit(_) => Err(UseError::BattleOnly),
+            Effect::Teach(mv) if entity.get_moves().contains(&mv) => {
+                Err(UseError::AlreadyKnows(mv))
+            }
+            Effect::Teach(mv) => match entity.learn_move(mv) {
+                true => Ok(format!("{} learned {}!", entity, mv)),
+                false => Err(UseError::TooManyMoves),
+            },
+        }
+    }
+}
+
+/// The items a shop sells.
+#[derive(PartialEq, Clone, Debug)]
+pub struct Shop {
+    pub id: String,
+    pub name: String,
+    /// Ids of the items for sale.
+    pub stock: Vec<String>,
+}
+
+/// Every item and shop in the game, loaded from the items file.
+#[derive(Clone, Debug, Default)]
+pub struct ItemCatalog {
+    items: Vec<Item>,
+    shops: Vec<Shop>,
+}
+
+impl ItemCatalog {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, DataError> {
+        Self::from_document(&Document::load(path)?)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, DataError> {
+        Self::from_document(&Document::parse(text)?)
+    }
+
+    fn from_document(document: &Document) -> Result<Self, DataError> {
+        let mut items: Vec<Item> = Vec::new();
+        for section in document.sections_named("item") {
+            let id = section.require("id")?;
+            if items.iter().any(|item| item.id == id) {
+                return Err(section.invalid("id", id));
+            }
+
+            items.push(Item {
+                id: String::from(id),
+                name: String::from(section.require("name")?),
+                description: String::from(section.get("description").unwrap_or_default()),
+                effect: section.parse("effect")?,
+                price: section.parse("price")?,
+            });
+        }
+
+        let mut shops = Vec::new();
+        for section in document.sections_named("shop") {
+            let stock: Vec<String> = section.parse_list("stock")?;
+            if let Some(unknown) = stock.iter().find(|id| !items.iter().any(|i| &i.id == *id)) {
+                return Err(section.invalid("stock", unknown));
+            }
+
+            shops.push(Shop {
+                id: String::from(section.require("id")?),
+                name: String::from(section.require("name")?),
+                stock,
+            });
+        }
+
+        Ok(Self { items, shops })
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Item> {
+        self.items.iter().find(|item| item.id == id)
+    }
+
+    pub fn shop(&self, id: &str) -> Option<&Shop> {
+        self.shops.iter().find(|shop| shop.id == id)
+    }
+
+    pub fn shops(&self) -> &[Shop] {
+        &self.shops
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entity::{EntityType, Stat};
+    use crate::overworld::Map;
+
+    const TEXT: &str = "
+[item]
+id = coffee
+name = Coffee
+description = Restores 50 HP.
+effect = heal:50
+price = 100
+
+[item]
+id = debugger
+name = Debugger
+effect = cure
+price = 150
+
+[item]
+id = rfc_math
+name = Math RFC
+effect = teach:Math
+price = 800
+
+[shop]
+id = mart
+name = Mart
+stock = coffee, debugger
+";
+
+    #[test]
+    fn parse_catalog() {
+        let catalog = ItemCatalog::parse(TEXT).unwrap();
+        assert_eq!(catalog.get("coffee").unwrap().effect, Effect::Heal(50));
+        assert_eq!(catalog.get("coffee").unwrap().sell_price(), 50);
+        assert_eq!(catalog.shop("mart").unwrap().stock.len(), 2);
+
+        assert_eq!("recruit:0.2".parse(), Ok(Effect::Recruit(0.2)));
+        assert!("recruit:2".parse::<Effect>().is_err());
+        assert!("teach:Fly".parse::<Effect>().is_err());
+
+        let unknown = TEXT.replace("stock = coffee, debugger", "stock = coffee, tea");
+        assert!(matches!(
+            ItemCatalog::parse(&unknown),
+            Err(DataError::Invalid { .. })
+        ));
+    }
+
+    #[test]
+    fn use_items() {
+        let catalog = ItemCatalog::parse(TEXT).unwrap();
+        let mut go = EntityType::Go.build(0, None);
+
+        let coffee = catalog.get("coffee").unwrap();
+        assert_eq!(coffee.use_on(&mut go), Err(UseError::FullHealth));
+        go.health = 10;
+        assert!(coffee.use_on(&mut go).is_ok());
+        assert_eq!(go.health, 60);
+        go.health = 0;
+        assert_eq!(coffee.use_on(&mut go), Err(UseError::Fainted));
+
+        let debugger = catalog.get("debugger").unwrap();
+        assert_eq!(debugger.use_on(&mut go), Err(UseError::NoStatChanges));
+        let accuracy = go.accuracy;
+        go.change_stat(Stat::Accuracy, -10);
+        assert!(debugger.use_on(&mut go).is_ok());
+        assert_eq!(go.accuracy, accuracy);
+
+        let rfc = catalog.get("rfc_math").unwrap();
+        assert!(rfc.use_on(&mut go).is_ok());
+        assert!(go.get_moves().contains(&Move::Math));
+        assert_eq!(rfc.use_on(&mut go), Err(UseError::AlreadyKnows(Move::Math)));
+    }
+
+    #[test]
+    fn maps_use_known_shops() {
+        let catalog = ItemCatalog::load("assets/items.data").unwrap();
+        for id in ["town", "route", "office"] {
+            let map = Map::load("assets/maps", id).unwrap();
+            for spot in map.shops.iter() {
+                assert!(catalog.shop(&spot.id).is_some(), "unknown shop {}", spot.id);
+            }
+        }
+    }
+}
@@ -0,0 +1,149 @@
+use macroquad::prelude::*;
+
+/// The smallest virtual screen, every ui position is given in virtual pixels.
+/// Windows with a different shape get a wider or taller virtual screen rather than black bars.
+pub const VIRTUAL_WIDTH: f32 = 1920.0;
+pub const VIRTUAL_HEIGHT: f32 = 1080.0;
+
+/// A point on the virtual screen that ui can be attached to, so it stays by the edges on any shape of window.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum Anchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Centre,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+impl Anchor {
+    /// Where the anchor is on a virtual screen of the given size.
+    pub fn point(&self, size: Vec2) -> Vec2 {
+        let (x, y) = match self {
+            Anchor::TopLeft => (0.0, 0.0),
+            Anchor::Top => (0.5, 0.0),
+            Anchor::TopRight => (1.0, 0.0),
+            Anchor::Left => (0.0, 0.5),
+            Anchor::Centre => (0.5, 0.5),
+            Anchor::Right => (1.0, 0.5),
+            Anchor::BottomLeft => (0.0, 1.0),
+            Anchor::Bottom => (0.5, 1.0),
+            Anchor::BottomRight => (1.0, 1.0),
+        };
+        vec2(x * size.x, y * size.y)
+    }
+
+    /// A position given as an offset from the anchor on the current virtual screen.
+    pub fn at(&self, offset: Vec2) -> Vec2 {
+        self.point(Viewport::current().size()) + offset
+    }
+}
+
+/// How the virtual screen is scaled to fit the window.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct Viewport {
+    /// Window pixels per virtual pixel.
+    pub scale: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Viewport {
+    /// Fit the virtual screen to a window, scaling evenly so the whole minimum size is visible.
+    pub fn fit(window_width: f32, window_height: f32) -> Self {
+        let scale = (window_width / VIRTUAL_WIDTH)
+            .min(window_height / VIRTUAL_HEIGHT)
+            .max(f32::EPSILON);
+        Self {
+            scale,
+            width: window_width / scale,
+            height: window_height / scale,
+        }
+    }
+
+    /// The viewport for the window this frame.
+    pub fn current() -> Self {
+        Self::fit(screen_width(), screen_height())
+    }
+
+    pub fn size(&self) -> Vec2 {
+        vec2(self.width, self.height)
+    }
+
+    /// A camera showing the virtual screen with y growing downwards, the same as the window.
+    /// On screen macroquad flips the y zoom, so it is given positive here.
+    pub fn camera(&self) -> Camera2D {
+        Camera2D {
+            target: self.size() / 2.0,
+            zoom: vec2(2.0 / self.width, 2.0 / self.height),
+            ..Default::default()
+        }
+    }
+
+    /// Convert a point in window pixels to virtual pixels.
+    pub fn to_virtual(&self, (x, y): (f32, f32)) -> Vec2 {
+        vec2(x / self.scale, y / self.scale)
+    }
+}
+
+/// Draw everything this frame in virtual pixels, called once at the start of every frame.
+pub fn begin_frame() {
+    set_camera(&Viewport::current().camera());
+}
+
+/// Width of the virtual screen this frame.
+pub fn width() -> f32 {
+    Viewport::current().width
+}
+
+/// Height of the virtual screen this frame.
+pub fn height() -> f32 {
+    Viewport::current().height
+}
+
+/// The mouse position in virtual pixels.
+pub fn mouse() -> Vec2 {
+    Viewport::current().to_virtual(mouse_position())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fit_windows() {
+        // the same shape just scales
+        let small = Viewport::fit(960.0, 540.0);
+        assert_eq!(small.scale, 0.5);
+        assert_eq!(small.size(), vec2(VIRTUAL_WIDTH, VIRTUAL_HEIGHT));
+        assert_eq!(small.to_virtual((480.0, 270.0)), vec2(960.0, 540.0));
+
+        // ultrawide windows get more room to the sides and square ones more room below
+        let wide = Viewport::fit(3440.0, 1440.0);
+        assert!((wide.height - VIRTUAL_HEIGHT).abs() < 0.01);
+        assert!(wide.width > VIRTUAL_WIDTH);
+        let square = Viewport::fit(1000.0, 1000.0);
+        assert!((square.width - VIRTUAL_WIDTH).abs() < 0.01);
+        assert!(square.height > VIRTUAL_HEIGHT);
+    }
+
+    #[test]
+    fn camera_keeps_top_at_top() {
+        let matrix = Viewport::fit(1920.0, 1080.0).camera().matrix();
+        let top_left = matrix.project_point3(vec3(0.0, 0.0, 0.0));
+        assert_eq!((top_left.x, top_left.y), (-1.0, 1.0));
+        let bottom_right = matrix.project_point3(vec3(VIRTUAL_WIDTH, VIRTUAL_HEIGHT, 0.0));
+        assert_eq!((bottom_right.x, bottom_right.y), (1.0, -1.0));
+    }
+
+    #[test]
+    fn anchor_points() {
+        let size = vec2(2560.0, 1080.0);
+        assert_eq!(Anchor::TopLeft.point(size), vec2(0.0, 0.0));
+        assert_eq!(Anchor::Centre.point(size), vec2(1280.0, 540.0));
+        assert_eq!(Anchor::BottomRight.point(size), size);
+    }
+}
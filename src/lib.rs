@@ -1,10 +1,35 @@
+pub mod animation;
+pub mod assets;
+pub mod audio;
+pub mod battle;
+pub mod battle_log;
+pub mod data;
 pub mod entity;
+pub mod gamepad;
+pub mod inventory;
+pub mod item;
+pub mod layout;
 pub mod moves;
+pub mod net;
+pub mod overworld;
+pub mod pause;
+pub mod progression;
+pub mod registry;
+pub mod rules;
+pub mod save;
+pub mod settings;
+pub mod settings_menu;
+pub mod shop;
+pub mod sprite;
+pub mod storage;
+pub mod team_builder;
+pub mod theme;
+pub mod trainer;
 pub mod ui;
 
 mod team;
 
-pub use team::Team;
+pub use team::{Team, TeamError};
 
 use entity::*;
 use std::collections::VecDeque;
@@ -22,35 +47,69 @@ pub fn active_died(team: &mut Team) -> bool {
 }
 
 /// Execute moves of the player and enemy.
-/// Returns True if either the player or enemy died.
-pub fn execute_moves(player: &mut Entity, enemy: &mut Entity, text_queue: &mut VecDeque<String>) {
-    let player_priority = match player.get_move_priority() {
-        Some(priority) => priority,
-        None => 0,
-    };
+/// All randomness is drawn from `rng` so that a seeded generator replays the same turn.
+pub fn execute_moves(
+    player: &mut Entity,
+    enemy: &mut Entity,
+    text_queue: &mut VecDeque<String>,
+    rng: &mut impl Rng,
+) {
+    execute_moves_with(player, enemy, text_queue, rng, |_, _, _, _| {});
+}
 
-    let enemy_priority = match enemy.get_move_priority() {
-        Some(priority) => priority,
-        None => 0,
-    };
+/// Execute moves of the player and enemy, calling `after_move` once each move is done.
+/// It is given which entity moved, 0 for the player and 1 for the enemy, both entities and the text so far.
+pub fn execute_moves_with(
+    player: &mut Entity,
+    enemy: &mut Entity,
+    text_queue: &mut VecDeque<String>,
+    rng: &mut impl Rng,
+    mut after_move: impl FnMut(usize, &Entity, &Entity, &VecDeque<String>),
+) {
+    let player_priority = player.get_move_priority().unwrap_or(0);
+    let enemy_priority = enemy.get_move_priority().unwrap_or(0);
 
     match player_priority >= enemy_priority {
         true => {
-            player.execute_move(enemy, text_queue);
-            enemy.execute_move(player, text_queue);
+            player.execute_move(enemy, text_queue, rng);
+            after_move(0, player, enemy, text_queue);
+            enemy.execute_move(player, text_queue, rng);
+            after_move(1, player, enemy, text_queue);
         }
         false => {
-            enemy.execute_move(player, text_queue);
-            player.execute_move(enemy, text_queue);
+            enemy.execute_move(player, text_queue, rng);
+            after_move(1, player, enemy, text_queue);
+            player.execute_move(enemy, text_queue, rng);
+            after_move(0, player, enemy, text_queue);
         }
     };
 }
 
-pub fn queue_enemy_move(enemy: &mut Entity) {
-    let i = rand::thread_rng().gen_range(0..=enemy.get_moves().len() - 1);
-    let mv = enemy.get_moves().get(i).unwrap().clone();
-
-    enemy.queue_move(mv);
+/// Pick a move for a computer controlled entity to use against a target.
+pub fn choose_move(
+    strategy: battle::Strategy,
+    entity: &Entity,
+    target: &Entity,
+    rng: &mut impl Rng,
+) -> moves::Move {
+    let moves = entity.get_moves();
+    match strategy {
+        battle::Strategy::Random => moves[rng.gen_range(0..moves.len())],
+        battle::Strategy::Smart => {
+            let score = |mv: &moves::Move| {
+                if target.weaknesses.contains(mv) {
+                    2
+                } else if target.strengths.contains(mv) {
+                    0
+                } else {
+                    1
+                }
+            };
+            let best = moves.iter().map(score).max().unwrap_or_default();
+            let choices: Vec<&moves::Move> = moves.iter().filter(|mv| score(mv) == best).collect();
+            *choices[rng.gen_range(0..choices.len())]
+        }
+    }
 }
 
 #[cfg(test)]
@@ -62,15 +121,29 @@ mod tests {
         let mut team = Team::new();
         let rust = RustEntity::build(0, None);
         let rust2 = RustEntity::build(0, None);
-        team.push(rust);
-        team.push(rust2);
+        team.push(rust).unwrap();
+        team.push(rust2).unwrap();
 
         team.entities[0].health = 0;
         team.entities[1].health = 1;
 
-        assert_eq!(false, active_died(&mut team));
+        assert!(!active_died(&mut team));
 
         team.entities[1].health = 0;
-        assert_eq!(true, active_died(&mut team));
+        assert!(active_died(&mut team));
+    }
+
+    #[test]
+    fn smart_strategy_picks_effective_moves() {
+        let go = GoEntity::build(0, None);
+        let mut target = RustEntity::build(0, None);
+        target.weaknesses = vec![moves::Move::Async];
+        target.strengths = vec![moves::Move::IntParse];
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(3);
+        for _ in 0..20 {
+            let mv = choose_move(battle::Strategy::Smart, &go, &target, &mut rng);
+            assert_eq!(mv, moves::Move::Async);
+        }
     }
 }
@@ -1,314 +1,733 @@
-use rpg_game::entity::{CppEntity, EntityBuilder, GoEntity, JsEntity, PythonEntity, RustEntity};
-use rpg_game::moves::Move;
-use rpg_game::ui::{Button, ButtonLink, EntityImageParams, EntityStats};
+use rpg_game::animation::AnimationBook;
+use rpg_game::assets::{AssetLoader, AssetManifest};
+use rpg_game::audio::{Audio, Music};
+use rpg_game::battle::{Battle, BattleTextures, Controller, Side};
+use rpg_game::gamepad;
+use rpg_game::item::ItemCatalog;
+use rpg_game::layout;
+use rpg_game::net::{Handshake, TcpTransport, TeamMember, DEFAULT_PORT};
+use rpg_game::overworld::{BattleResult, Overworld, OverworldAssets, OverworldEvent};
+use rpg_game::rules::Ruleset;
+use rpg_game::save::SaveData;
+use rpg_game::settings::Settings;
+use rpg_game::settings_menu::SettingsScreen;
+use rpg_game::sprite::SpriteBook;
+use rpg_game::team_builder::TeamBuilder;
+use rpg_game::theme::Theme;
+use rpg_game::trainer::TrainerBook;
+use rpg_game::ui::{self, Button, ButtonLink};
 use rpg_game::Team;
 
 use macroquad::prelude::*;
 
 use std::collections::HashMap;
-use std::collections::VecDeque;
+use std::net::TcpListener;
+use std::path::Path;
 
 #[derive(PartialEq, Clone)]
-enum State {
-    Wait,
-    Move,
-    Dialogue(Box<State>),
-    End,
-    Switch,
+enum Mode {
+    Adventure,
+    /// Carry on the adventure from the save file.
+    Continue,
+    SinglePlayer,
+    HotSeat,
+    Host,
+    Join(String),
+    /// Open the settings screen.
+    Settings,
 }
 
-struct Battle<'a> {
-    player_team: Team,
-    enemy_team: Team,
-    state: State,
-    debounce: bool,
-    debounce_step: bool,
-    text_queue: VecDeque<String>,
-    player_ui: EntityStats<'a>,
-    enemy_ui: EntityStats<'a>,
-    attack_button: Button<'a>,
-    switch_button: Button<'a>,
-    forfeit_button: Button<'a>,
-    empty_button_texture: Texture2D,
+enum Scene<'a> {
+    ModeSelect,
+    EnterAddress(String),
+    BuildTeams {
+        mode: Mode,
+        builder: TeamBuilder<'a>,
+        built: Vec<Team>,
+    },
+    HostWaiting {
+        listener: TcpListener,
+        team: Team,
+    },
+    Connecting {
+        handshake: Handshake,
+        team: Team,
+    },
+    Message(String),
+    Battle(Box<Battle<'a>>),
+    /// Rolled after the final boss is beaten.
+    Credits {
+        lines: Vec<String>,
+        scroll: f32,
+    },
+    Overworld {
+        overworld: Box<Overworld<'a>>,
+        battle: Option<Box<Battle<'a>>>,
+    },
+    /// Goes back to the scene it was opened from, or the main menu.
+    Settings {
+        screen: SettingsScreen<'a>,
+        previous: Option<Box<Scene<'a>>>,
+    },
 }
 
-#[macroquad::main("RPG Game")]
-async fn main() {
-    // load textures for entities
-    let rust_texture = load_texture("assets/rust.png").await.ok();
-    let python_texture = load_texture("assets/python.png").await.ok();
-    let cpp_texture = load_texture("assets/cpp.png").await.ok();
-    let js_texture = load_texture("assets/js.png").await.ok();
-    let go_texture = load_texture("assets/go.png").await.ok();
-
-    // create player and enemy teams
-    let mut player_team = Team::new();
-    player_team.push(RustEntity::build(0, rust_texture.clone()));
-    player_team.push(PythonEntity::build(0, python_texture.clone()));
-
-    let mut enemy_team = Team::new();
-    enemy_team.push(CppEntity::build(0, cpp_texture.clone()));
-    enemy_team.push(JsEntity::build(0, js_texture.clone()));
-    enemy_team.push(GoEntity::build(0, go_texture.clone()));
-
-    // load textures
-    let empty_button_texture: Texture2D = load_texture("assets/empty-button.png").await.unwrap();
-    let attack_button_texture: Texture2D = load_texture("assets/attack-button.png").await.unwrap();
-    let switch_button_texture: Texture2D = load_texture("assets/switch-button.png").await.unwrap();
-    let forfeit_button_texture: Texture2D =
-        load_texture("assets/forfeit-button.png").await.unwrap();
-    let health_bar_texture: Texture2D = load_texture("assets/health-bar.png").await.unwrap();
-
-    // create ui elements
-    let attack_button = Button::new(&attack_button_texture, 1100.0, 600.0);
-    let switch_button = Button::new(&switch_button_texture, 1100.0, 675.0);
-    let forfeit_button = Button::new(&forfeit_button_texture, 1100.0, 750.0);
-    let player_ui = EntityStats::new(100.0, 600.0, &health_bar_texture);
-    let enemy_ui = EntityStats::new(600.0, 100.0, &health_bar_texture);
-
-    // change the window to fullscreen
-    set_fullscreen(true);
-
-    // initialize state
-    let state = State::Wait;
-
-    let text_queue: VecDeque<String> = VecDeque::new();
-    let debounce = false;
-    let debounce_step = false;
-
-    let mut battle = Battle {
-        player_team,
-        enemy_team,
-        debounce,
-        debounce_step,
-        state,
-        text_queue,
-        player_ui,
-        enemy_ui,
-        attack_button,
-        switch_button,
-        forfeit_button,
-        empty_button_texture,
-    };
+/// Directory holding the overworld map files.
+const MAPS_DIR: &str = "assets/maps";
 
-    // game loop
-    loop {
-        // refresh frame
-        clear_background(BLACK);
+/// Map the adventure starts on.
+const START_MAP: &str = "town";
 
-        if battle.update() {
-            break;
-        };
+/// Pixels per second the credits scroll up the screen.
+const CREDITS_SPEED: f32 = 60.0;
 
-        next_frame().await;
-    }
-}
+/// File listing every texture the game loads.
+const ASSET_MANIFEST_PATH: &str = "assets/assets.data";
 
-impl Battle<'_> {
-    pub fn update(&mut self) -> bool {
-        // update player and enemy health.
-        let player = self
-            .player_team
-            .get_active()
-            .expect("Active player has somehow been destroyed");
-
-        let enemy = self
-            .enemy_team
-            .get_active()
-            .expect("Active enemy has somehow been destroyed");
-
-        self.player_ui.update(
-            player.health,
-            player.max_health,
-            EntityImageParams {
-                texture: &player.texture,
-                x: 400.0,
-                y: 550.0,
-            },
-            format!("{}", player).as_str(),
-        );
-        self.enemy_ui.update(
-            enemy.health,
-            enemy.max_health,
-            EntityImageParams {
-                texture: &enemy.texture,
-                x: 900.0,
-                y: 50.0,
-            },
-            format!("{}", enemy).as_str(),
-        );
+/// File with every trainer in the adventure.
+const TRAINERS_PATH: &str = "assets/trainers.data";
 
-        match self.state {
-            State::Dialogue(ref transition_state) => {
-                let state_clone = transition_state.clone();
-                self.dialogue(state_clone);
-            }
+/// File with every item and shop in the adventure.
+const ITEMS_PATH: &str = "assets/items.data";
 
-            State::Wait => {
-                self.wait_state();
-            }
+/// File with the animation played for each move.
+const ANIMATIONS_PATH: &str = "assets/animations.data";
 
-            State::Move => {
-                self.move_state();
-            }
-            State::Switch => {
-                self.switch_state();
-            }
-            State::End => {
-                return true;
-            }
-        }
+/// File with the sprite sheet for each entity type.
+const SPRITES_PATH: &str = "assets/sprites.data";
 
-        if self.debounce && self.debounce_step {
-            self.debounce = false;
-            self.debounce_step = false;
-        } else if self.debounce {
-            self.debounce_step = true
-        }
+/// File with the fonts, colours and button skin the UI is drawn with.
+const THEME_PATH: &str = "assets/theme.data";
+
+/// Where the adventure is saved.
+const SAVE_PATH: &str = "adventure.save";
+
+/// Config file with the player's settings, written whenever they change.
+const SETTINGS_PATH: &str = "settings.cfg";
 
-        false
+/// Open the window the way the settings ask for.
+fn window_conf() -> Conf {
+    let settings = Settings::load_or_default(SETTINGS_PATH);
+    Conf {
+        window_title: String::from("RPG Game"),
+        fullscreen: settings.fullscreen,
+        window_width: settings.resolution.width as i32,
+        window_height: settings.resolution.height as i32,
+        ..Default::default()
     }
+}
 
-    fn wait_state(&mut self) {
-        // draw buttons.
-        self.attack_button.draw();
-        self.switch_button.draw();
-        self.forfeit_button.draw();
+#[macroquad::main(window_conf)]
+async fn main() {
+    // the window was opened with these, the rest are applied below
+    let mut settings = Settings::load_or_default(SETTINGS_PATH);
+    ui::set_key_bindings(settings.keys);
+    gamepad::init();
+
+    // anything the theme leaves out keeps the default look
+    let theme = Theme::load(THEME_PATH).unwrap_or_else(|e| {
+        eprintln!("Could not load the theme: {}", e);
+        Theme::default()
+    });
+    let font = match theme.font {
+        Some(ref path) => match load_ttf_font(&path.to_string_lossy()).await {
+            Ok(font) => Some(font),
+            Err(e) => {
+                eprintln!("Could not load the font {}: {}", path.display(), e);
+                None
+            }
+        },
+        None => None,
+    };
+    ui::set_theme(theme.clone(), font);
 
-        // check for button presses and change the state accordingly
-        if self.attack_button.clicked() && !self.debounce {
-            self.state = State::Move;
-            self.debounce = true;
+    let trainers = match TrainerBook::load(TRAINERS_PATH) {
+        Ok(trainers) => trainers,
+        Err(e) => {
+            eprintln!("Could not load the trainers: {}", e);
+            TrainerBook::default()
         }
+    };
 
-        if self.switch_button.clicked() && !self.debounce {
-            self.state = State::Switch;
-            self.debounce = true;
+    // every texture comes from the manifest, with placeholders for any that are missing
+    let mut manifest = AssetManifest::load(ASSET_MANIFEST_PATH).unwrap_or_else(|e| {
+        eprintln!("Could not load the asset manifest: {}", e);
+        AssetManifest::default()
+    });
+    for trainer in trainers.trainers() {
+        if let Some(ref path) = trainer.sprite {
+            manifest.add_texture(&trainer_sprite_key(&trainer.id), path);
         }
+    }
+    let mut audio = Audio::load(&manifest).await;
+    let mut loader = AssetLoader::new(manifest);
+    while !loader.finished() {
+        clear_background(BLACK);
+        layout::begin_frame();
+        loader.load_next().await;
+        loader.draw();
+        next_frame().await;
+    }
+    let assets = loader.finish();
 
-        if self.forfeit_button.clicked() && !self.debounce {
-            self.text_queue.push_back(String::from(
-                "You decided that the battle was futile and quit early",
-            ));
-            self.state = State::Dialogue(Box::new(State::End));
-            self.debounce = true;
+    let entity_textures = assets.entity_textures();
+    let mut trainer_sprites: HashMap<String, Texture2D> = HashMap::new();
+    for trainer in trainers.trainers() {
+        if let Some(texture) = assets.get(&trainer_sprite_key(&trainer.id)) {
+            trainer_sprites.insert(trainer.id.clone(), texture.clone());
         }
     }
-    fn move_state(&mut self) {
-        let mut player_mv: Option<Move> = None;
-        let mut move_buttons: HashMap<Move, Button> = HashMap::new();
-        let player = self.player_team.get_active().unwrap();
-        let enemy = self.enemy_team.get_active().unwrap();
-
-        // add buttons for all the moves
-        for (i, mv) in player.get_moves().iter().enumerate() {
-            move_buttons.insert(
-                mv.clone(),
-                Button::new(
-                    &self.empty_button_texture,
-                    1100.0,
-                    550.0 + (75.0 * (i as f32)),
-                ),
-            );
-        }
-
-        // check if any of the buttons are clicked
-        for (mv, mut button) in move_buttons.into_iter() {
-            button.draw();
-            if button.clicked() && !self.debounce {
-                self.debounce = true;
-                player_mv = Some(mv);
-            }
 
-            // draw text on top of the button
-            draw_text(
-                format!("{}", mv).as_str(),
-                button.xpos + 10.0,
-                button.ypos + 40.0,
-                30.0,
-                WHITE,
-            );
+    let items = match ItemCatalog::load(ITEMS_PATH) {
+        Ok(items) => items,
+        Err(e) => {
+            eprintln!("Could not load the items: {}", e);
+            ItemCatalog::default()
         }
+    };
 
-        if let Some(mv) = player_mv {
-            player.queue_move(mv);
-            rpg_game::queue_enemy_move(enemy);
-            rpg_game::execute_moves(player, enemy, &mut self.text_queue);
-            self.state = State::Dialogue(Box::new(State::Wait));
+    // moves without an animation just skip straight to the damage
+    let animations = match AnimationBook::load(ANIMATIONS_PATH) {
+        Ok(animations) => animations,
+        Err(e) => {
+            eprintln!("Could not load the move animations: {}", e);
+            AnimationBook::default()
         }
+    };
 
-        if player.health == 0 {
-            self.text_queue
-                .push_back(format!("The player, {}, has fallen.", player));
-            if rpg_game::active_died(&mut self.player_team) {
-                self.text_queue
-                    .push_back(String::from("You lost the battle"));
-                self.state = State::Dialogue(Box::new(State::End));
-                return;
-            }
+    // entity types without a sheet are drawn as a single frame
+    let sprites = match SpriteBook::load(SPRITES_PATH) {
+        Ok(sprites) => sprites,
+        Err(e) => {
+            eprintln!("Could not load the sprite sheets: {}", e);
+            SpriteBook::default()
         }
+    };
 
-        if enemy.health == 0 {
-            self.text_queue
-                .push_back(format!("The enemy, {}, has fallen.", enemy));
-            if rpg_game::active_died(&mut self.enemy_team) {
-                self.text_queue
-                    .push_back(String::from("You won the battle, congratulations!"));
-                self.state = State::Dialogue(Box::new(State::End));
-                return;
+    audio.set_volumes(
+        settings.music_volume.fraction(),
+        settings.effects_volume.fraction(),
+    );
+
+    let empty_button_texture = assets.texture(&theme.button).clone();
+    let attack_button_texture = assets.texture("button/attack").clone();
+    let switch_button_texture = assets.texture("button/switch").clone();
+    let forfeit_button_texture = assets.texture("button/forfeit").clone();
+    let health_bar_texture = assets.texture("ui/health-bar").clone();
+
+    let battle_textures = || BattleTextures {
+        empty_button: &empty_button_texture,
+        attack_button: &attack_button_texture,
+        switch_button: &switch_button_texture,
+        forfeit_button: &forfeit_button_texture,
+        health_bar: &health_bar_texture,
+        animations: &animations,
+        sprites: &sprites,
+    };
+    let overworld_assets = || OverworldAssets {
+        entity_textures: &entity_textures,
+        empty_button: &empty_button_texture,
+        trainers: &trainers,
+        trainer_sprites: &trainer_sprites,
+        items: &items,
+        health_bar: &health_bar_texture,
+        sprites: &sprites,
+    };
+    let team_builder =
+        |title: &str| TeamBuilder::new(title, &entity_textures, &empty_button_texture);
+    let online_team_builder = || {
+        let mut builder = team_builder("Pick your team");
+        builder.set_ruleset(Ruleset::competitive());
+        builder
+    };
+
+    let mut scene = Scene::ModeSelect;
+
+    // game loop
+    loop {
+        // refresh frame
+        clear_background(BLACK);
+        layout::begin_frame();
+        gamepad::update();
+        ui::begin_frame();
+
+        let mut open_settings = false;
+        let next_scene = match scene {
+            Scene::ModeSelect => {
+                match select_mode(&empty_button_texture, Path::new(SAVE_PATH).exists()) {
+                    Some(Mode::Adventure) => {
+                        let mut builder = team_builder("Pick your starter");
+                        builder.set_ruleset(Ruleset {
+                            max_team_size: 1,
+                            ..Default::default()
+                        });
+                        Some(Scene::BuildTeams {
+                            mode: Mode::Adventure,
+                            builder,
+                            built: Vec::new(),
+                        })
+                    }
+                    Some(Mode::Continue) => Some(
+                        match SaveData::load(SAVE_PATH).and_then(|save| {
+                            Overworld::from_save(MAPS_DIR, &save, overworld_assets())
+                        }) {
+                            Ok(mut overworld) => {
+                                overworld.set_save_path(SAVE_PATH);
+                                overworld.set_dialogue_settings(settings.dialogue());
+                                Scene::Overworld {
+                                    overworld: Box::new(overworld),
+                                    battle: None,
+                                }
+                            }
+                            Err(e) => Scene::Message(format!("Could not load the save: {}", e)),
+                        },
+                    ),
+                    Some(Mode::SinglePlayer) => Some(Scene::BuildTeams {
+                        mode: Mode::SinglePlayer,
+                        builder: team_builder("Pick your team"),
+                        built: Vec::new(),
+                    }),
+                    Some(Mode::HotSeat) => Some(Scene::BuildTeams {
+                        mode: Mode::HotSeat,
+                        builder: team_builder("Player 1, pick your team"),
+                        built: Vec::new(),
+                    }),
+                    Some(Mode::Host) => Some(Scene::BuildTeams {
+                        mode: Mode::Host,
+                        builder: online_team_builder(),
+                        built: Vec::new(),
+                    }),
+                    Some(Mode::Join(_)) => {
+                        Some(Scene::EnterAddress(format!("127.0.0.1:{}", DEFAULT_PORT)))
+                    }
+                    Some(Mode::Settings) => Some(Scene::Settings {
+                        screen: SettingsScreen::new(&empty_button_texture),
+                        previous: None,
+                    }),
+                    None => None,
+                }
+            }
+            Scene::EnterAddress(ref mut address) => match enter_address(address) {
+                true => Some(Scene::BuildTeams {
+                    mode: Mode::Join(address.clone()),
+                    builder: online_team_builder(),
+                    built: Vec::new(),
+                }),
+                false => None,
+            },
+            Scene::BuildTeams {
+                ref mode,
+                ref mut builder,
+                ref mut built,
+            } => match builder.update() {
+                Some(team) => {
+                    built.push(team);
+                    match mode {
+                        Mode::Adventure => {
+                            let team = built.pop().unwrap();
+                            Some(
+                                match Overworld::new(MAPS_DIR, START_MAP, team, overworld_assets())
+                                {
+                                    Ok(mut overworld) => {
+                                        overworld.set_save_path(SAVE_PATH);
+                                        overworld.set_dialogue_settings(settings.dialogue());
+                                        Scene::Overworld {
+                                            overworld: Box::new(overworld),
+                                            battle: None,
+                                        }
+                                    }
+                                    Err(e) => {
+                                        Scene::Message(format!("Could not load the map: {}", e))
+                                    }
+                                },
+                            )
+                        }
+                        // neither of these builds a team
+                        Mode::Continue | Mode::Settings => Some(Scene::ModeSelect),
+                        Mode::SinglePlayer if built.len() < 2 => {
+                            *builder = team_builder("Pick the opponent team");
+                            builder.allow_random();
+                            None
+                        }
+                        Mode::SinglePlayer => {
+                            let enemy_team = built.pop().unwrap();
+                            let player_team = built.pop().unwrap();
+                            let sides = [
+                                Side::new("Player", player_team, Controller::Human),
+                                Side::new("Enemy", enemy_team, Controller::Computer),
+                            ];
+                            Some(Scene::Battle(Box::new(with_settings(
+                                Battle::new(sides, battle_textures()),
+                                &settings,
+                            ))))
+                        }
+                        Mode::HotSeat if built.len() < 2 => {
+                            *builder = team_builder("Player 2, pick your team");
+                            None
+                        }
+                        Mode::HotSeat => {
+                            let enemy_team = built.pop().unwrap();
+                            let player_team = built.pop().unwrap();
+                            let sides = [
+                                Side::new("Player 1", player_team, Controller::Human),
+                                Side::new("Player 2", enemy_team, Controller::Human),
+                            ];
+                            Some(Scene::Battle(Box::new(with_settings(
+                                Battle::new(sides, battle_textures()),
+                                &settings,
+                            ))))
+                        }
+                        Mode::Host => {
+                            let team = built.pop().unwrap();
+                            Some(match TcpTransport::listen(("0.0.0.0", DEFAULT_PORT)) {
+                                Ok(listener) => Scene::HostWaiting { listener, team },
+                                Err(e) => Scene::Message(format!("Could not host: {}", e)),
+                            })
+                        }
+                        Mode::Join(address) => {
+                            let team = built.pop().unwrap();
+                            Some(match TcpTransport::connect(address.as_str()) {
+                                Ok(transport) => {
+                                    let mut handshake =
+                                        Handshake::join(Box::new(transport), team_members(&team));
+                                    handshake.set_ruleset(Ruleset::competitive());
+                                    Scene::Connecting { handshake, team }
+                                }
+                                Err(e) => Scene::Message(format!("Could not join: {}", e)),
+                            })
+                        }
+                    }
+                }
+                None => None,
+            },
+            Scene::HostWaiting {
+                ref listener,
+                ref mut team,
+            } => {
+                draw_text(
+                    format!("Waiting for a player to join on port {}...", DEFAULT_PORT).as_str(),
+                    100.0,
+                    300.0,
+                    40.0,
+                    WHITE,
+                );
+                match TcpTransport::accept(listener) {
+                    Ok(Some(transport)) => {
+                        let mut handshake = Handshake::host(
+                            Box::new(transport),
+                            team_members(team),
+                            ::rand::random(),
+                        );
+                        handshake.set_ruleset(Ruleset::competitive());
+                        Some(Scene::Connecting {
+                            handshake,
+                            team: std::mem::take(team),
+                        })
+                    }
+                    Ok(None) => None,
+                    Err(e) => Some(Scene::Message(format!("Could not host: {}", e))),
+                }
+            }
+            Scene::Connecting {
+                ref mut handshake,
+                ref mut team,
+            } => {
+                draw_text("Connecting...", 100.0, 300.0, 40.0, WHITE);
+                match handshake.poll() {
+                    Ok(Some(session)) => {
+                        // the handshake has already checked the team against the ruleset.
+                        let remote_team =
+                            TeamMember::build_team(session.remote_team(), &entity_textures)
+                                .unwrap_or_default();
+                        let local = Side::new("You", std::mem::take(team), Controller::Human);
+                        let remote = Side::new("Your opponent", remote_team, Controller::Remote);
+
+                        // both instances order the sides the same way, host first.
+                        let sides = match session.role().side() {
+                            0 => [local, remote],
+                            _ => [remote, local],
+                        };
+                        Some(Scene::Battle(Box::new(with_settings(
+                            Battle::networked(sides, battle_textures(), session),
+                            &settings,
+                        ))))
+                    }
+                    Ok(None) => None,
+                    Err(e) => Some(Scene::Message(format!("Could not connect: {}", e))),
+                }
             }
+            Scene::Message(ref message) => {
+                draw_multiline_text(message, 100.0, 300.0, 40.0, Some(1.5), WHITE);
+                match ui::advance_pressed() {
+                    true => Some(Scene::ModeSelect),
+                    false => None,
+                }
+            }
+            Scene::Battle(ref mut battle) => match battle.update() {
+                true => Some(Scene::ModeSelect),
+                false => None,
+            },
+            Scene::Overworld {
+                ref mut overworld,
+                ref mut battle,
+            } => {
+                match battle {
+                    // the battle runs as a sub-scene and hands the team back when it ends
+                    Some(current) => {
+                        if current.update() {
+                            let result = match current.winner() {
+                                Some(0) => BattleResult::Won,
+                                Some(_) => BattleResult::Lost,
+                                None => BattleResult::Escaped,
+                            };
+                            let recruited = current.take_recruited();
+                            let inventory = current.take_inventory().unwrap_or_default();
+                            let [player, _] = battle.take().unwrap().into_sides();
+                            overworld.finish_battle(player.team, inventory, result);
+                            if let Some(entity) = recruited {
+                                overworld.recruit(entity);
+                            }
+                        }
+                        None
+                    }
+                    None => match overworld.update() {
+                        Some(OverworldEvent::TrainerBattle {
+                            name,
+                            team,
+                            strategy,
+                        }) => {
+                            let mut trainer = Side::new(name.as_str(), team, Controller::Computer);
+                            trainer.strategy = strategy;
+                            let sides = [
+                                Side::new("Player", overworld.take_team(), Controller::Human),
+                                trainer,
+                            ];
+                            let mut trainer_battle =
+                                with_settings(Battle::new(sides, battle_textures()), &settings);
+                            trainer_battle.set_progression(overworld.progression().clone());
+                            trainer_battle.set_experience();
+                            trainer_battle.set_inventory(overworld.take_inventory(), &items);
+                            *battle = Some(Box::new(trainer_battle));
+                            None
+                        }
+                        Some(OverworldEvent::WildBattle { team }) => {
+                            let sides = [
+                                Side::new("Player", overworld.take_team(), Controller::Human),
+                                Side::new("Wild", team, Controller::Computer),
+                            ];
+                            let mut wild =
+                                with_settings(Battle::new(sides, battle_textures()), &settings);
+                            wild.set_wild();
+                            wild.set_progression(overworld.progression().clone());
+                            wild.set_experience();
+                            wild.set_inventory(overworld.take_inventory(), &items);
+                            *battle = Some(Box::new(wild));
+                            None
+                        }
+                        Some(OverworldEvent::Credits) => Some(Scene::Credits {
+                            lines: credits(overworld),
+                            scroll: 0.0,
+                        }),
+                        Some(OverworldEvent::Settings) => {
+                            open_settings = true;
+                            None
+                        }
+                        None => None,
+                    },
+                }
+            }
+            Scene::Credits {
+                ref lines,
+                ref mut scroll,
+            } => {
+                *scroll += CREDITS_SPEED * get_frame_time();
+                let top = layout::height() - *scroll;
+                for (i, line) in lines.iter().enumerate() {
+                    let width = measure_text(line, None, 40, 1.0).width;
+                    draw_text(
+                        line,
+                        (layout::width() - width) / 2.0,
+                        top + 60.0 * i as f32,
+                        40.0,
+                        WHITE,
+                    );
+                }
+
+                let finished = top + 60.0 * (lines.len() as f32) < 0.0;
+                match finished || ui::advance_pressed() {
+                    true => Some(Scene::ModeSelect),
+                    false => None,
+                }
+            }
+            Scene::Settings {
+                ref mut screen,
+                ref mut previous,
+            } => {
+                let before = settings.clone();
+                let closed = screen.update(&mut settings);
+                if settings != before {
+                    apply_settings(&settings, &before, &mut audio);
+                    if let Some(Scene::Overworld { overworld, .. }) = previous.as_deref_mut() {
+                        overworld.set_dialogue_settings(settings.dialogue());
+                    }
+                    if let Err(e) = settings.write(SETTINGS_PATH) {
+                        eprintln!("Could not save the settings: {}", e);
+                    }
+                }
+                match closed {
+                    true => Some(previous.take().map_or(Scene::ModeSelect, |scene| *scene)),
+                    false => None,
+                }
+            }
+        };
+
+        if let Some(next_scene) = next_scene {
+            scene = next_scene;
+        }
+        // the settings keep hold of the scene they were opened from
+        if open_settings {
+            let previous = std::mem::replace(&mut scene, Scene::ModeSelect);
+            scene = Scene::Settings {
+                screen: SettingsScreen::new(&empty_button_texture),
+                previous: Some(Box::new(previous)),
+            };
         }
+        audio.play_music(scene_music(&scene));
+        audio.update();
+
+        ui::end_frame();
+        next_frame().await;
     }
+}
 
-    fn dialogue(&mut self, transition_state: Box<State>) {
-        let s = match self.text_queue.get(0) {
-            Some(s) => s,
-            None => {
-                self.state = *transition_state;
-                return;
-            }
+/// The music for a scene, victory music plays once the player has won a battle.
+fn scene_music(scene: &Scene) -> Option<Music> {
+    let battle_music = |battle: &Battle| match battle.winner() {
+        Some(side) if battle.sides()[side].controller == Controller::Human => Music::Victory,
+        _ => Music::Battle,
+    };
+    match scene {
+        Scene::Battle(battle) => Some(battle_music(battle)),
+        Scene::Overworld {
+            battle: Some(battle),
+            ..
+        } => Some(battle_music(battle)),
+        Scene::Overworld { battle: None, .. } => Some(Music::Overworld),
+        Scene::Credits { .. } => Some(Music::Victory),
+        Scene::Settings {
+            previous: Some(previous),
+            ..
+        } => scene_music(previous),
+        _ => None,
+    }
+}
+
+/// Set up a new battle the way the player's settings ask for.
+fn with_settings<'a>(mut battle: Battle<'a>, settings: &Settings) -> Battle<'a> {
+    battle.set_dialogue_settings(settings.dialogue());
+    battle.set_animations(settings.animations);
+    battle
+}
+
+/// Change the window, controls and volumes after the settings have changed.
+fn apply_settings(settings: &Settings, before: &Settings, audio: &mut Audio) {
+    if settings.fullscreen != before.fullscreen {
+        set_fullscreen(settings.fullscreen);
+    }
+    let resized =
+        settings.resolution != before.resolution || settings.fullscreen != before.fullscreen;
+    if !settings.fullscreen && resized {
+        request_new_screen_size(
+            settings.resolution.width as f32,
+            settings.resolution.height as f32,
+        );
+    }
+    ui::set_key_bindings(settings.keys);
+    audio.set_volumes(
+        settings.music_volume.fraction(),
+        settings.effects_volume.fraction(),
+    );
+}
+
+/// Draw the mode buttons and return the mode that was clicked, if any.
+/// Continuing is only offered when there is a save.
+fn select_mode(empty_button_texture: &Texture2D, can_continue: bool) -> Option<Mode> {
+    let mut modes = vec![
+        (Mode::Adventure, "Adventure"),
+        (Mode::SinglePlayer, "1 Player"),
+        (Mode::HotSeat, "2 Players"),
+        (Mode::Host, "Host online"),
+        (Mode::Join(String::new()), "Join online"),
+        (Mode::Settings, "Settings"),
+    ];
+    if can_continue {
+        modes.insert(0, (Mode::Continue, "Continue"));
+    }
+
+    let mut selected = None;
+    for (i, (mode, label)) in modes.into_iter().enumerate() {
+        let mut link = ButtonLink {
+            link: mode,
+            button: Button::text(
+                empty_button_texture,
+                label,
+                100.0,
+                200.0 + (75.0 * i as f32),
+            ),
         };
-        draw_rectangle(0.0, 675.0, screen_width(), 200.0, BLACK);
-        draw_multiline_text(s, 50.0, 725.0, 40.0, Some(1.5), WHITE);
 
-        if is_mouse_button_pressed(MouseButton::Left) {
-            self.text_queue.pop_front();
+        if link.button.clicked() {
+            selected = Some(link.link);
         }
+        link.button.draw();
     }
+    selected
+}
 
-    fn switch_state(&mut self) {
-        let mut links: Vec<ButtonLink<usize>> = Vec::new();
-        for i in 0..self.player_team.entities.len() {
-            if self.player_team.entities[i].health <= 0 {
-                continue;
-            }
-            links.push(ButtonLink {
-                link: i,
-                button: Button::new(
-                    &self.empty_button_texture,
-                    1100.0,
-                    600.0 + (75.0 * (i as f32)),
-                ),
-            });
+/// Edit the address to join with the keyboard.
+/// Returns true once enter is pressed.
+fn enter_address(address: &mut String) -> bool {
+    while let Some(c) = get_char_pressed() {
+        if !c.is_control() {
+            address.push(c);
         }
+    }
+    if is_key_pressed(KeyCode::Backspace) {
+        address.pop();
+    }
 
-        // check if any of the buttons are clicked
-        for link in links.iter_mut() {
-            link.button.draw();
-            if link.button.clicked() && !self.debounce {
-                self.player_team.set_active(link.link).unwrap();
-                self.state = State::Wait;
-                self.debounce = true;
-            }
+    draw_text("Address of the host:", 100.0, 250.0, 40.0, WHITE);
+    draw_text(address.as_str(), 100.0, 300.0, 40.0, WHITE);
+    draw_text("Press enter to continue", 100.0, 350.0, 30.0, GRAY);
 
-            // draw text on top of the button
-            draw_text(
-                format!("{}", self.player_team.entities[link.link]).as_str(),
-                link.button.xpos + 10.0,
-                link.button.ypos + 40.0,
-                30.0,
-                WHITE,
-            );
-        }
+    is_key_pressed(KeyCode::Enter) && !address.is_empty()
+}
+
+/// The lines rolled in the credits, celebrating the team that finished the adventure.
+fn credits(overworld: &Overworld) -> Vec<String> {
+    let mut lines = vec![
+        String::from("Congratulations!"),
+        String::from("You finished the campaign and conquered the stack."),
+        String::new(),
+        String::from("Your team"),
+    ];
+    for entity in overworld.team().entities.iter() {
+        lines.push(format!("{}  Lv. {}", entity, entity.level));
     }
+
+    lines.push(String::new());
+    lines.push(String::from("Badges"));
+    lines.extend(overworld.progression().badges().iter().cloned());
+
+    lines.push(String::new());
+    lines.push(String::from("Thanks for playing!"));
+    lines
+}
+
+/// Key a trainer's sprite is loaded under.
+fn trainer_sprite_key(id: &str) -> String {
+    format!("trainer/{}", id)
+}
+
+fn team_members(team: &Team) -> Vec<TeamMember> {
+    team.entities.iter().map(TeamMember::from_entity).collect()
 }
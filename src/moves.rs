@@ -1,6 +1,7 @@
 use crate::entity::{Entity, EntityType, Stat};
 use std::collections::VecDeque;
 use std::fmt;
+use std::str::FromStr;
 
 #[derive(PartialEq, Eq, Hash, Copy, Clone, Debug)]
 pub enum Move {
@@ -12,6 +13,27 @@ pub enum Move {
     Math,
 }
 
+/// How well a move works against a target, going by the target's weaknesses and strengths.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum Effectiveness {
+    Super,
+    Normal,
+    NotVery,
+    /// The move does nothing at all to the target.
+    None,
+}
+
+impl fmt::Display for Effectiveness {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Effectiveness::Super => write!(f, "Super effective"),
+            Effectiveness::Normal => write!(f, "Normal damage"),
+            Effectiveness::NotVery => write!(f, "Not very effective"),
+            Effectiveness::None => write!(f, "No effect"),
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug)]
 pub struct MoveData {
     pub move_type: Move,
@@ -32,7 +54,43 @@ impl fmt::Display for Move {
     }
 }
 
+/// Parse a move from its identifier, such as `IntParse`.
+impl FromStr for Move {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Move::ALL
+            .into_iter()
+            .find(|mv| format!("{:?}", mv) == s)
+            .ok_or(())
+    }
+}
+
+/// Parse the moves an entity knows, written as `Move/Move` such as `Async/IntParse`.
+/// Like learning them, there can't be more than an entity can know or the same move twice.
+pub(crate) fn parse_moves(s: &str) -> Option<Vec<Move>> {
+    let mut moves = Vec::new();
+    for mv in s.split('/') {
+        let mv: Move = mv.trim().parse().ok()?;
+        if moves.contains(&mv) || moves.len() >= Entity::MAX_MOVES {
+            return None;
+        }
+        moves.push(mv);
+    }
+    Some(moves)
+}
+
 impl Move {
+    /// Every move in the game.
+    pub const ALL: [Move; 6] = [
+        Move::IntParse,
+        Move::Speed,
+        Move::MultiThread,
+        Move::Deadline,
+        Move::Async,
+        Move::Math,
+    ];
+
     /// Execute itself.
     /// Each move has unique functionality.
     pub fn execute(
@@ -68,11 +126,45 @@ impl Move {
         }
 
         if is_not_effective {
-            text_queue.push_back(format!("It wasn't very effective"));
+            text_queue.push_back(String::from("It wasn't very effective"));
         }
 
         if is_super_effective {
-            text_queue.push_back(format!("It was super effective"));
+            text_queue.push_back(String::from("It was super effective"));
+        }
+    }
+
+    /// A short explanation of what the move does.
+    pub fn description(&self) -> &'static str {
+        match self {
+            Move::IntParse => "Both sides handle an error, the worse handler takes more damage.",
+            Move::Speed => "Always goes first. Deals 25 damage.",
+            Move::MultiThread => "Lowers the target's accuracy by 10, Rust is immune.",
+            Move::Deadline => "Deals 30 damage.",
+            Move::Async => "Deals 30 damage.",
+            Move::Math => "Deals 25 damage.",
+        }
+    }
+
+    /// The most damage the move deals before any multipliers, None for moves that don't deal damage.
+    pub fn power(&self) -> Option<u32> {
+        match self {
+            Move::IntParse | Move::Deadline | Move::Async => Some(30),
+            Move::Speed | Move::Math => Some(25),
+            Move::MultiThread => None,
+        }
+    }
+
+    /// How well the move would work against a target.
+    pub fn effectiveness(&self, target: &Entity) -> Effectiveness {
+        if *self == Move::MultiThread && target.entity_type == EntityType::Rust {
+            Effectiveness::None
+        } else if target.weaknesses.contains(self) {
+            Effectiveness::Super
+        } else if target.strengths.contains(self) {
+            Effectiveness::NotVery
+        } else {
+            Effectiveness::Normal
         }
     }
 
@@ -95,7 +187,7 @@ fn math_move(
 ) {
     const DAMAGE: f64 = 25.0;
     text_queue.push_back(format!(
-        "with {}'s dynamic typing, an opening for an attack\ncould be calculated with ease",
+        "with {}'s dynamic typing, an opening for an attack could be calculated with ease",
         caller,
     ));
     enemy.damage((DAMAGE * attack_multiplier) as u32, Some(Move::Math));
@@ -109,7 +201,7 @@ fn async_move(
 ) {
     const DAMAGE: f64 = 30.0;
     text_queue.push_back(format!(
-        "{} unleashed attacks asynchronously, not needing to \n pause to wait for the last attack to complete.",
+        "{} unleashed attacks asynchronously, not needing to pause to wait for the last attack to complete.",
         caller
     ));
 
@@ -156,12 +248,12 @@ fn multi_thread_move(enemy: &mut Entity, text_queue: &mut VecDeque<String>) {
 
     // check if the move has no effect.
     if let EntityType::Rust = enemy.entity_type {
-        text_queue.push_back(format!("But it had no effect"));
+        text_queue.push_back(String::from("But it had no effect"));
         return;
     };
 
     text_queue.push_back(format!(
-        "A race condition was overlooked, the enemy {}'s accuracy has \nfallen due to undefined behavior",
+        "A race condition was overlooked, the enemy {}'s accuracy has fallen due to undefined behavior",
         enemy
     ));
 
@@ -198,24 +290,22 @@ fn int_parse_move(
     const SMALLER_DAMAGE: u32 = 15;
 
     text_queue.push_back(format!(
-        "A string needs to be parsed into an integer. \nThis may cause an error! {} and {} attempt to handle it",
+        "A string needs to be parsed into an integer. This may cause an error! {} and {} attempt to handle it",
         caller, enemy
     ));
 
     // closure that returns the entity specific dialogue.
-    let get_specific_dialogue = |name: &EntityType| {
-        return match name {
-            EntityType::Rust => "Rust returned a result type that can be matched on.",
-            EntityType::Cpp => "C++ needed a catch block to avoid a runtime error.",
-            EntityType::Python => "Python needed a catch block to avoid a runtime error.",
-            EntityType::Js => "JavaScript needed a catch block to avoid a runtime error.",
-            EntityType::Go => "Go has its own error type that is nil if there was no error",
-        };
+    let get_specific_dialogue = |name: &EntityType| match name {
+        EntityType::Rust => "Rust returned a result type that can be matched on.",
+        EntityType::Cpp => "C++ needed a catch block to avoid a runtime error.",
+        EntityType::Python => "Python needed a catch block to avoid a runtime error.",
+        EntityType::Js => "JavaScript needed a catch block to avoid a runtime error.",
+        EntityType::Go => "Go has its own error type that is nil if there was no error",
     };
 
     // get the entity specific dialogue and print it out.
-    text_queue.push_back(format!("{}", get_specific_dialogue(&caller.entity_type)));
-    text_queue.push_back(format!("{}", get_specific_dialogue(&enemy.entity_type)));
+    text_queue.push_back(String::from(get_specific_dialogue(&caller.entity_type)));
+    text_queue.push_back(String::from(get_specific_dialogue(&enemy.entity_type)));
 
     // the move has different functionality depending on who's error handling stat is higher.
     if caller.error_handling > enemy.error_handling {
@@ -249,8 +339,8 @@ fn int_parse_move(
     } else {
         // in the case where they both have equal error handling stats,
         // lower damage is dealt to both
-        text_queue.push_back(format!(
-            "Both languages have equal error handling abilities"
+        text_queue.push_back(String::from(
+            "Both languages have equal error handling abilities",
         ));
 
         // deal damage
@@ -261,3 +351,23 @@ fn int_parse_move(
         );
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn effectiveness_against_targets() {
+        let mut target = EntityType::Go.build(0, None);
+        target.weaknesses = vec![Move::Async];
+        target.strengths = vec![Move::Math];
+
+        assert_eq!(Move::Async.effectiveness(&target), Effectiveness::Super);
+        assert_eq!(Move::Math.effectiveness(&target), Effectiveness::NotVery);
+        assert_eq!(Move::Deadline.effectiveness(&target), Effectiveness::Normal);
+
+        let rust = EntityType::Rust.build(0, None);
+        assert_eq!(Move::MultiThread.effectiveness(&rust), Effectiveness::None);
+        assert_eq!(Move::MultiThread.power(), None);
+    }
+}
@@ -0,0 +1,487 @@
+pub mod protocol;
+pub mod transport;
+
+pub use protocol::{Command, Message, TeamMember, PROTOCOL_VERSION};
+pub use transport::{LoopbackTransport, TcpTransport, Transport};
+
+use crate::rules::{RuleViolation, Ruleset};
+use crate::Team;
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+
+/// Port used when hosting a game.
+pub const DEFAULT_PORT: u16 = 7777;
+
+#[derive(Debug)]
+pub enum NetError {
+    Io(std::io::Error),
+    Disconnected,
+    Malformed(String),
+    /// A line went on past the longest message without ending.
+    LineTooLong(usize),
+    VersionMismatch {
+        local: u32,
+        remote: u32,
+    },
+    Unexpected(Message),
+    Rejected(Vec<RuleViolation>),
+    Desync {
+        turn: u32,
+    },
+}
+
+impl fmt::Display for NetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NetError::Io(e) => write!(f, "network error: {}", e),
+            NetError::Disconnected => write!(f, "the other player disconnected"),
+            NetError::Malformed(line) => write!(f, "received a malformed message: {}", line),
+            NetError::LineTooLong(max) => {
+                write!(f, "received a message longer than {} bytes", max)
+            }
+            NetError::VersionMismatch { local, remote } => write!(
+                f,
+                "the other player is running protocol version {}, expected {}",
+                remote, local
+            ),
+            NetError::Unexpected(message) => {
+                write!(f, "received an unexpected message: {}", message)
+            }
+            NetError::Rejected(violations) => {
+                write!(f, "the other player's team breaks the rules:")?;
+                for violation in violations {
+                    write!(f, "\n{}", violation)?;
+                }
+                Ok(())
+            }
+            NetError::Desync { turn } => {
+                write!(
+                    f,
+                    "the battle went out of sync with the other player on turn {}",
+                    turn
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for NetError {}
+
+impl From<std::io::Error> for NetError {
+    fn from(e: std::io::Error) -> Self {
+        NetError::Io(e)
+    }
+}
+
+/// Hash everything about two teams that a turn can change.
+/// Both players hash their copy of the battle after each turn to detect a desync.
+pub fn state_hash(teams: [&Team; 2]) -> u64 {
+    let mut hash = Fnv1a::new();
+    for team in teams {
+        hash.write(team.get_active_index() as u64);
+        for entity in team.entities.iter() {
+            hash.write(entity.entity_type as u64);
+            for stat in [
+                entity.level,
+                entity.health,
+                entity.max_health,
+                entity.attack,
+                entity.defense,
+                entity.accuracy,
+                entity.error_handling,
+            ] {
+                hash.write(stat as u64);
+            }
+        }
+    }
+    hash.0
+}
+
+/// 64 bit FNV-1a, written out here so every build of the game hashes the same way.
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    fn new() -> Self {
+        Self(Self::OFFSET)
+    }
+
+    fn write(&mut self, value: u64) {
+        for byte in value.to_le_bytes() {
+            self.0 = (self.0 ^ byte as u64).wrapping_mul(Self::PRIME);
+        }
+    }
+}
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum Role {
+    Host,
+    Join,
+}
+
+impl Role {
+    /// The battle side this player controls, the host is always the first side.
+    pub fn side(&self) -> usize {
+        match self {
+            Role::Host => 0,
+            Role::Join => 1,
+        }
+    }
+}
+
+/// Exchanges versions, the seed and team definitions before a battle.
+pub struct Handshake {
+    transport: Option<Box<dyn Transport>>,
+    role: Role,
+    team: Vec<TeamMember>,
+    seed: Option<u64>,
+    remote_team: Option<Vec<TeamMember>>,
+    greeted: bool,
+    ruleset: Ruleset,
+}
+
+impl Handshake {
+    pub fn host(transport: Box<dyn Transport>, team: Vec<TeamMember>, seed: u64) -> Self {
+        Self {
+            transport: Some(transport),
+            role: Role::Host,
+            team,
+            seed: Some(seed),
+            remote_team: None,
+            greeted: false,
+            ruleset: Ruleset::default(),
+        }
+    }
+
+    pub fn join(transport: Box<dyn Transport>, team: Vec<TeamMember>) -> Self {
+        Self {
+            transport: Some(transport),
+            role: Role::Join,
+            team,
+            seed: None,
+            remote_team: None,
+            greeted: false,
+            ruleset: Ruleset::default(),
+        }
+    }
+
+    /// Set the rules the other player's team is checked against.
+    pub fn set_ruleset(&mut self, ruleset: Ruleset) {
+        self.ruleset = ruleset;
+    }
+
+    fn greet(&mut self, transport: &mut dyn Transport) -> Result<(), NetError> {
+        let seed = match self.role {
+            Role::Host => self.seed,
+            Role::Join => None,
+        };
+        transport.send(&Message::Hello {
+            version: PROTOCOL_VERSION,
+            seed,
+        })?;
+        transport.send(&Message::Team(self.team.clone()))?;
+        self.greeted = true;
+        Ok(())
+    }
+
+    /// Make progress on the handshake.
+    /// Returns the session once both players know everything needed to start.
+    pub fn poll(&mut self) -> Result<Option<Session>, NetError> {
+        let mut transport = match self.transport.take() {
+            Some(transport) => transport,
+            None => return Err(NetError::Disconnected),
+        };
+        let result = self.poll_transport(transport.as_mut());
+
+        match result {
+            Ok(true) => Ok(Some(Session {
+                transport,
+                role: self.role,
+                seed: self.seed.unwrap_or_default(),
+                remote_team: self.remote_team.take().unwrap_or_default(),
+                commands: VecDeque::new(),
+                local_hashes: HashMap::new(),
+                remote_hashes: HashMap::new(),
+            })),
+            Ok(false) => {
+                self.transport = Some(transport);
+                Ok(None)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    fn poll_transport(&mut self, transport: &mut dyn Transport) -> Result<bool, NetError> {
+        // the host speaks first, the joining player answers once it has checked the version.
+        if self.role == Role::Host && !self.greeted {
+            self.greet(transport)?;
+        }
+
+        while let Some(message) = transport.try_recv()? {
+            match message {
+                Message::Hello { version, seed } => {
+                    if version != PROTOCOL_VERSION {
+                        return Err(NetError::VersionMismatch {
+                            local: PROTOCOL_VERSION,
+                            remote: version,
+                        });
+                    }
+                    match (self.role, seed) {
+                        (Role::Join, Some(seed)) => self.seed = Some(seed),
+                        (Role::Host, None) => {}
+                        _ => return Err(NetError::Unexpected(Message::Hello { version, seed })),
+                    }
+                    if !self.greeted {
+                        self.greet(transport)?;
+                    }
+                }
+                Message::Team(members) if self.greeted => {
+                    self.check_team(&members)?;
+                    self.remote_team = Some(members);
+                }
+                message => return Err(NetError::Unexpected(message)),
+            }
+
+            if self.greeted && self.remote_team.is_some() {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+}
+
+impl Handshake {
+    fn check_team(&self, members: &[TeamMember]) -> Result<(), NetError> {
+        let team = TeamMember::build_team(members, &HashMap::new()).map_err(|_| {
+            NetError::Rejected(vec![RuleViolation::TooManyEntities {
+                count: members.len(),
+                max: Team::CAPACITY,
+            }])
+        })?;
+        self.ruleset.validate(&team).map_err(NetError::Rejected)
+    }
+}
+
+/// An established connection to the other player during a battle.
+pub struct Session {
+    transport: Box<dyn Transport>,
+    role: Role,
+    seed: u64,
+    remote_team: Vec<TeamMember>,
+    commands: VecDeque<(u32, Command)>,
+    local_hashes: HashMap<u32, u64>,
+    remote_hashes: HashMap<u32, u64>,
+}
+
+impl Session {
+    pub fn role(&self) -> Role {
+        self.role
+    }
+
+    /// The seed both players use for the battle's random numbers.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn remote_team(&self) -> &[TeamMember] {
+        &self.remote_team
+    }
+
+    pub fn send_command(&mut self, turn: u32, command: Command) -> Result<(), NetError> {
+        self.transport.send(&Message::Command { turn, command })
+    }
+
+    /// Read every message that has arrived, checking any state hashes received.
+    pub fn poll(&mut self) -> Result<(), NetError> {
+        while let Some(message) = self.transport.try_recv()? {
+            match message {
+                Message::Command { turn, command } => self.commands.push_back((turn, command)),
+                Message::Hash { turn, hash } => {
+                    self.remote_hashes.insert(turn, hash);
+                    self.check_hash(turn)?;
+                }
+                message => return Err(NetError::Unexpected(message)),
+            }
+        }
+        Ok(())
+    }
+
+    /// Take the next command the other player made for the given turn.
+    pub fn next_command(&mut self, turn: u32) -> Option<Command> {
+        match self.commands.front() {
+            Some(&(command_turn, command)) if command_turn == turn => {
+                self.commands.pop_front();
+                Some(command)
+            }
+            _ => None,
+        }
+    }
+
+    /// Send the hash of the local state after a turn and compare it if the other player's has arrived.
+    pub fn submit_hash(&mut self, turn: u32, hash: u64) -> Result<(), NetError> {
+        self.transport.send(&Message::Hash { turn, hash })?;
+        self.local_hashes.insert(turn, hash);
+        self.check_hash(turn)
+    }
+
+    fn check_hash(&mut self, turn: u32) -> Result<(), NetError> {
+        if let (Some(local), Some(remote)) =
+            (self.local_hashes.get(&turn), self.remote_hashes.get(&turn))
+        {
+            if local != remote {
+                return Err(NetError::Desync { turn });
+            }
+            self.local_hashes.remove(&turn);
+            self.remote_hashes.remove(&turn);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::moves::Move;
+    use rand::prelude::*;
+    use std::collections::HashMap;
+
+    fn members(types: &[crate::entity::EntityType]) -> Vec<TeamMember> {
+        types
+            .iter()
+            .map(|&entity_type| TeamMember {
+                entity_type,
+                level: 0,
+            })
+            .collect()
+    }
+
+    fn build_team(members: &[TeamMember]) -> Team {
+        TeamMember::build_team(members, &HashMap::new()).unwrap()
+    }
+
+    fn connect(host_team: Vec<TeamMember>, join_team: Vec<TeamMember>) -> (Session, Session) {
+        let (a, b) = LoopbackTransport::pair();
+        let mut host = Handshake::host(Box::new(a), host_team, 1234);
+        let mut join = Handshake::join(Box::new(b), join_team);
+        let (mut host_session, mut join_session) = (None, None);
+
+        while host_session.is_none() || join_session.is_none() {
+            if host_session.is_none() {
+                host_session = host.poll().unwrap();
+            }
+            if join_session.is_none() {
+                join_session = join.poll().unwrap();
+            }
+        }
+        (host_session.unwrap(), join_session.unwrap())
+    }
+
+    #[test]
+    fn handshake_exchanges_teams_and_seed() {
+        use crate::entity::EntityType::*;
+        let (host, join) = connect(members(&[Rust, Python]), members(&[Go]));
+
+        assert_eq!(host.seed(), join.seed());
+        assert_eq!(host.remote_team(), members(&[Go]).as_slice());
+        assert_eq!(join.remote_team(), members(&[Rust, Python]).as_slice());
+        assert_eq!(join.role().side(), 1);
+    }
+
+    #[test]
+    fn version_mismatch() {
+        let (mut a, b) = LoopbackTransport::pair();
+        let mut join = Handshake::join(Box::new(b), Vec::new());
+        a.send(&Message::Hello {
+            version: PROTOCOL_VERSION + 1,
+            seed: Some(1),
+        })
+        .unwrap();
+
+        assert!(matches!(join.poll(), Err(NetError::VersionMismatch { .. })));
+    }
+
+    #[test]
+    fn rejects_team_breaking_rules() {
+        use crate::entity::EntityType::*;
+        let (a, mut b) = LoopbackTransport::pair();
+        let mut host = Handshake::host(Box::new(a), members(&[Rust]), 1);
+        host.set_ruleset(Ruleset::competitive());
+        host.poll().unwrap();
+
+        b.send(&Message::Hello {
+            version: PROTOCOL_VERSION,
+            seed: None,
+        })
+        .unwrap();
+        b.send(&Message::Team(members(&[Go, Go]))).unwrap();
+
+        assert!(matches!(
+            host.poll(),
+            Err(NetError::Rejected(violations)) if violations == vec![RuleViolation::DuplicateSpecies(Go)]
+        ));
+    }
+
+    #[test]
+    fn lockstep_turns_stay_in_sync() {
+        use crate::entity::EntityType::*;
+        let host_members = members(&[Rust, Python]);
+        let join_members = members(&[Cpp, Js]);
+        let (mut host, mut join) = connect(host_members.clone(), join_members.clone());
+
+        // each instance builds both teams in the same order, the host's team first.
+        let mut host_teams = [build_team(&host_members), build_team(host.remote_team())];
+        let mut join_teams = [build_team(join.remote_team()), build_team(&join_members)];
+        let mut host_rng = StdRng::seed_from_u64(host.seed());
+        let mut join_rng = StdRng::seed_from_u64(join.seed());
+
+        for turn in 0..5 {
+            host.send_command(turn, Command::Move(Move::Speed)).unwrap();
+            join.send_command(turn, Command::Move(Move::Deadline))
+                .unwrap();
+            host.poll().unwrap();
+            join.poll().unwrap();
+            assert_eq!(host.next_command(turn), Some(Command::Move(Move::Deadline)));
+            assert_eq!(join.next_command(turn), Some(Command::Move(Move::Speed)));
+
+            for (teams, rng) in [
+                (&mut host_teams, &mut host_rng),
+                (&mut join_teams, &mut join_rng),
+            ] {
+                let (first, second) = teams.split_at_mut(1);
+                let a = first[0].get_active().unwrap();
+                let b = second[0].get_active().unwrap();
+                a.queue_move(Move::Speed);
+                b.queue_move(Move::Deadline);
+                crate::execute_moves(a, b, &mut VecDeque::new(), rng);
+            }
+
+            let host_hash = state_hash([&host_teams[0], &host_teams[1]]);
+            let join_hash = state_hash([&join_teams[0], &join_teams[1]]);
+            host.submit_hash(turn, host_hash).unwrap();
+            join.submit_hash(turn, join_hash).unwrap();
+            host.poll().unwrap();
+            join.poll().unwrap();
+        }
+    }
+
+    #[test]
+    fn state_hash_is_fixed() {
+        // pinned so builds from any compiler or platform agree on it
+        let empty = Team::new();
+        assert_eq!(state_hash([&empty, &empty]), 0x8820_1fb9_60ff_6465);
+    }
+
+    #[test]
+    fn desync_detected() {
+        use crate::entity::EntityType::*;
+        let (mut host, mut join) = connect(members(&[Rust]), members(&[Go]));
+
+        host.submit_hash(0, 1).unwrap();
+        join.submit_hash(0, 2).unwrap();
+        assert!(matches!(host.poll(), Err(NetError::Desync { turn: 0 })));
+        assert!(matches!(join.poll(), Err(NetError::Desync { turn: 0 })));
+    }
+}
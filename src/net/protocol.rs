@@ -0,0 +1,260 @@
+use super::NetError;
+use crate::entity::{Entity, EntityType};
+use crate::moves::Move;
+use crate::{Team, TeamError};
+
+use macroquad::texture::Texture2D;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// Version of the wire protocol, both players must be running the same one.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// An entity as it is described to the other player.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct TeamMember {
+    pub entity_type: EntityType,
+    pub level: u32,
+}
+
+impl TeamMember {
+    pub fn from_entity(entity: &Entity) -> Self {
+        Self {
+            entity_type: entity.entity_type,
+            level: entity.level,
+        }
+    }
+
+    pub fn build(&self, textures: &HashMap<EntityType, Texture2D>) -> Entity {
+        self.entity_type
+            .build(self.level, textures.get(&self.entity_type).cloned())
+    }
+
+    /// Build a whole team from the descriptions of its members.
+    pub fn build_team(
+        members: &[TeamMember],
+        textures: &HashMap<EntityType, Texture2D>,
+    ) -> Result<Team, TeamError> {
+        let mut team = Team::new();
+        for member in members {
+            team.push(member.build(textures))?;
+        }
+        Ok(team)
+    }
+}
+
+/// Parse a member written as `Type:level`, such as `Rust:5`. The level defaults to zero.
+impl FromStr for TeamMember {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, level) = match s.split_once(':') {
+            Some((name, level)) => (name, level.parse().map_err(|_| ())?),
+            None => (s, 0),
+        };
+        Ok(Self {
+            entity_type: name.trim().parse()?,
+            level,
+        })
+    }
+}
+
+/// An action a player takes during a turn.
+/// `Switch` can be sent any number of times before the `Move` or `Forfeit` ending the turn.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum Command {
+    Move(Move),
+    Switch(usize),
+    Forfeit,
+}
+
+/// Every message that can be sent between two game instances.
+/// Each message is a single line of space separated words.
+#[derive(PartialEq, Clone, Debug)]
+pub enum Message {
+    /// Sent first by both players, the host includes the battle seed.
+    Hello {
+        version: u32,
+        seed: Option<u64>,
+    },
+    Team(Vec<TeamMember>),
+    Command {
+        turn: u32,
+        command: Command,
+    },
+    /// Hash of the battle state after a turn was resolved.
+    Hash {
+        turn: u32,
+        hash: u64,
+    },
+}
+
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Message::Hello {
+                version,
+                seed: Some(seed),
+            } => write!(f, "HELLO {} {}", version, seed),
+            Message::Hello {
+                version,
+                seed: None,
+            } => write!(f, "HELLO {} -", version),
+            Message::Team(members) => {
+                write!(f, "TEAM")?;
+                for member in members {
+                    write!(f, " {:?}:{}", member.entity_type, member.level)?;
+                }
+                Ok(())
+            }
+            Message::Command { turn, command } => match command {
+                Command::Move(mv) => write!(f, "MOVE {} {:?}", turn, mv),
+                Command::Switch(index) => write!(f, "SWITCH {} {}", turn, index),
+                Command::Forfeit => write!(f, "FORFEIT {}", turn),
+            },
+            Message::Hash { turn, hash } => write!(f, "HASH {} {:016x}", turn, hash),
+        }
+    }
+}
+
+impl Message {
+    /// Encode the message as a line ready to be written to a stream.
+    pub fn encode(&self) -> String {
+        format!("{}\n", self)
+    }
+
+    /// Decode a single line into a message.
+    pub fn decode(line: &str) -> Result<Self, NetError> {
+        let malformed = || NetError::Malformed(String::from(line.trim_end()));
+        let mut words = line.split_whitespace();
+        let kind = words.next().ok_or_else(malformed)?;
+
+        let number = |words: &mut std::str::SplitWhitespace| -> Result<u32, NetError> {
+            words
+                .next()
+                .and_then(|word| word.parse().ok())
+                .ok_or_else(malformed)
+        };
+
+        let message = match kind {
+            "HELLO" => {
+                let version = number(&mut words)?;
+                let seed = match words.next().ok_or_else(malformed)? {
+                    "-" => None,
+                    seed => Some(seed.parse().map_err(|_| malformed())?),
+                };
+                Message::Hello { version, seed }
+            }
+            "TEAM" => {
+                let mut members = Vec::new();
+                for word in words.by_ref() {
+                    if !word.contains(':') {
+                        return Err(malformed());
+                    }
+                    members.push(word.parse().map_err(|_| malformed())?);
+                }
+                Message::Team(members)
+            }
+            "MOVE" => {
+                let turn = number(&mut words)?;
+                let name = words.next().ok_or_else(malformed)?;
+                let mv: Move = name.parse().map_err(|_| malformed())?;
+                Message::Command {
+                    turn,
+                    command: Command::Move(mv),
+                }
+            }
+            "SWITCH" => {
+                let turn = number(&mut words)?;
+                let index = number(&mut words)? as usize;
+                Message::Command {
+                    turn,
+                    command: Command::Switch(index),
+                }
+            }
+            "FORFEIT" => Message::Command {
+                turn: number(&mut words)?,
+                command: Command::Forfeit,
+            },
+            "HASH" => {
+                let turn = number(&mut words)?;
+                let hash = words
+                    .next()
+                    .and_then(|word| u64::from_str_radix(word, 16).ok())
+                    .ok_or_else(malformed)?;
+                Message::Hash { turn, hash }
+            }
+            _ => return Err(malformed()),
+        };
+
+        if words.next().is_some() {
+            return Err(malformed());
+        }
+        Ok(message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let messages = [
+            Message::Hello {
+                version: PROTOCOL_VERSION,
+                seed: Some(42),
+            },
+            Message::Hello {
+                version: PROTOCOL_VERSION,
+                seed: None,
+            },
+            Message::Team(vec![
+                TeamMember {
+                    entity_type: EntityType::Rust,
+                    level: 3,
+                },
+                TeamMember {
+                    entity_type: EntityType::Js,
+                    level: 0,
+                },
+            ]),
+            Message::Command {
+                turn: 7,
+                command: Command::Move(Move::Async),
+            },
+            Message::Command {
+                turn: 7,
+                command: Command::Switch(2),
+            },
+            Message::Command {
+                turn: 8,
+                command: Command::Forfeit,
+            },
+            Message::Hash {
+                turn: 7,
+                hash: u64::MAX,
+            },
+        ];
+
+        for message in messages {
+            assert_eq!(Message::decode(&message.encode()).unwrap(), message);
+        }
+    }
+
+    #[test]
+    fn reject_malformed() {
+        for line in [
+            "",
+            "HELLO",
+            "MOVE 1 Teleport",
+            "TEAM Cobol:1",
+            "SWITCH 1 2 3",
+            "PING",
+        ] {
+            assert!(matches!(Message::decode(line), Err(NetError::Malformed(_))));
+        }
+    }
+}
@@ -0,0 +1,235 @@
+use super::protocol::Message;
+use super::NetError;
+
+use std::io::{ErrorKind, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{channel, Receiver, Sender, TryRecvError};
+use std::time::Duration;
+
+/// Longest a join waits for the host to answer, the window doesn't redraw while it waits.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Most bytes a line can take, far more than a full team needs.
+/// Anything longer means the other side isn't playing this game, so the connection is dropped.
+const MAX_LINE_LENGTH: usize = 4096;
+
+/// A connection to the other game instance.
+/// Receiving never blocks so it can be polled once per frame.
+pub trait Transport {
+    fn send(&mut self, message: &Message) -> Result<(), NetError>;
+
+    /// Returns the next message if one has fully arrived.
+    fn try_recv(&mut self) -> Result<Option<Message>, NetError>;
+}
+
+/// Transport over a tcp socket, messages are newline separated.
+pub struct TcpTransport {
+    stream: TcpStream,
+    buffer: Vec<u8>,
+    /// Set once the other side has closed, the lines it sent before closing are still read.
+    closed: bool,
+}
+
+impl TcpTransport {
+    fn new(stream: TcpStream) -> Result<Self, NetError> {
+        stream.set_nonblocking(true)?;
+        stream.set_nodelay(true)?;
+        Ok(Self {
+            stream,
+            buffer: Vec::new(),
+            closed: false,
+        })
+    }
+
+    /// Connect to a game hosted at the given address, giving up after a few seconds.
+    pub fn connect(address: impl ToSocketAddrs) -> Result<Self, NetError> {
+        let mut last_error = None;
+        for address in address.to_socket_addrs()? {
+            match TcpStream::connect_timeout(&address, CONNECT_TIMEOUT) {
+                Ok(stream) => return Self::new(stream),
+                Err(e) => last_error = Some(e),
+            }
+        }
+        Err(match last_error {
+            Some(e) => e.into(),
+            None => std::io::Error::new(ErrorKind::InvalidInput, "no address to connect to").into(),
+        })
+    }
+
+    /// Start listening for a player to join.
+    pub fn listen(address: impl ToSocketAddrs) -> Result<TcpListener, NetError> {
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+        Ok(listener)
+    }
+
+    /// Accept a waiting player on a listener made with `listen`, if one has connected.
+    pub fn accept(listener: &TcpListener) -> Result<Option<Self>, NetError> {
+        match listener.accept() {
+            Ok((stream, _)) => Ok(Some(Self::new(stream)?)),
+            Err(e) if e.kind() == ErrorKind::WouldBlock => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn take_line(&mut self) -> Option<String> {
+        let end = self.buffer.iter().position(|&byte| byte == b'\n')?;
+        let line: Vec<u8> = self.buffer.drain(..=end).collect();
+        Some(String::from_utf8_lossy(&line).into_owned())
+    }
+}
+
+impl Transport for TcpTransport {
+    fn send(&mut self, message: &Message) -> Result<(), NetError> {
+        let encoded = message.encode();
+        let mut bytes = encoded.as_bytes();
+
+        // the socket is non-blocking, so keep retrying until the whole line is written.
+        while !bytes.is_empty() {
+            match self.stream.write(bytes) {
+                Ok(0) => return Err(NetError::Disconnected),
+                Ok(written) => bytes = &bytes[written..],
+                Err(e) if e.kind() == ErrorKind::WouldBlock => std::thread::yield_now(),
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(())
+    }
+
+    fn try_recv(&mut self) -> Result<Option<Message>, NetError> {
+        if let Some(line) = self.take_line() {
+            return Message::decode(&line).map(Some);
+        }
+
+        let mut chunk = [0u8; 512];
+        while !self.closed && self.buffer.len() <= MAX_LINE_LENGTH {
+            match self.stream.read(&mut chunk) {
+                Ok(0) => self.closed = true,
+                Ok(read) => self.buffer.extend_from_slice(&chunk[..read]),
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        match self.take_line() {
+            Some(line) => Message::decode(&line).map(Some),
+            None if self.closed => Err(NetError::Disconnected),
+            None if self.buffer.len() > MAX_LINE_LENGTH => {
+                self.buffer.clear();
+                self.closed = true;
+                let _ = self.stream.shutdown(Shutdown::Both);
+                Err(NetError::LineTooLong(MAX_LINE_LENGTH))
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+/// In-process transport, used to battle or test without a network.
+pub struct LoopbackTransport {
+    sender: Sender<String>,
+    receiver: Receiver<String>,
+}
+
+impl LoopbackTransport {
+    /// Create two transports connected to each other.
+    pub fn pair() -> (Self, Self) {
+        let (a_sender, b_receiver) = channel();
+        let (b_sender, a_receiver) = channel();
+        (
+            Self {
+                sender: a_sender,
+                receiver: a_receiver,
+            },
+            Self {
+                sender: b_sender,
+                receiver: b_receiver,
+            },
+        )
+    }
+}
+
+impl Transport for LoopbackTransport {
+    fn send(&mut self, message: &Message) -> Result<(), NetError> {
+        self.sender
+            .send(message.encode())
+            .map_err(|_| NetError::Disconnected)
+    }
+
+    fn try_recv(&mut self) -> Result<Option<Message>, NetError> {
+        match self.receiver.try_recv() {
+            Ok(line) => Message::decode(&line).map(Some),
+            Err(TryRecvError::Empty) => Ok(None),
+            Err(TryRecvError::Disconnected) => Err(NetError::Disconnected),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::net::protocol::Command;
+
+    fn recv(transport: &mut impl Transport) -> Message {
+        loop {
+            if let Some(message) = transport.try_recv().unwrap() {
+                return message;
+            }
+            std::thread::yield_now();
+        }
+    }
+
+    #[test]
+    fn tcp_exchange() {
+        let listener = TcpTransport::listen("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let mut client = TcpTransport::connect(address).unwrap();
+        let mut server = loop {
+            if let Some(server) = TcpTransport::accept(&listener).unwrap() {
+                break server;
+            }
+        };
+
+        let message = Message::Command {
+            turn: 1,
+            command: Command::Forfeit,
+        };
+        client.send(&message).unwrap();
+        client.send(&Message::Hash { turn: 1, hash: 5 }).unwrap();
+        assert_eq!(recv(&mut server), message);
+        assert_eq!(recv(&mut server), Message::Hash { turn: 1, hash: 5 });
+
+        // a forfeit sent just before closing still arrives ahead of the disconnect
+        client.send(&message).unwrap();
+        drop(client);
+        assert_eq!(recv(&mut server), message);
+        let disconnected = loop {
+            match server.try_recv() {
+                Ok(None) => std::thread::yield_now(),
+                result => break result,
+            }
+        };
+        assert!(matches!(disconnected, Err(NetError::Disconnected)));
+    }
+
+    #[test]
+    fn endless_lines_drop_the_connection() {
+        let listener = TcpTransport::listen("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let mut server = loop {
+            if let Some(server) = TcpTransport::accept(&listener).unwrap() {
+                break server;
+            }
+        };
+
+        client.write_all(&[b'A'; MAX_LINE_LENGTH + 1]).unwrap();
+        let error = loop {
+            match server.try_recv() {
+                Ok(None) => std::thread::yield_now(),
+                result => break result,
+            }
+        };
+        assert!(matches!(error, Err(NetError::LineTooLong(MAX_LINE_LENGTH))));
+        assert!(matches!(server.try_recv(), Err(NetError::Disconnected)));
+    }
+}
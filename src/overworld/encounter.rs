@@ -0,0 +1,157 @@
+use crate::data::{DataError, Section};
+use crate::entity::EntityType;
+use crate::net::TeamMember;
+
+use rand::Rng;
+
+use std::str::FromStr;
+
+/// An entity that can appear in the wild, written as `Type:min-max:weight` such as `Js:2-4:50`.
+/// The level range and weight are optional.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct EncounterSlot {
+    pub entity_type: EntityType,
+    pub min_level: u32,
+    pub max_level: u32,
+    pub weight: u32,
+}
+
+impl FromStr for EncounterSlot {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split(':').map(str::trim);
+        let entity_type = parts.next().ok_or(())?.parse()?;
+
+        let (min_level, max_level) = match parts.next() {
+            Some(levels) => match levels.split_once('-') {
+                Some((min, max)) => (
+                    min.trim().parse().map_err(|_| ())?,
+                    max.trim().parse().map_err(|_| ())?,
+                ),
+                None => {
+                    let level = levels.parse().map_err(|_| ())?;
+                    (level, level)
+                }
+            },
+            None => (0, 0),
+        };
+
+        let weight = match parts.next() {
+            Some(weight) => weight.parse().map_err(|_| ())?,
+            None => 1,
+        };
+
+        if parts.next().is_some() || min_level > max_level {
+            return Err(());
+        }
+
+        Ok(Self {
+            entity_type,
+            min_level,
+            max_level,
+            weight,
+        })
+    }
+}
+
+/// The wild entities that can be met in a map's tall grass.
+#[derive(Clone, Debug, Default)]
+pub struct EncounterTable {
+    /// Percent chance of an encounter on each step through the grass.
+    pub rate: u32,
+    pub slots: Vec<EncounterSlot>,
+}
+
+impl EncounterTable {
+    pub fn from_section(section: &Section) -> Result<Self, DataError> {
+        let rate = section.parse_or("rate", 10)?;
+        if rate > 100 {
+            return Err(section.invalid("rate", section.require("rate")?));
+        }
+        let slots: Vec<EncounterSlot> = section.parse_list("table")?;
+        // rolling adds up every weight, so the total has to fit
+        if slots
+            .iter()
+            .try_fold(0u32, |total, slot| total.checked_add(slot.weight))
+            .is_none()
+        {
+            return Err(section.invalid("table", section.require("table")?));
+        }
+        Ok(Self { rate, slots })
+    }
+
+    /// Roll for an encounter after a step through the grass.
+    pub fn roll(&self, rng: &mut impl Rng) -> Option<TeamMember> {
+        let total: u32 = self.slots.iter().map(|slot| slot.weight).sum();
+        if total == 0 || !rng.gen_ratio(self.rate, 100) {
+            return None;
+        }
+
+        let mut pick = rng.gen_range(0..total);
+        for slot in self.slots.iter() {
+            if pick < slot.weight {
+                return Some(TeamMember {
+                    entity_type: slot.entity_type,
+                    level: rng.gen_range(slot.min_level..=slot.max_level),
+                });
+            }
+            pick -= slot.weight;
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::Document;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn parse_slots() {
+        assert_eq!(
+            "Js:2-4:50".parse::<EncounterSlot>(),
+            Ok(EncounterSlot {
+                entity_type: EntityType::Js,
+                min_level: 2,
+                max_level: 4,
+                weight: 50,
+            })
+        );
+        assert_eq!("Go:3".parse::<EncounterSlot>().unwrap().max_level, 3);
+        assert_eq!("Rust".parse::<EncounterSlot>().unwrap().weight, 1);
+        assert!("Go:5-3".parse::<EncounterSlot>().is_err());
+        assert!("Cobol:1".parse::<EncounterSlot>().is_err());
+    }
+
+    #[test]
+    fn roll_respects_table() {
+        let document =
+            Document::parse("[encounters]\nrate = 100\ntable = Js:2-4:3, Go:7:1").unwrap();
+        let table = EncounterTable::from_section(document.section("encounters").unwrap()).unwrap();
+        let mut rng = StdRng::seed_from_u64(7);
+
+        for _ in 0..100 {
+            let member = table.roll(&mut rng).unwrap();
+            match member.entity_type {
+                EntityType::Js => assert!((2..=4).contains(&member.level)),
+                EntityType::Go => assert_eq!(member.level, 7),
+                _ => panic!("rolled an entity that isn't in the table"),
+            }
+        }
+
+        let never = EncounterTable {
+            rate: 0,
+            slots: table.slots.clone(),
+        };
+        assert!((0..100).all(|_| never.roll(&mut rng).is_none()));
+
+        let huge = Document::parse("[encounters]\ntable = Js:2:4294967295, Go:7:1").unwrap();
+        assert!(matches!(
+            EncounterTable::from_section(huge.section("encounters").unwrap()),
+            Err(DataError::Invalid { line: 2, .. })
+        ));
+    }
+}
@@ -0,0 +1,348 @@
+use super::encounter::EncounterTable;
+use crate::data::{DataError, Document, Section};
+
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+/// File extension of map files.
+pub const MAP_EXTENSION: &str = "map";
+
+/// How many tiles a trainer can see in front of them.
+pub const SIGHT_RANGE: i32 = 4;
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum Tile {
+    Wall,
+    Floor,
+    /// Tall grass where wild entities can appear.
+    Grass,
+    Heal,
+    Door,
+    /// A terminal for the package registry, where entities are stored.
+    Registry,
+    /// A shop counter, which shop it is comes from the map's shop sections.
+    Shop,
+}
+
+impl Tile {
+    fn from_char(c: char) -> Option<Self> {
+        match c {
+            '#' => Some(Tile::Wall),
+            '.' | '@' => Some(Tile::Floor),
+            ',' => Some(Tile::Grass),
+            'H' => Some(Tile::Heal),
+            'D' => Some(Tile::Door),
+            'P' => Some(Tile::Registry),
+            '$' => Some(Tile::Shop),
+            _ => None,
+        }
+    }
+}
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Direction {
+    /// The change in position from taking a step in this direction.
+    pub fn offset(&self) -> (i32, i32) {
+        match self {
+            Direction::Up => (0, -1),
+            Direction::Down => (0, 1),
+            Direction::Left => (-1, 0),
+            Direction::Right => (1, 0),
+        }
+    }
+}
+
+impl FromStr for Direction {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "up" => Ok(Direction::Up),
+            "down" => Ok(Direction::Down),
+            "left" => Ok(Direction::Left),
+            "right" => Ok(Direction::Right),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Direction::Up => "up",
+            Direction::Down => "down",
+            Direction::Left => "left",
+            Direction::Right => "right",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A door tile leading to a position on another map.
+#[derive(Clone, Debug)]
+pub struct Door {
+    pub x: i32,
+    pub y: i32,
+    pub to: String,
+    pub spawn: (i32, i32),
+}
+
+/// Where a trainer from the trainers file stands on the map, they battle the player on sight.
+#[derive(Clone, Debug)]
+pub struct TrainerSpot {
+    pub x: i32,
+    pub y: i32,
+    pub facing: Direction,
+    pub id: String,
+}
+
+/// Which shop from the items file a shop tile opens.
+#[derive(Clone, Debug)]
+pub struct ShopSpot {
+    pub x: i32,
+    pub y: i32,
+    pub id: String,
+}
+
+/// A tile based map loaded from a map file.
+pub struct Map {
+    pub id: String,
+    pub name: String,
+    tiles: Vec<Vec<Tile>>,
+    pub start: (i32, i32),
+    pub doors: Vec<Door>,
+    pub trainers: Vec<TrainerSpot>,
+    pub shops: Vec<ShopSpot>,
+    pub encounters: EncounterTable,
+}
+
+/// Parse a position written as `x,y`.
+pub fn parse_position(section: &Section, key: &str) -> Result<(i32, i32), DataError> {
+    let value = section.require(key)?;
+    value
+        .split_once(',')
+        .and_then(|(x, y)| Some((x.trim().parse().ok()?, y.trim().parse().ok()?)))
+        .ok_or_else(|| section.invalid(key, value))
+}
+
+impl Map {
+    /// Load the map with the given id from a directory of map files.
+    pub fn load(dir: impl AsRef<Path>, id: &str) -> Result<Self, DataError> {
+        let path = dir.as_ref().join(format!("{}.{}", id, MAP_EXTENSION));
+        Self::from_document(id, &Document::load(path)?)
+    }
+
+    pub fn parse(id: &str, text: &str) -> Result<Self, DataError> {
+        Self::from_document(id, &Document::parse(text)?)
+    }
+
+    fn from_document(id: &str, document: &Document) -> Result<Self, DataError> {
+        let info = document.require_section("map")?;
+        let tile_section = document.require_section("tiles")?;
+
+        let mut tiles = Vec::new();
+        let mut start = None;
+        for (y, row) in tile_section.lines.iter().enumerate() {
+            let mut tile_row = Vec::new();
+            for (x, c) in row.chars().enumerate() {
+                let tile = Tile::from_char(c).ok_or_else(|| DataError::Syntax {
+                    line: tile_section.line + y + 1,
+                    message: format!("unknown tile '{}'", c),
+                })?;
+                if c == '@' {
+                    start = Some((x as i32, y as i32));
+                }
+                tile_row.push(tile);
+            }
+            tiles.push(tile_row);
+        }
+
+        let mut doors = Vec::new();
+        for section in document.sections_named("door") {
+            let (x, y) = parse_position(section, "at")?;
+            doors.push(Door {
+                x,
+                y,
+                to: String::from(section.require("to")?),
+                spawn: parse_position(section, "spawn")?,
+            });
+        }
+
+        let mut trainers = Vec::new();
+        for section in document.sections_named("trainer") {
+            let (x, y) = parse_position(section, "at")?;
+            trainers.push(TrainerSpot {
+                x,
+                y,
+                facing: section.parse("facing")?,
+                id: String::from(section.require("id")?),
+            });
+        }
+
+        let mut shops = Vec::new();
+        for section in document.sections_named("shop") {
+            let (x, y) = parse_position(section, "at")?;
+            shops.push(ShopSpot {
+                x,
+                y,
+                id: String::from(section.require("id")?),
+            });
+        }
+
+        let encounters = match document.section("encounters") {
+            Some(section) => EncounterTable::from_section(section)?,
+            None => EncounterTable::default(),
+        };
+
+        Ok(Self {
+            id: String::from(id),
+            name: String::from(info.require("name")?),
+            tiles,
+            start: start.unwrap_or((1, 1)),
+            doors,
+            trainers,
+            shops,
+            encounters,
+        })
+    }
+
+    pub fn width(&self) -> i32 {
+        self.tiles.iter().map(Vec::len).max().unwrap_or(0) as i32
+    }
+
+    pub fn height(&self) -> i32 {
+        self.tiles.len() as i32
+    }
+
+    /// The tile at a position, anything outside the map is a wall.
+    pub fn tile(&self, x: i32, y: i32) -> Tile {
+        if x < 0 || y < 0 {
+            return Tile::Wall;
+        }
+        self.tiles
+            .get(y as usize)
+            .and_then(|row| row.get(x as usize))
+            .copied()
+            .unwrap_or(Tile::Wall)
+    }
+
+    /// True if the player can stand at a position.
+    pub fn walkable(&self, x: i32, y: i32) -> bool {
+        self.tile(x, y) != Tile::Wall
+            && !self
+                .trainers
+                .iter()
+                .any(|trainer| trainer.x == x && trainer.y == y)
+    }
+
+    pub fn door_at(&self, x: i32, y: i32) -> Option<&Door> {
+        self.doors.iter().find(|door| door.x == x && door.y == y)
+    }
+
+    pub fn shop_at(&self, x: i32, y: i32) -> Option<&ShopSpot> {
+        self.shops.iter().find(|shop| shop.x == x && shop.y == y)
+    }
+
+    /// Indexes of every trainer that can see the given position.
+    pub fn spotted_by(&self, x: i32, y: i32) -> Vec<usize> {
+        let mut spotted = Vec::new();
+        for (i, trainer) in self.trainers.iter().enumerate() {
+            let (dx, dy) = trainer.facing.offset();
+            for distance in 1..=SIGHT_RANGE {
+                let (sx, sy) = (trainer.x + dx * distance, trainer.y + dy * distance);
+                if !self.walkable(sx, sy) {
+                    break;
+                }
+                if (sx, sy) == (x, y) {
+                    spotted.push(i);
+                    break;
+                }
+            }
+        }
+        spotted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEXT: &str = "
+[map]
+name = Test Town
+
+[tiles]
+#######
+#@...D#
+#.H.,,#
+#$....#
+#######
+
+[door]
+at = 5,1
+to = route
+spawn = 1,1
+
+[trainer]
+at = 5,2
+facing = left
+id = junior_dev
+
+[shop]
+at = 1,3
+id = mart
+
+[encounters]
+rate = 20
+table = Rust:3-5:1, Go:2:3
+";
+
+    #[test]
+    fn parse_map() {
+        let map = Map::parse("town", TEXT).unwrap();
+        assert_eq!(map.name, "Test Town");
+        assert_eq!((map.width(), map.height()), (7, 5));
+        assert_eq!(map.start, (1, 1));
+        assert_eq!(map.tile(2, 2), Tile::Heal);
+        assert_eq!(map.tile(-1, 0), Tile::Wall);
+        assert_eq!(map.door_at(5, 1).unwrap().to, "route");
+        assert_eq!(map.trainers[0].id, "junior_dev");
+        assert!(!map.walkable(5, 2));
+        assert_eq!(map.tile(4, 2), Tile::Grass);
+        assert_eq!(map.tile(1, 3), Tile::Shop);
+        assert_eq!(map.shop_at(1, 3).unwrap().id, "mart");
+        assert_eq!(map.encounters.rate, 20);
+        assert_eq!(map.encounters.slots.len(), 2);
+    }
+
+    #[test]
+    fn trainer_sight() {
+        let map = Map::parse("town", TEXT).unwrap();
+        assert_eq!(map.spotted_by(3, 2), vec![0]);
+        assert_eq!(map.spotted_by(1, 2), vec![0]);
+        assert!(map.spotted_by(3, 1).is_empty());
+
+        // the heal point doesn't block sight but walls do
+        let blocked = TEXT.replace("#.H.,,#", "#.H#,,#");
+        let map = Map::parse("town", &blocked).unwrap();
+        assert!(map.spotted_by(2, 2).is_empty());
+        assert_eq!(map.spotted_by(4, 2), vec![0]);
+    }
+
+    #[test]
+    fn reject_unknown_tiles() {
+        let text = TEXT.replace("#.H.,,#", "#.H.?,#");
+        assert!(matches!(
+            Map::parse("town", &text),
+            Err(DataError::Syntax { .. })
+        ));
+    }
+}
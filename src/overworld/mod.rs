@@ -0,0 +1,641 @@
+pub mod encounter;
+pub mod map;
+
+pub use encounter::{EncounterSlot, EncounterTable};
+pub use map::{Direction, Map, Tile};
+
+use crate::battle::Strategy;
+use crate::data::DataError;
+use crate::entity::{Entity, EntityType};
+use crate::inventory::Inventory;
+use crate::item::ItemCatalog;
+use crate::layout;
+use crate::pause::PauseMenu;
+use crate::progression::Progression;
+use crate::registry::Registry;
+use crate::save::{SaveData, SaveError, SavedEntity};
+use crate::shop::ShopScreen;
+use crate::sprite::SpriteBook;
+use crate::storage::Storage;
+use crate::trainer::{Trainer, TrainerBook};
+use crate::ui::{DialogueBox, DialogueSettings, NavInput};
+use crate::Team;
+
+use ::rand::rngs::StdRng;
+use ::rand::SeedableRng;
+use macroquad::prelude::*;
+
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+
+/// Size of a tile on screen in pixels.
+const TILE_SIZE: f32 = 48.0;
+
+/// Seconds between steps while a direction key is held.
+const STEP_DELAY: f32 = 0.15;
+
+/// Something that happened on the map which needs another scene.
+pub enum OverworldEvent {
+    TrainerBattle {
+        name: String,
+        team: Team,
+        strategy: Strategy,
+    },
+    /// A wild entity appeared in the tall grass.
+    WildBattle { team: Team },
+    /// The final boss was beaten.
+    Credits,
+    /// The player asked for the settings screen from the pause menu.
+    Settings,
+}
+
+/// How a battle started from the overworld ended for the player.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum BattleResult {
+    Won,
+    Lost,
+    /// The player ran away from a wild battle.
+    Escaped,
+}
+
+/// Textures and data the overworld shares with the rest of the game.
+#[derive(Clone, Copy)]
+pub struct OverworldAssets<'a> {
+    pub entity_textures: &'a HashMap<EntityType, Texture2D>,
+    pub empty_button: &'a Texture2D,
+    pub trainers: &'a TrainerBook,
+    /// Trainer sprites by trainer id, trainers without one are drawn as circles.
+    pub trainer_sprites: &'a HashMap<String, Texture2D>,
+    pub items: &'a ItemCatalog,
+    pub health_bar: &'a Texture2D,
+    pub sprites: &'a SpriteBook,
+}
+
+/// A menu drawn over the map while the player is using it.
+enum Screen<'a> {
+    Registry(Registry<'a>),
+    Shop(ShopScreen<'a>),
+    Pause(PauseMenu<'a>),
+}
+
+/// The scene where the player walks around the maps.
+pub struct Overworld<'a> {
+    maps_dir: PathBuf,
+    map: Map,
+    x: i32,
+    y: i32,
+    facing: Direction,
+    team: Team,
+    storage: Storage,
+    inventory: Inventory,
+    progression: Progression,
+    /// Where the player goes after losing a battle.
+    respawn: (String, i32, i32),
+    messages: VecDeque<String>,
+    dialogue_box: DialogueBox,
+    pending: Option<OverworldEvent>,
+    /// Id of the trainer currently being battled.
+    battling: Option<String>,
+    step_timer: f32,
+    rng: StdRng,
+    screen: Option<Screen<'a>>,
+    save_path: Option<PathBuf>,
+    assets: OverworldAssets<'a>,
+}
+
+impl<'a> Overworld<'a> {
+    pub fn new(
+        maps_dir: impl Into<PathBuf>,
+        start_map: &str,
+        team: Team,
+        assets: OverworldAssets<'a>,
+    ) -> Result<Self, DataError> {
+        let maps_dir = maps_dir.into();
+        let map = Map::load(&maps_dir, start_map)?;
+        let (x, y) = map.start;
+
+        Ok(Self {
+            maps_dir,
+            respawn: (map.id.clone(), x, y),
+            map,
+            x,
+            y,
+            facing: Direction::Down,
+            team,
+            storage: Storage::new(),
+            inventory: Inventory::default(),
+            progression: Progression::new(),
+            messages: VecDeque::new(),
+            dialogue_box: DialogueBox::default(),
+            pending: None,
+            battling: None,
+            step_timer: 0.0,
+            rng: StdRng::from_entropy(),
+            screen: None,
+            save_path: None,
+            assets,
+        })
+    }
+
+    /// Carry on an adventure from a save.
+    pub fn from_save(
+        maps_dir: impl Into<PathBuf>,
+        save: &SaveData,
+        assets: OverworldAssets<'a>,
+    ) -> Result<Self, SaveError> {
+        let team = save.build_team(assets.entity_textures)?;
+        let mut overworld = Self::new(maps_dir, &save.map, team, assets)?;
+
+        (overworld.x, overworld.y) = save.position;
+        overworld.facing = save.facing;
+        overworld.respawn = save.respawn.clone();
+        overworld.storage = save.build_storage(assets.entity_textures);
+        overworld.inventory = save.build_inventory(assets.items)?;
+        overworld.progression = Progression::restore(&save.defeated, &save.badges, save.complete);
+        Ok(overworld)
+    }
+
+    /// Save progress to a file whenever the player rests or uses the registry or a shop.
+    pub fn set_save_path(&mut self, path: impl Into<PathBuf>) {
+        self.save_path = Some(path.into());
+    }
+
+    pub fn set_dialogue_settings(&mut self, settings: DialogueSettings) {
+        self.dialogue_box.set_settings(settings);
+    }
+
+    pub fn save_data(&self) -> SaveData {
+        let mut defeated: Vec<String> = self.progression.defeated().iter().cloned().collect();
+        defeated.sort();
+
+        SaveData {
+            map: self.map.id.clone(),
+            position: (self.x, self.y),
+            facing: self.facing,
+            respawn: self.respawn.clone(),
+            money: self.inventory.money,
+            items: self.inventory.items().to_vec(),
+            team: self
+                .team
+                .entities
+                .iter()
+                .map(SavedEntity::from_entity)
+                .collect(),
+            storage: self
+                .storage
+                .entities
+                .iter()
+                .map(SavedEntity::from_entity)
+                .collect(),
+            defeated,
+            badges: self.progression.badges().to_vec(),
+            complete: self.progression.is_complete(),
+        }
+    }
+
+    /// Write the save file, if there is one.
+    fn save(&mut self) {
+        let path = match self.save_path {
+            Some(ref path) => path,
+            None => return,
+        };
+
+        match self.save_data().write(path) {
+            Ok(()) => self
+                .messages
+                .push_back(String::from("Your progress was saved.")),
+            Err(e) => self
+                .messages
+                .push_back(format!("Your progress could not be saved: {}", e)),
+        }
+    }
+
+    pub fn team(&self) -> &Team {
+        &self.team
+    }
+
+    /// Take the team out of the overworld for a battle.
+    pub fn take_team(&mut self) -> Team {
+        std::mem::take(&mut self.team)
+    }
+
+    pub fn storage(&self) -> &Storage {
+        &self.storage
+    }
+
+    pub fn inventory(&self) -> &Inventory {
+        &self.inventory
+    }
+
+    /// Take the inventory out of the overworld so items can be used in a battle.
+    pub fn take_inventory(&mut self) -> Inventory {
+        std::mem::take(&mut self.inventory)
+    }
+
+    /// Add a recruited entity to the team, or to storage if the team is full.
+    pub fn recruit(&mut self, entity: Entity) {
+        let name = format!("{}", entity);
+        match self.team.push(entity.clone()) {
+            Ok(()) => self
+                .messages
+                .push_back(format!("{} joined your team!", name)),
+            Err(_) => {
+                self.storage.deposit(entity);
+                self.messages.push_back(format!(
+                    "Your team is full, so {} was sent to storage.",
+                    name
+                ));
+            }
+        }
+    }
+
+    /// Return to the map after a battle started by an `OverworldEvent`.
+    pub fn finish_battle(&mut self, team: Team, inventory: Inventory, result: BattleResult) {
+        self.team = team;
+        self.inventory = inventory;
+        let trainer = self.battling.take();
+
+        match result {
+            BattleResult::Won | BattleResult::Escaped => {
+                match trainer.and_then(|id| self.assets.trainers.get(&id)) {
+                    Some(trainer) if result == BattleResult::Won => self.defeat_trainer(trainer),
+                    Some(_) => {}
+                    // a trainer who saw the player walk into the grass battles them next
+                    None => self.check_trainers(),
+                }
+                return;
+            }
+            BattleResult::Lost => {}
+        }
+
+        // losing sends the player back to the last place they healed.
+        self.team.heal_all();
+        let (map, x, y) = self.respawn.clone();
+        if map != self.map.id {
+            match Map::load(&self.maps_dir, &map) {
+                Ok(loaded) => self.map = loaded,
+                Err(e) => self.messages.push_back(format!("{}", e)),
+            }
+        }
+        self.x = x;
+        self.y = y;
+        self.messages
+            .push_back(String::from("Your team was defeated..."));
+        self.messages.push_back(String::from(
+            "You hurried back to the last place you rested and your team recovered.",
+        ));
+    }
+
+    /// Remember a beaten trainer and pay out their prize and badge.
+    fn defeat_trainer(&mut self, trainer: &Trainer) {
+        let victory = self.progression.record_win(trainer, self.assets.trainers);
+        self.say(trainer, &trainer.defeat);
+
+        if trainer.prize > 0 {
+            self.inventory.money += trainer.prize;
+            self.messages.push_back(format!(
+                "You got ${} for beating {}.",
+                trainer.prize, trainer.name
+            ));
+        }
+        if let Some(badge) = victory.badge {
+            self.messages.push_back(format!(
+                "You earned the {}! Entities up to level {} will now obey you.",
+                badge,
+                self.progression.level_cap()
+            ));
+        }
+        if victory.finished {
+            self.messages.push_back(format!(
+                "You beat {}, the final boss. Congratulations!",
+                trainer.name
+            ));
+            self.save();
+            self.pending = Some(OverworldEvent::Credits);
+        }
+    }
+
+    /// Queue lines of dialogue from a trainer.
+    fn say(&mut self, trainer: &Trainer, lines: &[String]) {
+        for line in lines {
+            self.messages
+                .push_back(format!("{}: {}", trainer.name, line));
+        }
+    }
+
+    pub fn progression(&self) -> &Progression {
+        &self.progression
+    }
+
+    /// Draw and update the overworld.
+    /// Returns an event when another scene needs to take over.
+    pub fn update(&mut self) -> Option<OverworldEvent> {
+        if let Some(ref mut screen) = self.screen {
+            let closed = match screen {
+                Screen::Registry(registry) => registry.update(&mut self.team, &mut self.storage),
+                Screen::Shop(shop) => shop.update(&mut self.inventory),
+                Screen::Pause(menu) => menu.update(&mut self.team, &mut self.inventory),
+            };
+            if closed {
+                let pause = match self.screen {
+                    Some(Screen::Pause(ref menu)) => {
+                        if menu.wants_settings() {
+                            self.pending = Some(OverworldEvent::Settings);
+                        }
+                        true
+                    }
+                    _ => false,
+                };
+                self.screen = None;
+                if !pause {
+                    self.save();
+                }
+            }
+            return None;
+        }
+
+        self.draw();
+
+        if let Some(message) = self.messages.front() {
+            if self.dialogue_box.update(message) {
+                self.messages.pop_front();
+            }
+            return None;
+        }
+
+        if let Some(event) = self.pending.take() {
+            return Some(event);
+        }
+
+        if NavInput::Back.pressed() {
+            self.screen = Some(Screen::Pause(PauseMenu::new(
+                self.assets.items,
+                self.assets.sprites,
+                self.assets.empty_button,
+                self.assets.health_bar,
+            )));
+            return None;
+        }
+
+        self.step_timer -= get_frame_time();
+        if self.step_timer > 0.0 {
+            return None;
+        }
+
+        let direction = if NavInput::Up.held() {
+            Direction::Up
+        } else if NavInput::Down.held() {
+            Direction::Down
+        } else if NavInput::Left.held() {
+            Direction::Left
+        } else if NavInput::Right.held() {
+            Direction::Right
+        } else {
+            return None;
+        };
+
+        self.step(direction);
+        None
+    }
+
+    /// Take a step in a direction and react to where the player ends up.
+    fn step(&mut self, direction: Direction) {
+        self.facing = direction;
+        self.step_timer = STEP_DELAY;
+
+        let (dx, dy) = direction.offset();
+        let (x, y) = (self.x + dx, self.y + dy);
+        if let Some(spot) = self.map.trainers.iter().find(|t| (t.x, t.y) == (x, y)) {
+            let id = spot.id.clone();
+            self.talk_to(&id);
+            return;
+        }
+        if !self.map.walkable(x, y) {
+            return;
+        }
+        self.x = x;
+        self.y = y;
+
+        match self.map.tile(x, y) {
+            Tile::Heal => {
+                self.team.heal_all();
+                self.respawn = (self.map.id.clone(), x, y);
+                self.messages
+                    .push_back(String::from("You rested and your team was fully restored."));
+                self.save();
+            }
+            Tile::Registry => {
+                self.screen = Some(Screen::Registry(Registry::new(self.assets.empty_button)))
+            }
+            Tile::Shop => self.open_shop(),
+            Tile::Door => self.enter_door(),
+            Tile::Grass => self.check_encounter(),
+            Tile::Floor | Tile::Wall => {}
+        }
+
+        // a wild battle that just started goes first, trainers are checked again once it is over
+        if self.pending.is_none() {
+            self.check_trainers();
+        }
+    }
+
+    fn open_shop(&mut self) {
+        let shop = self
+            .map
+            .shop_at(self.x, self.y)
+            .and_then(|spot| self.assets.items.shop(&spot.id));
+        match shop {
+            Some(shop) => {
+                self.screen = Some(Screen::Shop(ShopScreen::new(
+                    shop,
+                    self.assets.items,
+                    self.assets.empty_button,
+                )))
+            }
+            None => self.messages.push_back(String::from("The shop is closed.")),
+        }
+    }
+
+    /// Roll for a wild entity after stepping into tall grass.
+    fn check_encounter(&mut self) {
+        let member = match self.map.encounters.roll(&mut self.rng) {
+            Some(member) => member,
+            None => return,
+        };
+
+        let mut team = Team::new();
+        if team
+            .push(member.build(self.assets.entity_textures))
+            .is_err()
+        {
+            return;
+        }
+
+        self.messages
+            .push_back(format!("A wild {} appeared!", member.entity_type));
+        self.pending = Some(OverworldEvent::WildBattle { team });
+    }
+
+    fn enter_door(&mut self) {
+        let door = match self.map.door_at(self.x, self.y) {
+            Some(door) => door.clone(),
+            None => return,
+        };
+
+        match Map::load(&self.maps_dir, &door.to) {
+            Ok(map) => {
+                self.map = map;
+                (self.x, self.y) = door.spawn;
+                self.messages.push_back(self.map.name.clone());
+            }
+            Err(e) => self
+                .messages
+                .push_back(format!("The door wouldn't open: {}", e)),
+        }
+    }
+
+    /// Start a battle with the first trainer who can see the player and is ready to battle.
+    fn check_trainers(&mut self) {
+        let trainers = self.assets.trainers;
+        let spotted = self
+            .map
+            .spotted_by(self.x, self.y)
+            .into_iter()
+            .filter_map(|i| trainers.get(&self.map.trainers[i].id))
+            .find(|trainer| {
+                !self.progression.has_defeated(&trainer.id)
+                    && trainers
+                        .blocker(&trainer.id, self.progression.defeated())
+                        .is_none()
+            });
+
+        let trainer = match spotted {
+            Some(trainer) => trainer,
+            None => return,
+        };
+
+        let team = match trainer.build_team(self.assets.entity_textures) {
+            Ok(team) if !team.entities.is_empty() => team,
+            _ => return,
+        };
+
+        self.messages
+            .push_back(format!("{} spotted you!", trainer.name));
+        if let Some(ref paradigm) = trainer.paradigm {
+            self.messages.push_back(format!(
+                "{} is a framework leader who specialises in {} programming.",
+                trainer.name,
+                paradigm.to_lowercase()
+            ));
+        }
+        self.say(trainer, &trainer.intro);
+        self.messages
+            .push_back(format!("{} wants to battle!", trainer.name));
+        self.battling = Some(trainer.id.clone());
+        self.pending = Some(OverworldEvent::TrainerBattle {
+            name: trainer.name.clone(),
+            team,
+            strategy: trainer.strategy,
+        });
+    }
+
+    /// Walking into a trainer who won't battle has them say something instead.
+    fn talk_to(&mut self, id: &str) {
+        let trainers = self.assets.trainers;
+        let trainer = match trainers.get(id) {
+            Some(trainer) => trainer,
+            None => return,
+        };
+
+        if self.progression.has_defeated(id) {
+            self.say(trainer, &trainer.defeat);
+        } else if let Some(blocker) = trainers.blocker(id, self.progression.defeated()) {
+            self.messages.push_back(format!(
+                "{}: Come back once you've beaten {}.",
+                trainer.name, blocker.name
+            ));
+        }
+    }
+
+    fn draw(&self) {
+        // keep the player in the centre of the screen
+        let offset_x = layout::width() / 2.0 - (self.x as f32 + 0.5) * TILE_SIZE;
+        let offset_y = layout::height() / 2.0 - (self.y as f32 + 0.5) * TILE_SIZE;
+
+        for y in 0..self.map.height() {
+            for x in 0..self.map.width() {
+                let color = match self.map.tile(x, y) {
+                    Tile::Wall => DARKGRAY,
+                    Tile::Floor => DARKGREEN,
+                    Tile::Grass => GREEN,
+                    Tile::Heal => PINK,
+                    Tile::Door => BROWN,
+                    Tile::Registry => SKYBLUE,
+                    Tile::Shop => GOLD,
+                };
+                draw_rectangle(
+                    offset_x + x as f32 * TILE_SIZE,
+                    offset_y + y as f32 * TILE_SIZE,
+                    TILE_SIZE,
+                    TILE_SIZE,
+                    color,
+                );
+            }
+        }
+
+        for spot in self.map.trainers.iter() {
+            if let Some(sprite) = self.assets.trainer_sprites.get(&spot.id) {
+                draw_texture_ex(
+                    sprite,
+                    offset_x + spot.x as f32 * TILE_SIZE,
+                    offset_y + spot.y as f32 * TILE_SIZE,
+                    WHITE,
+                    DrawTextureParams {
+                        dest_size: Some(vec2(TILE_SIZE, TILE_SIZE)),
+                        ..Default::default()
+                    },
+                );
+                continue;
+            }
+
+            let color = match self.progression.has_defeated(&spot.id) {
+                true => GRAY,
+                false => RED,
+            };
+            draw_character(offset_x, offset_y, spot.x, spot.y, spot.facing, color);
+        }
+
+        draw_character(offset_x, offset_y, self.x, self.y, self.facing, BLUE);
+
+        draw_text(self.map.name.as_str(), 20.0, 40.0, 35.0, WHITE);
+        draw_text(
+            format!("${}", self.inventory.money).as_str(),
+            20.0,
+            80.0,
+            30.0,
+            GOLD,
+        );
+        draw_text(
+            format!("Badges: {}", self.progression.badges().len()).as_str(),
+            20.0,
+            115.0,
+            30.0,
+            WHITE,
+        );
+    }
+}
+
+/// Draw a person on the map as a circle with a dot showing which way they face.
+fn draw_character(offset_x: f32, offset_y: f32, x: i32, y: i32, facing: Direction, color: Color) {
+    let centre_x = offset_x + (x as f32 + 0.5) * TILE_SIZE;
+    let centre_y = offset_y + (y as f32 + 0.5) * TILE_SIZE;
+    let (dx, dy) = facing.offset();
+
+    draw_circle(centre_x, centre_y, TILE_SIZE * 0.4, color);
+    draw_circle(
+        centre_x + dx as f32 * TILE_SIZE * 0.25,
+        centre_y + dy as f32 * TILE_SIZE * 0.25,
+        TILE_SIZE * 0.1,
+        WHITE,
+    );
+}
@@ -0,0 +1,386 @@
+use crate::entity::Entity;
+use crate::inventory::Inventory;
+use crate::item::{Item, ItemCatalog, UseError};
+use crate::moves::Move;
+use crate::sprite::{SpriteBook, View};
+use crate::ui::{
+    draw_entity_details, AnimatedSprite, Button, ButtonLink, EntityImageParams, EntityStats,
+    NavInput,
+};
+use crate::Team;
+
+use macroquad::prelude::*;
+
+/// Buttons on the right of the pause menu.
+#[derive(PartialEq, Clone, Copy)]
+enum PauseAction {
+    Use,
+    Summary,
+    MoveUp,
+    MoveDown,
+    Settings,
+    Close,
+}
+
+/// Buttons on a team member's summary page.
+#[derive(PartialEq, Clone, Copy)]
+enum SummaryAction {
+    Previous,
+    Next,
+    Back,
+}
+
+/// Menu opened from the overworld showing the team and the player's money and items.
+/// Items can be used on the team, members can be reordered and each has a summary page.
+pub struct PauseMenu<'a> {
+    catalog: &'a ItemCatalog,
+    sprites: &'a SpriteBook,
+    selected_member: Option<usize>,
+    /// The team member whose summary page is open.
+    summary: Option<usize>,
+    selected_item: Option<String>,
+    /// What happened after the last item was used.
+    status: Option<Result<String, UseError>>,
+    /// The menu was closed to open the settings screen.
+    settings_requested: bool,
+    empty_button_texture: &'a Texture2D,
+    stats: EntityStats<'a>,
+}
+
+impl<'a> PauseMenu<'a> {
+    pub fn new(
+        catalog: &'a ItemCatalog,
+        sprites: &'a SpriteBook,
+        empty_button_texture: &'a Texture2D,
+        health_bar_texture: &'a Texture2D,
+    ) -> Self {
+        Self {
+            catalog,
+            sprites,
+            selected_member: None,
+            summary: None,
+            selected_item: None,
+            status: None,
+            settings_requested: false,
+            empty_button_texture,
+            stats: EntityStats::new(100.0, 200.0, health_bar_texture),
+        }
+    }
+
+    /// True when the player closed the menu by asking for the settings screen.
+    pub fn wants_settings(&self) -> bool {
+        self.settings_requested
+    }
+
+    /// Draw and update the menu.
+    /// Returns true once the player closes it.
+    pub fn update(&mut self, team: &mut Team, inventory: &mut Inventory) -> bool {
+        if let Some(index) = self.summary {
+            self.summary_page(team, index);
+            return false;
+        }
+
+        draw_text("Paused", 100.0, 100.0, 40.0, WHITE);
+        draw_text(
+            format!("${}", inventory.money).as_str(),
+            800.0,
+            100.0,
+            40.0,
+            GOLD,
+        );
+
+        let mut previewed = None;
+
+        // the team down the left
+        let mut members: Vec<ButtonLink<usize>> = Vec::new();
+        for (i, entity) in team.entities.iter().enumerate() {
+            let marker = match self.selected_member == Some(i) {
+                true => ">",
+                false => "",
+            };
+            let label = format!(
+                "{}{} Lv. {} {}/{}",
+                marker, entity, entity.level, entity.health, entity.max_health
+            );
+            members.push(ButtonLink {
+                link: i,
+                button: Button::text(
+                    self.empty_button_texture,
+                    label,
+                    100.0,
+                    150.0 + (75.0 * i as f32),
+                ),
+            });
+        }
+
+        for link in members.iter_mut() {
+            if link.button.hovered() {
+                previewed = team.entities.get(link.link).cloned();
+            }
+            if link.button.clicked() {
+                self.selected_member = Some(link.link);
+            }
+            link.button.draw();
+        }
+
+        // the items in the middle
+        let mut items: Vec<ButtonLink<&Item>> = Vec::new();
+        for stack in inventory.items().iter() {
+            let item = match self.catalog.get(&stack.id) {
+                Some(item) => item,
+                None => continue,
+            };
+            let marker = match self.selected_item.as_deref() == Some(item.id.as_str()) {
+                true => ">",
+                false => "",
+            };
+            items.push(ButtonLink {
+                link: item,
+                button: Button::text(
+                    self.empty_button_texture,
+                    format!("{}{} x{}", marker, item.name, stack.count),
+                    450.0,
+                    150.0 + (75.0 * items.len() as f32),
+                ),
+            });
+        }
+
+        let mut hovered_item = None;
+        for link in items.iter_mut() {
+            let item = link.link;
+            if link.button.hovered() {
+                hovered_item = Some(item);
+            }
+            if link.button.clicked() {
+                self.selected_item = Some(item.id.clone());
+            }
+            link.button.draw();
+        }
+        if items.is_empty() {
+            draw_text("No items", 450.0, 190.0, 30.0, GRAY);
+        }
+
+        let clicked = match NavInput::Back.pressed() {
+            true => Some(PauseAction::Close),
+            false => self.actions(),
+        };
+
+        // show whatever is hovered, falling back to the selected member
+        if let Some(item) = hovered_item {
+            draw_text(item.name.as_str(), 1150.0, 170.0, 30.0, WHITE);
+            draw_text(item.description.as_str(), 1150.0, 205.0, 30.0, WHITE);
+        } else if let Some(entity) = previewed.or_else(|| {
+            self.selected_member
+                .and_then(|i| team.entities.get(i).cloned())
+        }) {
+            draw_entity_details(&entity, 1150.0, 170.0);
+        }
+
+        let status = match self.status {
+            Some(Ok(ref message)) => Some((message.clone(), WHITE)),
+            Some(Err(ref error)) => Some((format!("{}", error), RED)),
+            None => None,
+        };
+        if let Some((text, color)) = status {
+            draw_text(text.as_str(), 450.0, 750.0, 30.0, color);
+        }
+
+        match clicked {
+            Some(PauseAction::Use) => {
+                let item = self
+                    .selected_item
+                    .as_deref()
+                    .and_then(|id| self.catalog.get(id));
+                let entity = self.selected_member.and_then(|i| team.entities.get_mut(i));
+                if let (Some(item), Some(entity)) = (item, entity) {
+                    let result = item.use_on(entity);
+                    if result.is_ok() {
+                        // the item is only shown while there is at least one, so this can't fail
+                        let _ = inventory.remove(&item.id, 1);
+                    }
+                    if inventory.count(&item.id) == 0 {
+                        self.selected_item = None;
+                    }
+                    self.status = Some(result);
+                }
+                false
+            }
+            Some(PauseAction::Summary) => {
+                self.summary = self.selected_member;
+                false
+            }
+            Some(PauseAction::MoveUp) => {
+                if let Some(i) = self.selected_member.filter(|&i| i > 0) {
+                    self.reorder(team, i, i - 1);
+                }
+                false
+            }
+            Some(PauseAction::MoveDown) => {
+                if let Some(i) = self
+                    .selected_member
+                    .filter(|&i| i + 1 < team.entities.len())
+                {
+                    self.reorder(team, i, i + 1);
+                }
+                false
+            }
+            Some(PauseAction::Settings) => {
+                self.status = None;
+                self.settings_requested = true;
+                true
+            }
+            Some(PauseAction::Close) => {
+                self.status = None;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Draw the action buttons and return the one that was clicked.
+    fn actions(&self) -> Option<PauseAction> {
+        let actions = [
+            (PauseAction::Use, "Use"),
+            (PauseAction::Summary, "Summary"),
+            (PauseAction::MoveUp, "Move up"),
+            (PauseAction::MoveDown, "Move down"),
+            (PauseAction::Settings, "Settings"),
+            (PauseAction::Close, "Close"),
+        ];
+
+        let mut clicked = None;
+        for (i, (action, label)) in actions.into_iter().enumerate() {
+            let mut link = ButtonLink {
+                link: action,
+                button: Button::text(
+                    self.empty_button_texture,
+                    label,
+                    800.0,
+                    150.0 + (75.0 * i as f32),
+                ),
+            };
+
+            if link.button.clicked() {
+                clicked = Some(link.link);
+            }
+            link.button.draw();
+        }
+        clicked
+    }
+
+    /// Swap two team members, the first healthy member leads the next battle.
+    fn reorder(&mut self, team: &mut Team, a: usize, b: usize) {
+        if team.swap(a, b).is_err() {
+            return;
+        }
+        self.selected_member = Some(b);
+        team.lead_with_first_healthy();
+    }
+
+    /// Draw everything about one team member, with buttons to page through the team.
+    fn summary_page(&mut self, team: &Team, index: usize) {
+        let entity = match team.entities.get(index) {
+            Some(entity) => entity,
+            None => {
+                self.summary = None;
+                return;
+            }
+        };
+
+        draw_text(
+            format!("{}. {}", index + 1, entity).as_str(),
+            100.0,
+            100.0,
+            40.0,
+            WHITE,
+        );
+        // the idle clip loops, so the time since the game started picks the frame
+        let mut sprite = AnimatedSprite::new(self.sprites.get(entity.entity_type));
+        sprite.update(get_time() as f32, View::Front);
+        self.stats.update(
+            entity.health as f32,
+            entity.max_health,
+            EntityImageParams {
+                texture: &entity.texture,
+                x: 100.0,
+                y: 250.0,
+                color: WHITE,
+                sprite: Some((&sprite, View::Front)),
+            },
+            format!("{}", entity).as_str(),
+        );
+
+        for (i, line) in summary_lines(entity).iter().enumerate() {
+            draw_text(line.as_str(), 550.0, 200.0 + (35.0 * i as f32), 30.0, WHITE);
+        }
+
+        draw_text("Moves", 1000.0, 200.0, 30.0, WHITE);
+        for (i, mv) in entity.get_moves().iter().enumerate() {
+            let y = 240.0 + (75.0 * i as f32);
+            draw_text(format!("{}", mv).as_str(), 1000.0, y, 30.0, WHITE);
+            draw_text(mv.description(), 1000.0, y + 30.0, 25.0, GRAY);
+        }
+
+        let actions = [
+            (SummaryAction::Previous, "Previous"),
+            (SummaryAction::Next, "Next"),
+            (SummaryAction::Back, "Back"),
+        ];
+        let mut clicked = None;
+        for (i, (action, label)) in actions.into_iter().enumerate() {
+            let mut link = ButtonLink {
+                link: action,
+                button: Button::text(
+                    self.empty_button_texture,
+                    label,
+                    100.0 + (350.0 * i as f32),
+                    650.0,
+                ),
+            };
+
+            if link.button.clicked() {
+                clicked = Some(link.link);
+            }
+            link.button.draw();
+        }
+
+        if NavInput::Back.pressed() {
+            clicked = Some(SummaryAction::Back);
+        }
+
+        let count = team.entities.len();
+        match clicked {
+            Some(SummaryAction::Previous) => self.summary = Some((index + count - 1) % count),
+            Some(SummaryAction::Next) => self.summary = Some((index + 1) % count),
+            Some(SummaryAction::Back) => {
+                self.selected_member = Some(index);
+                self.summary = None;
+            }
+            None => {}
+        }
+    }
+}
+
+/// The stats shown on a summary page.
+fn summary_lines(entity: &Entity) -> Vec<String> {
+    let list = |moves: &[Move]| match moves.is_empty() {
+        true => String::from("nothing"),
+        false => moves
+            .iter()
+            .map(|mv| format!("{}", mv))
+            .collect::<Vec<String>>()
+            .join(", "),
+    };
+
+    vec![
+        format!("Level: {}", entity.level),
+        format!("XP: {}/{}", entity.xp, entity.xp_to_next_level()),
+        format!("HP: {}/{}", entity.health, entity.max_health),
+        format!("Attack: {}", entity.attack),
+        format!("Defense: {}", entity.defense),
+        format!("Accuracy: {}", entity.accuracy),
+        format!("Error handling: {}", entity.error_handling),
+        format!("Weak to: {}", list(&entity.weaknesses)),
+        format!("Resists: {}", list(&entity.strengths)),
+    ]
+}
@@ -0,0 +1,149 @@
+use crate::entity::Entity;
+use crate::trainer::{Trainer, TrainerBook};
+
+use std::collections::HashSet;
+
+/// Highest level that obeys the player before they have any badges.
+pub const BASE_LEVEL_CAP: u32 = 5;
+
+/// How much each badge raises the level cap.
+pub const LEVEL_CAP_PER_BADGE: u32 = 5;
+
+/// What the player has achieved in the campaign.
+#[derive(PartialEq, Clone, Debug, Default)]
+pub struct Progression {
+    /// Ids of the trainers already beaten.
+    defeated: HashSet<String>,
+    /// Badges in the order they were earned.
+    badges: Vec<String>,
+    /// Set once the final boss is beaten.
+    complete: bool,
+}
+
+/// What beating a trainer earned the player.
+#[derive(PartialEq, Clone, Debug, Default)]
+pub struct Victory {
+    pub badge: Option<String>,
+    /// True if this win finished the campaign.
+    pub finished: bool,
+}
+
+impl Progression {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Rebuild progression from a save.
+    pub fn restore(defeated: &[String], badges: &[String], complete: bool) -> Self {
+        Self {
+            defeated: defeated.iter().cloned().collect(),
+            badges: badges.to_vec(),
+            complete,
+        }
+    }
+
+    pub fn has_defeated(&self, id: &str) -> bool {
+        self.defeated.contains(id)
+    }
+
+    pub fn defeated(&self) -> &HashSet<String> {
+        &self.defeated
+    }
+
+    pub fn badges(&self) -> &[String] {
+        &self.badges
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.complete
+    }
+
+    /// The highest level that will obey the player.
+    pub fn level_cap(&self) -> u32 {
+        BASE_LEVEL_CAP + LEVEL_CAP_PER_BADGE * self.badges.len() as u32
+    }
+
+    pub fn obeys(&self, entity: &Entity) -> bool {
+        entity.level <= self.level_cap()
+    }
+
+    /// Record a win against a trainer, awarding their badge the first time they are beaten.
+    pub fn record_win(&mut self, trainer: &Trainer, book: &TrainerBook) -> Victory {
+        if !self.defeated.insert(trainer.id.clone()) {
+            return Victory::default();
+        }
+
+        let badge = trainer
+            .badge
+            .clone()
+            .filter(|badge| !self.badges.contains(badge));
+        if let Some(ref badge) = badge {
+            self.badges.push(badge.clone());
+        }
+
+        let finished = !self.complete && book.is_final_boss(&trainer.id);
+        self.complete |= finished;
+        Victory { badge, finished }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entity::EntityType;
+
+    const TEXT: &str = "
+[campaign]
+order = leader, boss
+
+[trainer]
+id = leader
+name = Django Leader
+badge = Batteries Badge
+team = Python:4
+
+[trainer]
+id = boss
+name = CTO
+team = Rust:10
+";
+
+    #[test]
+    fn badges_raise_the_level_cap() {
+        let book = TrainerBook::parse(TEXT).unwrap();
+        let mut progression = Progression::new();
+        let entity = EntityType::Go.build(BASE_LEVEL_CAP + 1, None);
+        assert!(!progression.obeys(&entity));
+
+        let victory = progression.record_win(book.get("leader").unwrap(), &book);
+        assert_eq!(victory.badge.as_deref(), Some("Batteries Badge"));
+        assert!(!victory.finished);
+        assert_eq!(
+            progression.level_cap(),
+            BASE_LEVEL_CAP + LEVEL_CAP_PER_BADGE
+        );
+        assert!(progression.obeys(&entity));
+
+        // beating someone again doesn't give another badge
+        let again = progression.record_win(book.get("leader").unwrap(), &book);
+        assert_eq!(again, Victory::default());
+        assert_eq!(progression.badges().len(), 1);
+    }
+
+    #[test]
+    fn final_boss_completes_the_campaign() {
+        let book = TrainerBook::parse(TEXT).unwrap();
+        let mut progression = Progression::new();
+
+        let victory = progression.record_win(book.get("boss").unwrap(), &book);
+        assert!(victory.finished);
+        assert!(progression.is_complete());
+
+        let restored = Progression::restore(
+            &[String::from("boss")],
+            progression.badges(),
+            progression.is_complete(),
+        );
+        assert_eq!(restored, progression);
+    }
+}
@@ -0,0 +1,275 @@
+use crate::entity::EntityType;
+use crate::storage::{Filter, SortKey, Storage};
+use crate::ui::{draw_entity_details, Button, ButtonLink, NavInput};
+use crate::{Team, TeamError};
+
+use macroquad::prelude::*;
+
+/// Number of stored entities listed at once.
+const PAGE_SIZE: usize = 6;
+
+/// Lowest levels the level filter steps through, after showing every level.
+const LEVEL_FILTERS: [u32; 4] = [5, 10, 20, 30];
+
+/// Buttons on the right of the registry screen.
+#[derive(PartialEq, Clone, Copy)]
+enum RegistryAction {
+    Sort,
+    Filter,
+    LevelFilter,
+    Deposit,
+    Withdraw,
+    Swap,
+    PreviousPage,
+    NextPage,
+    Close,
+}
+
+/// Screen for moving entities between the team and the package registry.
+pub struct Registry<'a> {
+    sort: SortKey,
+    filter: Filter,
+    page: usize,
+    selected_member: Option<usize>,
+    /// Index into the storage of the selected stored entity.
+    selected_stored: Option<usize>,
+    error: Option<TeamError>,
+    empty_button_texture: &'a Texture2D,
+}
+
+impl<'a> Registry<'a> {
+    pub fn new(empty_button_texture: &'a Texture2D) -> Self {
+        Self {
+            sort: SortKey::Type,
+            filter: Filter::default(),
+            page: 0,
+            selected_member: None,
+            selected_stored: None,
+            error: None,
+            empty_button_texture,
+        }
+    }
+
+    /// Draw and update the registry.
+    /// Returns true once the player closes it.
+    pub fn update(&mut self, team: &mut Team, storage: &mut Storage) -> bool {
+        draw_text("Package registry", 100.0, 100.0, 40.0, WHITE);
+
+        let mut previewed = None;
+
+        // the team down the left, clicking a member selects it
+        let mut members: Vec<ButtonLink<usize>> = Vec::new();
+        for (i, entity) in team.entities.iter().enumerate() {
+            let marker = match self.selected_member == Some(i) {
+                true => ">",
+                false => "",
+            };
+            let label = format!("{}{}. {} Lv. {}", marker, i + 1, entity, entity.level);
+            members.push(ButtonLink {
+                link: i,
+                button: Button::text(
+                    self.empty_button_texture,
+                    label,
+                    100.0,
+                    150.0 + (75.0 * i as f32),
+                ),
+            });
+        }
+
+        for link in members.iter_mut() {
+            if link.button.hovered() {
+                previewed = team.entities.get(link.link).cloned();
+            }
+            if link.button.clicked() {
+                self.selected_member = Some(link.link);
+            }
+            link.button.draw();
+        }
+
+        // one page of the stored entities that match the filter
+        let listed = storage.filtered(&self.filter);
+        let pages = listed.len().div_ceil(PAGE_SIZE).max(1);
+        self.page = self.page.min(pages - 1);
+
+        let mut stored: Vec<ButtonLink<usize>> = Vec::new();
+        for (row, index) in listed
+            .iter()
+            .skip(self.page * PAGE_SIZE)
+            .take(PAGE_SIZE)
+            .enumerate()
+        {
+            let marker = match self.selected_stored == Some(*index) {
+                true => ">",
+                false => "",
+            };
+            let entity = &storage.entities[*index];
+            stored.push(ButtonLink {
+                link: *index,
+                button: Button::text(
+                    self.empty_button_texture,
+                    format!("{}{} Lv. {}", marker, entity, entity.level),
+                    450.0,
+                    150.0 + (75.0 * row as f32),
+                ),
+            });
+        }
+
+        for link in stored.iter_mut() {
+            if link.button.hovered() {
+                previewed = storage.entities.get(link.link).cloned();
+            }
+            if link.button.clicked() {
+                self.selected_stored = Some(link.link);
+            }
+            link.button.draw();
+        }
+
+        draw_text(
+            format!(
+                "Page {}/{}, {} stored",
+                self.page + 1,
+                pages,
+                storage.entities.len()
+            )
+            .as_str(),
+            450.0,
+            640.0,
+            30.0,
+            GRAY,
+        );
+
+        let clicked = match NavInput::Back.pressed() {
+            true => Some(RegistryAction::Close),
+            false => self.actions(),
+        };
+
+        // show whatever is hovered, falling back to the selection
+        let details = previewed
+            .or_else(|| {
+                self.selected_stored
+                    .and_then(|i| storage.entities.get(i).cloned())
+            })
+            .or_else(|| {
+                self.selected_member
+                    .and_then(|i| team.entities.get(i).cloned())
+            });
+        if let Some(entity) = details {
+            draw_entity_details(&entity, 1150.0, 170.0);
+        }
+
+        if let Some(ref error) = self.error {
+            draw_text(format!("{}", error).as_str(), 450.0, 750.0, 30.0, RED);
+        }
+
+        let result = match clicked {
+            Some(RegistryAction::Sort) => {
+                self.sort = self.sort.next();
+                storage.sort(self.sort);
+                self.selected_stored = None;
+                Ok(())
+            }
+            Some(RegistryAction::Filter) => {
+                self.filter.entity_type = next_type_filter(self.filter.entity_type);
+                self.page = 0;
+                Ok(())
+            }
+            Some(RegistryAction::LevelFilter) => {
+                self.filter.min_level = next_level_filter(self.filter.min_level);
+                self.page = 0;
+                Ok(())
+            }
+            Some(RegistryAction::Deposit) => match self.selected_member.take() {
+                Some(member) => storage.deposit_from(team, member),
+                None => Ok(()),
+            },
+            Some(RegistryAction::Withdraw) => match self.selected_stored.take() {
+                Some(index) => storage.withdraw_to(team, index),
+                None => Ok(()),
+            },
+            Some(RegistryAction::Swap) => match (self.selected_member, self.selected_stored) {
+                (Some(member), Some(index)) => storage.swap_with(team, member, index),
+                _ => Ok(()),
+            },
+            Some(RegistryAction::PreviousPage) => {
+                self.page = self.page.saturating_sub(1);
+                Ok(())
+            }
+            Some(RegistryAction::NextPage) => {
+                self.page += 1;
+                Ok(())
+            }
+            Some(RegistryAction::Close) => {
+                self.error = None;
+                return true;
+            }
+            None => return false,
+        };
+
+        self.error = result.err();
+        false
+    }
+
+    /// Draw the action buttons and return the one that was clicked.
+    fn actions(&self) -> Option<RegistryAction> {
+        let filter_label = match self.filter.entity_type {
+            Some(entity_type) => format!("Filter: {}", entity_type),
+            None => String::from("Filter: All"),
+        };
+        let level_label = match self.filter.min_level {
+            Some(level) => format!("Level: {}+", level),
+            None => String::from("Level: Any"),
+        };
+        let actions = [
+            (RegistryAction::Sort, format!("Sort: {}", self.sort)),
+            (RegistryAction::Filter, filter_label),
+            (RegistryAction::LevelFilter, level_label),
+            (RegistryAction::Deposit, String::from("Deposit")),
+            (RegistryAction::Withdraw, String::from("Withdraw")),
+            (RegistryAction::Swap, String::from("Swap")),
+            (RegistryAction::PreviousPage, String::from("Previous page")),
+            (RegistryAction::NextPage, String::from("Next page")),
+            (RegistryAction::Close, String::from("Close")),
+        ];
+
+        let mut clicked = None;
+        for (i, (action, label)) in actions.into_iter().enumerate() {
+            let mut link = ButtonLink {
+                link: action,
+                button: Button::text(
+                    self.empty_button_texture,
+                    label,
+                    800.0,
+                    150.0 + (75.0 * i as f32),
+                ),
+            };
+
+            if link.button.clicked() {
+                clicked = Some(link.link);
+            }
+            link.button.draw();
+        }
+        clicked
+    }
+}
+
+/// Cycle the type filter through every entity type and back to showing everything.
+fn next_type_filter(current: Option<EntityType>) -> Option<EntityType> {
+    match current {
+        None => Some(EntityType::ALL[0]),
+        Some(entity_type) => {
+            let i = EntityType::ALL
+                .iter()
+                .position(|t| *t == entity_type)
+                .unwrap_or(0);
+            EntityType::ALL.get(i + 1).copied()
+        }
+    }
+}
+
+/// Step the level filter through each of `LEVEL_FILTERS` and back to showing every level.
+fn next_level_filter(current: Option<u32>) -> Option<u32> {
+    match current {
+        None => Some(LEVEL_FILTERS[0]),
+        Some(level) => LEVEL_FILTERS.into_iter().find(|&next| next > level),
+    }
+}
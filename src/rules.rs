@@ -0,0 +1,198 @@
+use crate::entity::EntityType;
+use crate::moves::Move;
+use crate::Team;
+
+use std::fmt;
+
+/// A reason a team isn't allowed under a ruleset.
+#[derive(PartialEq, Clone, Debug)]
+pub enum RuleViolation {
+    EmptyTeam,
+    TooManyEntities {
+        count: usize,
+        max: usize,
+    },
+    OverLevelCap {
+        entity_type: EntityType,
+        level: u32,
+        cap: u32,
+    },
+    DuplicateSpecies(EntityType),
+    BannedMove {
+        entity_type: EntityType,
+        mv: Move,
+    },
+}
+
+impl fmt::Display for RuleViolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RuleViolation::EmptyTeam => write!(f, "The team needs at least one entity"),
+            RuleViolation::TooManyEntities { count, max } => write!(
+                f,
+                "The team has {} entities but at most {} are allowed",
+                count, max
+            ),
+            RuleViolation::OverLevelCap {
+                entity_type,
+                level,
+                cap,
+            } => write!(
+                f,
+                "{} is level {} but the level cap is {}",
+                entity_type, level, cap
+            ),
+            RuleViolation::DuplicateSpecies(entity_type) => {
+                write!(f, "Only one {} is allowed per team", entity_type)
+            }
+            RuleViolation::BannedMove { entity_type, mv } => {
+                write!(f, "{} knows {}, which is banned", entity_type, mv)
+            }
+        }
+    }
+}
+
+/// Restrictions a team must meet to take part in a battle.
+#[derive(PartialEq, Clone, Debug)]
+pub struct Ruleset {
+    pub max_team_size: usize,
+    pub level_cap: Option<u32>,
+    /// Only allow one of each entity type per team.
+    pub species_clause: bool,
+    pub banned_moves: Vec<Move>,
+}
+
+impl Default for Ruleset {
+    fn default() -> Self {
+        Self {
+            max_team_size: Team::CAPACITY,
+            level_cap: None,
+            species_clause: false,
+            banned_moves: Vec::new(),
+        }
+    }
+}
+
+impl Ruleset {
+    /// Rules used for battles against other players.
+    pub fn competitive() -> Self {
+        Self {
+            species_clause: true,
+            ..Default::default()
+        }
+    }
+
+    /// Check a team against every rule.
+    /// Returns all the rules the team breaks.
+    pub fn validate(&self, team: &Team) -> Result<(), Vec<RuleViolation>> {
+        let mut violations = Vec::new();
+
+        if team.entities.is_empty() {
+            violations.push(RuleViolation::EmptyTeam);
+        }
+
+        if team.entities.len() > self.max_team_size {
+            violations.push(RuleViolation::TooManyEntities {
+                count: team.entities.len(),
+                max: self.max_team_size,
+            });
+        }
+
+        let mut seen: Vec<EntityType> = Vec::new();
+        for entity in team.entities.iter() {
+            if let Some(cap) = self.level_cap {
+                if entity.level > cap {
+                    violations.push(RuleViolation::OverLevelCap {
+                        entity_type: entity.entity_type,
+                        level: entity.level,
+                        cap,
+                    });
+                }
+            }
+
+            if self.species_clause && seen.contains(&entity.entity_type) {
+                let violation = RuleViolation::DuplicateSpecies(entity.entity_type);
+                if !violations.contains(&violation) {
+                    violations.push(violation);
+                }
+            }
+            seen.push(entity.entity_type);
+
+            for mv in entity.get_moves() {
+                if self.banned_moves.contains(mv) {
+                    violations.push(RuleViolation::BannedMove {
+                        entity_type: entity.entity_type,
+                        mv: *mv,
+                    });
+                }
+            }
+        }
+
+        match violations.is_empty() {
+            true => Ok(()),
+            false => Err(violations),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn team(types: &[EntityType], level: u32) -> Team {
+        let mut team = Team::new();
+        for entity_type in types {
+            team.push(entity_type.build(level, None)).unwrap();
+        }
+        team
+    }
+
+    #[test]
+    fn default_allows_normal_teams() {
+        let rules = Ruleset::default();
+        assert!(rules
+            .validate(&team(&[EntityType::Rust, EntityType::Rust], 10))
+            .is_ok());
+        assert_eq!(
+            rules.validate(&Team::new()),
+            Err(vec![RuleViolation::EmptyTeam])
+        );
+    }
+
+    #[test]
+    fn reports_every_violation() {
+        let rules = Ruleset {
+            max_team_size: 1,
+            level_cap: Some(5),
+            species_clause: true,
+            banned_moves: vec![Move::Speed],
+        };
+
+        let violations = rules
+            .validate(&team(&[EntityType::Go, EntityType::Go, EntityType::Go], 6))
+            .unwrap_err();
+
+        assert!(violations.contains(&RuleViolation::TooManyEntities { count: 3, max: 1 }));
+        assert!(violations.contains(&RuleViolation::OverLevelCap {
+            entity_type: EntityType::Go,
+            level: 6,
+            cap: 5,
+        }));
+        assert_eq!(
+            violations
+                .iter()
+                .filter(|v| **v == RuleViolation::DuplicateSpecies(EntityType::Go))
+                .count(),
+            1
+        );
+
+        let violations = rules.validate(&team(&[EntityType::Rust], 0)).unwrap_err();
+        assert_eq!(
+            violations,
+            vec![RuleViolation::BannedMove {
+                entity_type: EntityType::Rust,
+                mv: Move::Speed,
+            }]
+        );
+    }
+}
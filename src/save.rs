@@ -0,0 +1,387 @@
+use crate::data::{DataError, Document};
+use crate::entity::{Entity, EntityType};
+use crate::inventory::{Inventory, ItemStack};
+use crate::item::ItemCatalog;
+use crate::moves::{parse_moves, Move};
+use crate::overworld::map::parse_position;
+use crate::overworld::Direction;
+use crate::rules::{RuleViolation, Ruleset};
+use crate::storage::Storage;
+use crate::{Team, TeamError};
+
+use macroquad::texture::Texture2D;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+#[derive(Debug)]
+pub enum SaveError {
+    Data(DataError),
+    Write {
+        path: String,
+        error: std::io::Error,
+    },
+    Team(TeamError),
+    /// The saved team breaks the rules every team has to follow.
+    Invalid(Vec<RuleViolation>),
+    /// The save holds an item that isn't in the items file.
+    UnknownItem(String),
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SaveError::Data(e) => write!(f, "{}", e),
+            SaveError::Write { path, error } => write!(f, "could not write {}: {}", path, error),
+            SaveError::Team(e) => write!(f, "the saved team is broken: {}", e),
+            SaveError::Invalid(violations) => {
+                let reasons: Vec<String> = violations.iter().map(|v| format!("{}", v)).collect();
+                write!(f, "the saved team is not allowed: {}", reasons.join(", "))
+            }
+            SaveError::UnknownItem(id) => write!(f, "the save has an unknown item {}", id),
+        }
+    }
+}
+
+impl std::error::Error for SaveError {}
+
+impl From<DataError> for SaveError {
+    fn from(e: DataError) -> Self {
+        SaveError::Data(e)
+    }
+}
+
+impl From<TeamError> for SaveError {
+    fn from(e: TeamError) -> Self {
+        SaveError::Team(e)
+    }
+}
+
+/// An entity as it is written in a save file, `Type:level:health:xp:Move/Move`
+/// such as `Rust:5:120:40:Speed/Math`.
+/// The experience and moves are optional for saves made before entities had them.
+#[derive(PartialEq, Clone, Debug)]
+pub struct SavedEntity {
+    pub entity_type: EntityType,
+    pub level: u32,
+    pub health: u32,
+    pub xp: u32,
+    /// Empty for saves without moves, the entity keeps its usual ones.
+    pub moves: Vec<Move>,
+}
+
+impl SavedEntity {
+    pub fn from_entity(entity: &Entity) -> Self {
+        Self {
+            entity_type: entity.entity_type,
+            level: entity.level,
+            health: entity.health,
+            xp: entity.xp,
+            moves: entity.get_moves().clone(),
+        }
+    }
+
+    pub fn build(&self, textures: &HashMap<EntityType, Texture2D>) -> Entity {
+        let mut entity = self
+            .entity_type
+            .build(self.level, textures.get(&self.entity_type).cloned());
+        entity.health = self.health.min(entity.max_health);
+        entity.xp = self.xp;
+        entity.set_moves(self.moves.clone());
+        entity
+    }
+}
+
+impl FromStr for SavedEntity {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.split(':').map(str::trim).collect();
+        let (entity_type, level, health, xp, moves) = match parts[..] {
+            [entity_type, level, health] => (entity_type, level, health, "0", ""),
+            [entity_type, level, health, xp] => (entity_type, level, health, xp, ""),
+            [entity_type, level, health, xp, moves] => (entity_type, level, health, xp, moves),
+            _ => return Err(()),
+        };
+        let moves = match moves {
+            "" => Vec::new(),
+            moves => parse_moves(moves).ok_or(())?,
+        };
+        Ok(Self {
+            entity_type: entity_type.parse()?,
+            level: level.parse().map_err(|_| ())?,
+            health: health.parse().map_err(|_| ())?,
+            xp: xp.parse().map_err(|_| ())?,
+            moves,
+        })
+    }
+}
+
+impl fmt::Display for SavedEntity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:?}:{}:{}:{}",
+            self.entity_type, self.level, self.health, self.xp
+        )?;
+        if !self.moves.is_empty() {
+            let moves: Vec<String> = self.moves.iter().map(|mv| format!("{:?}", mv)).collect();
+            write!(f, ":{}", moves.join("/"))?;
+        }
+        Ok(())
+    }
+}
+
+/// Everything needed to carry on an adventure later.
+#[derive(PartialEq, Clone, Debug)]
+pub struct SaveData {
+    pub map: String,
+    pub position: (i32, i32),
+    pub facing: Direction,
+    /// Where the player goes after losing a battle.
+    pub respawn: (String, i32, i32),
+    pub money: u32,
+    pub items: Vec<ItemStack>,
+    pub team: Vec<SavedEntity>,
+    pub storage: Vec<SavedEntity>,
+    /// Ids of the trainers already beaten.
+    pub defeated: Vec<String>,
+    pub badges: Vec<String>,
+    /// True once the final boss has been beaten.
+    pub complete: bool,
+}
+
+impl SaveData {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SaveError> {
+        Self::from_document(&Document::load(path)?)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, SaveError> {
+        Self::from_document(&Document::parse(text)?)
+    }
+
+    fn from_document(document: &Document) -> Result<Self, SaveError> {
+        let player = document.require_section("player")?;
+        let (respawn_x, respawn_y) = parse_position(player, "respawn_at")?;
+        let entities = |name: &str| -> Result<Vec<SavedEntity>, SaveError> {
+            match document.section(name) {
+                Some(section) => Ok(section.parse_list("entities")?),
+                None => Ok(Vec::new()),
+            }
+        };
+        let (money, items) = match document.section("inventory") {
+            Some(section) => (section.parse_or("money", 0)?, section.parse_list("items")?),
+            None => (0, Vec::new()),
+        };
+        let (defeated, badges, complete) = match document.section("progress") {
+            Some(section) => (
+                section.parse_list("defeated")?,
+                section.parse_list("badges")?,
+                section.parse_or("complete", false)?,
+            ),
+            // saves from before badges kept only the beaten trainers
+            None => match document.section("defeated") {
+                Some(section) => (section.parse_list("trainers")?, Vec::new(), false),
+                None => (Vec::new(), Vec::new(), false),
+            },
+        };
+
+        Ok(Self {
+            map: String::from(player.require("map")?),
+            position: parse_position(player, "at")?,
+            facing: player.parse_or("facing", Direction::Down)?,
+            respawn: (
+                String::from(player.require("respawn_map")?),
+                respawn_x,
+                respawn_y,
+            ),
+            money,
+            items,
+            team: entities("team")?,
+            storage: entities("storage")?,
+            defeated,
+            badges,
+            complete,
+        })
+    }
+
+    /// Write the save in the data file format.
+    pub fn to_text(&self) -> String {
+        fn list<T: fmt::Display>(values: &[T]) -> String {
+            values
+                .iter()
+                .map(|value| format!("{}", value))
+                .collect::<Vec<String>>()
+                .join(", ")
+        }
+        let (respawn_map, respawn_x, respawn_y) = &self.respawn;
+
+        format!(
+            "[player]\nmap = {}\nat = {},{}\nfacing = {}\nrespawn_map = {}\nrespawn_at = {},{}\n\n\
+             [inventory]\nmoney = {}\nitems = {}\n\n[team]\nentities = {}\n\n[storage]\nentities = {}\n\n\
+             [progress]\ndefeated = {}\nbadges = {}\ncomplete = {}\n",
+            self.map,
+            self.position.0,
+            self.position.1,
+            self.facing,
+            respawn_map,
+            respawn_x,
+            respawn_y,
+            self.money,
+            list(&self.items),
+            list(&self.team),
+            list(&self.storage),
+            self.defeated.join(", "),
+            self.badges.join(", "),
+            self.complete,
+        )
+    }
+
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<(), SaveError> {
+        let path = path.as_ref();
+        std::fs::write(path, self.to_text()).map_err(|error| SaveError::Write {
+            path: path.display().to_string(),
+            error,
+        })
+    }
+
+    /// Build the saved team, checking it is one the player could have made.
+    pub fn build_team(&self, textures: &HashMap<EntityType, Texture2D>) -> Result<Team, SaveError> {
+        let mut team = Team::new();
+        for saved in self.team.iter() {
+            team.push(saved.build(textures))?;
+        }
+        Ruleset::default()
+            .validate(&team)
+            .map_err(SaveError::Invalid)?;
+        Ok(team)
+    }
+
+    /// Build the saved inventory, checking every item still exists.
+    pub fn build_inventory(&self, catalog: &ItemCatalog) -> Result<Inventory, SaveError> {
+        if let Some(unknown) = self
+            .items
+            .iter()
+            .find(|stack| catalog.get(&stack.id).is_none())
+        {
+            return Err(SaveError::UnknownItem(unknown.id.clone()));
+        }
+        Ok(Inventory::new(self.money, self.items.clone()))
+    }
+
+    pub fn build_storage(&self, textures: &HashMap<EntityType, Texture2D>) -> Storage {
+        let mut storage = Storage::new();
+        for saved in self.storage.iter() {
+            storage.deposit(saved.build(textures));
+        }
+        storage
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn save() -> SaveData {
+        SaveData {
+            map: String::from("route"),
+            position: (3, 4),
+            facing: Direction::Left,
+            respawn: (String::from("town"), 10, 2),
+            money: 250,
+            items: vec![ItemStack {
+                id: String::from("coffee"),
+                count: 3,
+            }],
+            team: vec![SavedEntity {
+                entity_type: EntityType::Rust,
+                level: 5,
+                health: 120,
+                xp: 40,
+                moves: vec![Move::Speed, Move::Math],
+            }],
+            storage: vec![
+                SavedEntity {
+                    entity_type: EntityType::Go,
+                    level: 2,
+                    health: 200,
+                    xp: 0,
+                    moves: Vec::new(),
+                },
+                SavedEntity {
+                    entity_type: EntityType::Js,
+                    level: 3,
+                    health: 0,
+                    xp: 10,
+                    moves: Vec::new(),
+                },
+            ],
+            defeated: vec![String::from("junior_dev"), String::from("django_lead")],
+            badges: vec![String::from("Batteries Badge")],
+            complete: false,
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let save = save();
+        assert_eq!(SaveData::parse(&save.to_text()).unwrap(), save);
+
+        let storage = save.build_storage(&HashMap::new());
+        assert_eq!(storage.entities.len(), 2);
+        assert_eq!(storage.entities[1].health, 0);
+        assert_eq!(storage.entities[1].xp, 10);
+
+        // taught moves come back with the entity
+        let team = save.build_team(&HashMap::new()).unwrap();
+        assert_eq!(team.entities[0].get_moves(), &vec![Move::Speed, Move::Math]);
+
+        // saves from before experience and moves existed still load
+        let old: SavedEntity = "Rust:5:120".parse().unwrap();
+        assert_eq!(old.xp, 0);
+        assert!(old.moves.is_empty());
+        assert_eq!(
+            old.build(&HashMap::new()).get_moves(),
+            EntityType::Rust.build(5, None).get_moves()
+        );
+        assert!("Rust:5:120:0:Teleport".parse::<SavedEntity>().is_err());
+        assert!("Rust:5:120:0:Async/IntParse/Math/Speed/Deadline"
+            .parse::<SavedEntity>()
+            .is_err());
+        assert!("Rust:5:120:0:Math/Math".parse::<SavedEntity>().is_err());
+
+        let old = save
+            .to_text()
+            .replace("[progress]\ndefeated", "[defeated]\ntrainers");
+        let old = SaveData::parse(&old).unwrap();
+        assert_eq!(old.defeated, save.defeated);
+        assert!(old.badges.is_empty());
+
+        let catalog = ItemCatalog::load("assets/items.data").unwrap();
+        assert_eq!(save.build_inventory(&catalog).unwrap().count("coffee"), 3);
+    }
+
+    #[test]
+    fn reject_bad_saves() {
+        let mut save = save();
+        save.team.clear();
+        assert!(matches!(
+            save.build_team(&HashMap::new()),
+            Err(SaveError::Invalid(_))
+        ));
+
+        let text = save.to_text().replace("Go:2:200:0", "Go:2");
+        assert!(matches!(
+            SaveData::parse(&text),
+            Err(SaveError::Data(DataError::Invalid { .. }))
+        ));
+
+        save.items[0].id = String::from("tea");
+        assert!(matches!(
+            save.build_inventory(&ItemCatalog::default()),
+            Err(SaveError::UnknownItem(_))
+        ));
+    }
+}
@@ -0,0 +1,355 @@
+use crate::data::{DataError, Document};
+use crate::ui::{DialogueSettings, KeyBindings, NavInput};
+
+use macroquad::input::KeyCode;
+
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Keys that can be bound to a menu control, named the same as macroquad's `KeyCode`.
+/// L is left out because it always opens the battle log.
+pub const BINDABLE_KEYS: [KeyCode; 43] = [
+    KeyCode::A,
+    KeyCode::B,
+    KeyCode::C,
+    KeyCode::D,
+    KeyCode::E,
+    KeyCode::F,
+    KeyCode::G,
+    KeyCode::H,
+    KeyCode::I,
+    KeyCode::J,
+    KeyCode::K,
+    KeyCode::M,
+    KeyCode::N,
+    KeyCode::O,
+    KeyCode::P,
+    KeyCode::Q,
+    KeyCode::R,
+    KeyCode::S,
+    KeyCode::T,
+    KeyCode::U,
+    KeyCode::V,
+    KeyCode::W,
+    KeyCode::X,
+    KeyCode::Y,
+    KeyCode::Z,
+    KeyCode::Key0,
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+    KeyCode::Space,
+    KeyCode::Tab,
+    KeyCode::Backspace,
+    KeyCode::LeftShift,
+    KeyCode::RightShift,
+    KeyCode::LeftControl,
+    KeyCode::RightControl,
+    KeyCode::Delete,
+];
+
+/// Parse a key written as its `KeyCode` name, such as `W` or `Space`.
+pub fn parse_key(name: &str) -> Option<KeyCode> {
+    BINDABLE_KEYS
+        .into_iter()
+        .find(|key| format!("{:?}", key) == name)
+}
+
+/// How fast dialogue is typed out.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum TextSpeed {
+    Slow,
+    Normal,
+    Fast,
+    Instant,
+}
+
+impl TextSpeed {
+    pub const ALL: [TextSpeed; 4] = [
+        TextSpeed::Slow,
+        TextSpeed::Normal,
+        TextSpeed::Fast,
+        TextSpeed::Instant,
+    ];
+
+    pub fn characters_per_second(&self) -> f32 {
+        match self {
+            TextSpeed::Slow => 30.0,
+            TextSpeed::Normal => 60.0,
+            TextSpeed::Fast => 120.0,
+            TextSpeed::Instant => 100_000.0,
+        }
+    }
+}
+
+impl fmt::Display for TextSpeed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TextSpeed::Slow => write!(f, "slow"),
+            TextSpeed::Normal => write!(f, "normal"),
+            TextSpeed::Fast => write!(f, "fast"),
+            TextSpeed::Instant => write!(f, "instant"),
+        }
+    }
+}
+
+impl FromStr for TextSpeed {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        TextSpeed::ALL
+            .into_iter()
+            .find(|speed| speed.to_string() == s)
+            .ok_or(())
+    }
+}
+
+/// Size of the window when not fullscreen, written as `1280x720`.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct Resolution {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Resolution {
+    /// Sizes offered on the settings screen.
+    pub const CHOICES: [Resolution; 4] = [
+        Resolution::new(1280, 720),
+        Resolution::new(1600, 900),
+        Resolution::new(1920, 1080),
+        Resolution::new(2560, 1440),
+    ];
+
+    pub const fn new(width: u32, height: u32) -> Self {
+        Self { width, height }
+    }
+}
+
+impl fmt::Display for Resolution {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}x{}", self.width, self.height)
+    }
+}
+
+impl FromStr for Resolution {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (width, height) = s.split_once('x').ok_or(())?;
+        let width = width.trim().parse().map_err(|_| ())?;
+        let height = height.trim().parse().map_err(|_| ())?;
+        match (width, height) {
+            (0, _) | (_, 0) => Err(()),
+            _ => Ok(Self { width, height }),
+        }
+    }
+}
+
+/// A volume from 0 to 100 percent.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct Volume(u32);
+
+impl Volume {
+    pub fn new(percent: u32) -> Self {
+        Self(percent.min(100))
+    }
+
+    pub fn percent(&self) -> u32 {
+        self.0
+    }
+
+    /// The volume from 0 to 1.
+    pub fn fraction(&self) -> f32 {
+        self.0 as f32 / 100.0
+    }
+}
+
+impl FromStr for Volume {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.parse() {
+            Ok(percent) if percent <= 100 => Ok(Self(percent)),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Everything the player can change about the game, kept in the config file.
+#[derive(PartialEq, Clone, Debug)]
+pub struct Settings {
+    pub fullscreen: bool,
+    pub resolution: Resolution,
+    pub text_speed: TextSpeed,
+    /// Move on from finished messages without waiting for the player.
+    pub auto_advance: bool,
+    pub music_volume: Volume,
+    pub effects_volume: Volume,
+    /// Play move animations in battles.
+    pub animations: bool,
+    pub keys: KeyBindings,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            fullscreen: true,
+            resolution: Resolution::new(1920, 1080),
+            text_speed: TextSpeed::Normal,
+            auto_advance: false,
+            music_volume: Volume::new(60),
+            effects_volume: Volume::new(80),
+            animations: true,
+            keys: KeyBindings::default(),
+        }
+    }
+}
+
+impl Settings {
+    /// Seconds a finished message waits when advancing by itself.
+    const AUTO_ADVANCE_DELAY: f32 = 1.5;
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, DataError> {
+        Self::from_document(&Document::load(path)?)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, DataError> {
+        Self::from_document(&Document::parse(text)?)
+    }
+
+    /// Load the settings, falling back to the defaults if there is no config file yet or it can't be read.
+    pub fn load_or_default(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Self::default();
+        }
+        Self::load(path).unwrap_or_else(|e| {
+            eprintln!("Could not load the settings, using the defaults: {}", e);
+            Self::default()
+        })
+    }
+
+    fn from_document(document: &Document) -> Result<Self, DataError> {
+        let defaults = Self::default();
+        let section = match document.section("settings") {
+            Some(section) => section,
+            None => return Ok(defaults),
+        };
+
+        let mut keys = defaults.keys;
+        for input in NavInput::ALL {
+            let name = key_setting(input);
+            if let Some(value) = section.get(&name) {
+                let key = parse_key(value).ok_or_else(|| section.invalid(&name, value))?;
+                keys.set(input, key);
+            }
+        }
+
+        Ok(Self {
+            fullscreen: section.parse_or("fullscreen", defaults.fullscreen)?,
+            resolution: section.parse_or("resolution", defaults.resolution)?,
+            text_speed: section.parse_or("text_speed", defaults.text_speed)?,
+            auto_advance: section.parse_or("auto_advance", defaults.auto_advance)?,
+            music_volume: section.parse_or("music_volume", defaults.music_volume)?,
+            effects_volume: section.parse_or("effects_volume", defaults.effects_volume)?,
+            animations: section.parse_or("animations", defaults.animations)?,
+            keys,
+        })
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = format!(
+            "[settings]\nfullscreen = {}\nresolution = {}\ntext_speed = {}\nauto_advance = {}\n\
+             music_volume = {}\neffects_volume = {}\nanimations = {}\n",
+            self.fullscreen,
+            self.resolution,
+            self.text_speed,
+            self.auto_advance,
+            self.music_volume.percent(),
+            self.effects_volume.percent(),
+            self.animations,
+        );
+        for input in NavInput::ALL {
+            text.push_str(&format!(
+                "{} = {:?}\n",
+                key_setting(input),
+                self.keys.get(input)
+            ));
+        }
+        text
+    }
+
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<(), DataError> {
+        let path = path.as_ref();
+        std::fs::write(path, self.to_text()).map_err(|error| DataError::Io {
+            path: path.display().to_string(),
+            error,
+        })
+    }
+
+    /// How the dialogue boxes should behave.
+    pub fn dialogue(&self) -> DialogueSettings {
+        DialogueSettings {
+            text_speed: self.text_speed.characters_per_second(),
+            auto_advance: self.auto_advance.then_some(Self::AUTO_ADVANCE_DELAY),
+        }
+    }
+}
+
+/// The name a control's key is saved under, such as `key_confirm`.
+fn key_setting(input: NavInput) -> String {
+    format!("key_{}", input.to_string().to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn settings_round_trip() {
+        let mut settings = Settings {
+            fullscreen: false,
+            resolution: Resolution::new(1280, 720),
+            text_speed: TextSpeed::Fast,
+            auto_advance: true,
+            music_volume: Volume::new(30),
+            effects_volume: Volume::new(0),
+            animations: false,
+            keys: KeyBindings::default(),
+        };
+        settings.keys.set(NavInput::Confirm, KeyCode::E);
+
+        let text = settings.to_text();
+        assert!(text.contains("key_confirm = E\n"));
+        assert_eq!(Settings::parse(&text).unwrap(), settings);
+        assert_eq!(settings.dialogue().auto_advance, Some(1.5));
+        assert_eq!(settings.keys.bound_to(KeyCode::E), Some(NavInput::Confirm));
+        assert_eq!(settings.keys.bound_to(KeyCode::Space), None);
+    }
+
+    #[test]
+    fn missing_and_invalid_settings() {
+        // anything left out keeps its default
+        let partial = Settings::parse("[settings]\nfullscreen = false\n").unwrap();
+        assert!(!partial.fullscreen);
+        assert_eq!(partial.text_speed, TextSpeed::Normal);
+        assert_eq!(Settings::parse("").unwrap(), Settings::default());
+
+        for text in [
+            "[settings]\nresolution = 1280*720",
+            "[settings]\nmusic_volume = 150",
+            "[settings]\ntext_speed = ludicrous",
+            "[settings]\nkey_up = Escape",
+            "[settings]\nkey_up = L",
+        ] {
+            assert!(Settings::parse(text).is_err(), "{}", text);
+        }
+    }
+}
@@ -0,0 +1,238 @@
+use crate::settings::{self, Resolution, Settings, TextSpeed, Volume};
+use crate::ui::{self, Button, ButtonLink, NavInput};
+
+use macroquad::prelude::*;
+
+/// A setting shown as a button, clicking it moves on to the next value.
+#[derive(PartialEq, Clone, Copy)]
+enum SettingsRow {
+    Fullscreen,
+    Resolution,
+    TextSpeed,
+    AutoAdvance,
+    MusicVolume,
+    EffectsVolume,
+    Animations,
+    Key(NavInput),
+    Back,
+}
+
+/// Screen for changing the settings, opened from the main menu or the pause menu.
+pub struct SettingsScreen<'a> {
+    /// The control waiting for a new key.
+    rebinding: Option<NavInput>,
+    /// Shown after a key that can't be bound, or is already bound, was pressed.
+    error: Option<String>,
+    empty_button_texture: &'a Texture2D,
+}
+
+impl<'a> SettingsScreen<'a> {
+    pub fn new(empty_button_texture: &'a Texture2D) -> Self {
+        Self {
+            rebinding: None,
+            error: None,
+            empty_button_texture,
+        }
+    }
+
+    /// Draw the screen and change the settings as the player clicks through them.
+    /// Returns true once the player closes it.
+    pub fn update(&mut self, settings: &mut Settings) -> bool {
+        draw_text("Settings", 100.0, 100.0, 40.0, WHITE);
+
+        if let Some(input) = self.rebinding {
+            self.rebind(settings, input);
+            return false;
+        }
+
+        let rows = [
+            (SettingsRow::Fullscreen, "Fullscreen"),
+            (SettingsRow::Resolution, "Window size"),
+            (SettingsRow::TextSpeed, "Text speed"),
+            (SettingsRow::AutoAdvance, "Auto advance"),
+            (SettingsRow::MusicVolume, "Music"),
+            (SettingsRow::EffectsVolume, "Sound effects"),
+            (SettingsRow::Animations, "Animations"),
+            (SettingsRow::Back, "Back"),
+        ];
+        let mut links: Vec<ButtonLink<SettingsRow>> = Vec::new();
+        for (i, (row, label)) in rows.into_iter().enumerate() {
+            links.push(ButtonLink {
+                link: row,
+                button: Button::text(
+                    self.empty_button_texture,
+                    row_label(settings, row, label),
+                    100.0,
+                    150.0 + (75.0 * i as f32),
+                ),
+            });
+        }
+
+        // the keys down the right
+        for (i, input) in NavInput::ALL.into_iter().enumerate() {
+            let row = SettingsRow::Key(input);
+            links.push(ButtonLink {
+                link: row,
+                button: Button::text(
+                    self.empty_button_texture,
+                    row_label(settings, row, &input.to_string()),
+                    800.0,
+                    150.0 + (75.0 * i as f32),
+                ),
+            });
+        }
+
+        let theme = ui::theme();
+        let mut clicked = None;
+        for link in links.iter_mut() {
+            if link.button.clicked() {
+                clicked = Some(link.link);
+            }
+            link.button.draw();
+        }
+        draw_text(
+            "Arrow keys, Enter and Escape always work as well",
+            800.0,
+            640.0,
+            25.0,
+            theme.muted_color,
+        );
+        if let Some(ref error) = self.error {
+            draw_text(error.as_str(), 800.0, 680.0, 30.0, theme.error_color);
+        }
+
+        if NavInput::Back.pressed() {
+            clicked = Some(SettingsRow::Back);
+        }
+
+        match clicked {
+            Some(SettingsRow::Back) => {
+                self.error = None;
+                return true;
+            }
+            Some(SettingsRow::Key(input)) => {
+                self.error = None;
+                self.rebinding = Some(input);
+            }
+            Some(row) => next_value(settings, row),
+            None => {}
+        }
+        false
+    }
+
+    /// Wait for a key to bind to a control, Escape cancels.
+    fn rebind(&mut self, settings: &mut Settings, input: NavInput) {
+        draw_text(
+            format!("Press a key for {}, or Escape to cancel", input).as_str(),
+            100.0,
+            200.0,
+            40.0,
+            WHITE,
+        );
+
+        let key = match get_last_key_pressed() {
+            Some(key) => key,
+            None => return,
+        };
+        self.rebinding = None;
+        if key == KeyCode::Escape {
+            return;
+        }
+        if !settings::BINDABLE_KEYS.contains(&key) {
+            self.error = Some(format!("{:?} can't be bound", key));
+            return;
+        }
+        match settings.keys.bound_to(key) {
+            Some(other) if other != input => {
+                self.error = Some(format!("{:?} is already used for {}", key, other))
+            }
+            _ => settings.keys.set(input, key),
+        }
+    }
+}
+
+/// A setting's name with its current value, shown on its button.
+fn row_label(settings: &Settings, row: SettingsRow, name: &str) -> String {
+    match value_text(settings, row) {
+        Some(value) => format!("{}: {}", name, value),
+        None => String::from(name),
+    }
+}
+
+/// The current value of a setting.
+fn value_text(settings: &Settings, row: SettingsRow) -> Option<String> {
+    let on_off = |on: bool| match on {
+        true => String::from("On"),
+        false => String::from("Off"),
+    };
+    match row {
+        SettingsRow::Fullscreen => Some(on_off(settings.fullscreen)),
+        SettingsRow::Resolution => Some(settings.resolution.to_string()),
+        SettingsRow::TextSpeed => Some(settings.text_speed.to_string()),
+        SettingsRow::AutoAdvance => Some(on_off(settings.auto_advance)),
+        SettingsRow::MusicVolume => Some(format!("{}%", settings.music_volume.percent())),
+        SettingsRow::EffectsVolume => Some(format!("{}%", settings.effects_volume.percent())),
+        SettingsRow::Animations => Some(on_off(settings.animations)),
+        SettingsRow::Key(input) => Some(format!("{:?}", settings.keys.get(input))),
+        SettingsRow::Back => None,
+    }
+}
+
+/// Move a setting on to its next value, wrapping round at the end.
+fn next_value(settings: &mut Settings, row: SettingsRow) {
+    let next_volume = |volume: Volume| match volume.percent() {
+        100 => Volume::new(0),
+        percent => Volume::new(percent + 10),
+    };
+    match row {
+        SettingsRow::Fullscreen => settings.fullscreen = !settings.fullscreen,
+        SettingsRow::Resolution => {
+            let choices = Resolution::CHOICES;
+            settings.resolution = match choices.iter().position(|&r| r == settings.resolution) {
+                Some(i) => choices[(i + 1) % choices.len()],
+                None => choices[0],
+            };
+        }
+        SettingsRow::TextSpeed => {
+            let speeds = TextSpeed::ALL;
+            let i = speeds
+                .iter()
+                .position(|&speed| speed == settings.text_speed)
+                .unwrap_or_default();
+            settings.text_speed = speeds[(i + 1) % speeds.len()];
+        }
+        SettingsRow::AutoAdvance => settings.auto_advance = !settings.auto_advance,
+        SettingsRow::MusicVolume => settings.music_volume = next_volume(settings.music_volume),
+        SettingsRow::EffectsVolume => {
+            settings.effects_volume = next_volume(settings.effects_volume)
+        }
+        SettingsRow::Animations => settings.animations = !settings.animations,
+        SettingsRow::Key(_) | SettingsRow::Back => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_cycle() {
+        let mut settings = Settings::default();
+
+        next_value(&mut settings, SettingsRow::Resolution);
+        assert_eq!(settings.resolution, Resolution::new(2560, 1440));
+        next_value(&mut settings, SettingsRow::Resolution);
+        assert_eq!(settings.resolution, Resolution::new(1280, 720));
+
+        settings.music_volume = Volume::new(95);
+        next_value(&mut settings, SettingsRow::MusicVolume);
+        assert_eq!(settings.music_volume.percent(), 100);
+        next_value(&mut settings, SettingsRow::MusicVolume);
+        assert_eq!(settings.music_volume.percent(), 0);
+
+        for _ in TextSpeed::ALL {
+            next_value(&mut settings, SettingsRow::TextSpeed);
+        }
+        assert_eq!(settings.text_speed, TextSpeed::Normal);
+    }
+}
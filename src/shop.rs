@@ -0,0 +1,227 @@
+use crate::inventory::{Inventory, InventoryError};
+use crate::item::{Item, ItemCatalog, Shop};
+use crate::ui::{Button, ButtonLink, NavInput};
+
+use macroquad::prelude::*;
+
+/// Buttons on the right of the shop screen.
+#[derive(PartialEq, Clone, Copy)]
+enum ShopAction {
+    Buy,
+    Sell,
+    Close,
+}
+
+/// An item picked from one of the two lists.
+#[derive(PartialEq, Clone)]
+enum Selection {
+    Buy(String),
+    Sell(String),
+}
+
+/// Screen for buying items from a shop and selling the player's items back.
+pub struct ShopScreen<'a> {
+    shop: &'a Shop,
+    catalog: &'a ItemCatalog,
+    selected: Option<Selection>,
+    /// What happened after the last buy or sell.
+    status: Option<Result<String, InventoryError>>,
+    empty_button_texture: &'a Texture2D,
+}
+
+impl<'a> ShopScreen<'a> {
+    pub fn new(
+        shop: &'a Shop,
+        catalog: &'a ItemCatalog,
+        empty_button_texture: &'a Texture2D,
+    ) -> Self {
+        Self {
+            shop,
+            catalog,
+            selected: None,
+            status: None,
+            empty_button_texture,
+        }
+    }
+
+    /// Draw and update the shop.
+    /// Returns true once the player closes it.
+    pub fn update(&mut self, inventory: &mut Inventory) -> bool {
+        draw_text(self.shop.name.as_str(), 100.0, 100.0, 40.0, WHITE);
+        draw_text(
+            format!("${}", inventory.money).as_str(),
+            800.0,
+            100.0,
+            40.0,
+            GOLD,
+        );
+
+        let mut previewed = None;
+
+        // what the shop sells down the left
+        let mut stock: Vec<ButtonLink<&Item>> = Vec::new();
+        for id in self.shop.stock.iter() {
+            let item = match self.catalog.get(id) {
+                Some(item) => item,
+                None => continue,
+            };
+            let marker = match self.selected == Some(Selection::Buy(item.id.clone())) {
+                true => ">",
+                false => "",
+            };
+            stock.push(ButtonLink {
+                link: item,
+                button: Button::text(
+                    self.empty_button_texture,
+                    format!("{}{} ${}", marker, item.name, item.price),
+                    100.0,
+                    150.0 + (75.0 * stock.len() as f32),
+                ),
+            });
+        }
+
+        for link in stock.iter_mut() {
+            if link.button.hovered() {
+                previewed = Some(link.link);
+            }
+            if link.button.clicked() {
+                self.selected = Some(Selection::Buy(link.link.id.clone()));
+            }
+            link.button.draw();
+        }
+
+        // the player's items in the middle
+        let mut carried: Vec<ButtonLink<&Item>> = Vec::new();
+        for stack in inventory.items().iter() {
+            let item = match self.catalog.get(&stack.id) {
+                Some(item) => item,
+                None => continue,
+            };
+            let marker = match self.selected == Some(Selection::Sell(item.id.clone())) {
+                true => ">",
+                false => "",
+            };
+            let label = format!(
+                "{}{} x{} ${}",
+                marker,
+                item.name,
+                stack.count,
+                item.sell_price()
+            );
+            carried.push(ButtonLink {
+                link: item,
+                button: Button::text(
+                    self.empty_button_texture,
+                    label,
+                    450.0,
+                    150.0 + (75.0 * carried.len() as f32),
+                ),
+            });
+        }
+
+        for link in carried.iter_mut() {
+            if link.button.hovered() {
+                previewed = Some(link.link);
+            }
+            if link.button.clicked() {
+                self.selected = Some(Selection::Sell(link.link.id.clone()));
+            }
+            link.button.draw();
+        }
+
+        let clicked = match NavInput::Back.pressed() {
+            true => Some(ShopAction::Close),
+            false => self.actions(),
+        };
+
+        // describe whatever is hovered, falling back to the selection
+        let details = previewed.or_else(|| match self.selected {
+            Some(Selection::Buy(ref id)) | Some(Selection::Sell(ref id)) => self.catalog.get(id),
+            None => None,
+        });
+        if let Some(item) = details {
+            let lines = [
+                item.name.clone(),
+                item.description.clone(),
+                format!("Price: ${}", item.price),
+                format!("Sells for: ${}", item.sell_price()),
+                format!("You have: {}", inventory.count(&item.id)),
+            ];
+            for (i, line) in lines.iter().enumerate() {
+                draw_text(
+                    line.as_str(),
+                    1150.0,
+                    170.0 + (35.0 * i as f32),
+                    30.0,
+                    WHITE,
+                );
+            }
+        }
+
+        let status = match self.status {
+            Some(Ok(ref message)) => Some((message.clone(), WHITE)),
+            Some(Err(ref error)) => Some((format!("{}", error), RED)),
+            None => None,
+        };
+        if let Some((text, color)) = status {
+            draw_text(text.as_str(), 450.0, 750.0, 30.0, color);
+        }
+
+        match (clicked, self.selected.clone()) {
+            (Some(ShopAction::Buy), Some(Selection::Buy(id))) => {
+                if let Some(item) = self.catalog.get(&id) {
+                    self.status = Some(
+                        inventory
+                            .buy(item, 1)
+                            .map(|()| format!("You bought a {}.", item.name)),
+                    );
+                }
+            }
+            (Some(ShopAction::Sell), Some(Selection::Sell(id))) => {
+                if let Some(item) = self.catalog.get(&id) {
+                    self.status =
+                        Some(inventory.sell(item, 1).map(|()| {
+                            format!("You sold a {} for ${}.", item.name, item.sell_price())
+                        }));
+                }
+                if inventory.count(&id) == 0 {
+                    self.selected = None;
+                }
+            }
+            (Some(ShopAction::Close), _) => {
+                self.status = None;
+                return true;
+            }
+            _ => {}
+        }
+        false
+    }
+
+    /// Draw the action buttons and return the one that was clicked.
+    fn actions(&self) -> Option<ShopAction> {
+        let actions = [
+            (ShopAction::Buy, "Buy"),
+            (ShopAction::Sell, "Sell"),
+            (ShopAction::Close, "Close"),
+        ];
+
+        let mut clicked = None;
+        for (i, (action, label)) in actions.into_iter().enumerate() {
+            let mut link = ButtonLink {
+                link: action,
+                button: Button::text(
+                    self.empty_button_texture,
+                    label,
+                    800.0,
+                    150.0 + (75.0 * i as f32),
+                ),
+            };
+
+            if link.button.clicked() {
+                clicked = Some(link.link);
+            }
+            link.button.draw();
+        }
+        clicked
+    }
+}
@@ -0,0 +1,259 @@
+use crate::data::{DataError, Document, Section};
+use crate::entity::EntityType;
+
+use macroquad::math::Rect;
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::str::FromStr;
+
+/// What an entity is doing, each has its own frames in a sprite sheet.
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+pub enum SpriteAction {
+    Idle,
+    Attack,
+    Hurt,
+}
+
+impl SpriteAction {
+    pub const ALL: [SpriteAction; 3] =
+        [SpriteAction::Idle, SpriteAction::Attack, SpriteAction::Hurt];
+
+    fn name(&self) -> &'static str {
+        match self {
+            SpriteAction::Idle => "idle",
+            SpriteAction::Attack => "attack",
+            SpriteAction::Hurt => "hurt",
+        }
+    }
+}
+
+/// Which way an entity faces, the far side is seen from the front and the near side from behind.
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+pub enum View {
+    Front,
+    Back,
+}
+
+impl View {
+    pub const ALL: [View; 2] = [View::Front, View::Back];
+
+    fn name(&self) -> &'static str {
+        match self {
+            View::Front => "front",
+            View::Back => "back",
+        }
+    }
+
+    fn other(&self) -> View {
+        match self {
+            View::Front => View::Back,
+            View::Back => View::Front,
+        }
+    }
+}
+
+/// Size of a cell in a sprite sheet, written as `300x300`.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct FrameSize {
+    pub width: u16,
+    pub height: u16,
+}
+
+impl FromStr for FrameSize {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (width, height) = s.split_once('x').ok_or(())?;
+        let width = width.trim().parse().map_err(|_| ())?;
+        let height = height.trim().parse().map_err(|_| ())?;
+        match (width, height) {
+            (0, _) | (_, 0) => Err(()),
+            _ => Ok(Self { width, height }),
+        }
+    }
+}
+
+/// How an entity type's texture is split into frames and which frames make up each clip.
+/// Frames are numbered left to right, then top to bottom.
+#[derive(PartialEq, Clone, Debug)]
+pub struct SpriteSheet {
+    pub frame: FrameSize,
+    /// Frames shown per second.
+    pub fps: f32,
+    clips: HashMap<(View, SpriteAction), Vec<usize>>,
+}
+
+impl SpriteSheet {
+    const DEFAULT_FPS: f32 = 8.0;
+
+    fn from_section(section: &Section) -> Result<Self, DataError> {
+        let fps: f32 = section.parse_or("fps", Self::DEFAULT_FPS)?;
+        if fps.is_nan() || fps <= 0.0 {
+            return Err(section.invalid("fps", section.require("fps")?));
+        }
+
+        let mut clips = HashMap::new();
+        for view in View::ALL {
+            for action in SpriteAction::ALL {
+                let key = format!("{}_{}", view.name(), action.name());
+                let frames: Vec<usize> = section.parse_list(&key)?;
+                if !frames.is_empty() {
+                    clips.insert((view, action), frames);
+                }
+            }
+        }
+        // every sheet needs at least one clip for the others to fall back to
+        if clips.is_empty() {
+            return Err(DataError::Missing {
+                section: section.name.clone(),
+                key: String::from("front_idle"),
+            });
+        }
+
+        Ok(Self {
+            frame: section.parse("frame")?,
+            fps,
+            clips,
+        })
+    }
+
+    /// The frames to play for an action, and whether they have to be mirrored.
+    /// Missing actions fall back to idle, and a missing view to the other one mirrored.
+    pub fn clip(&self, view: View, action: SpriteAction) -> (&[usize], bool) {
+        let choices = [
+            (view, action, false),
+            (view, SpriteAction::Idle, false),
+            (view.other(), action, true),
+            (view.other(), SpriteAction::Idle, true),
+        ];
+        choices
+            .into_iter()
+            .find_map(|(view, action, flip)| {
+                self.clips
+                    .get(&(view, action))
+                    .map(|frames| (frames.as_slice(), flip))
+            })
+            .unwrap_or((&[0], false))
+    }
+
+    /// Seconds a clip takes to play once.
+    pub fn duration(&self, view: View, action: SpriteAction) -> f32 {
+        self.clip(view, action).0.len() as f32 / self.fps
+    }
+
+    /// Where a frame is on a texture of the given width.
+    pub fn frame_rect(&self, index: usize, texture_width: f32) -> Rect {
+        let (width, height) = (self.frame.width as f32, self.frame.height as f32);
+        let columns = ((texture_width / width) as usize).max(1);
+        Rect::new(
+            (index % columns) as f32 * width,
+            (index / columns) as f32 * height,
+            width,
+            height,
+        )
+    }
+}
+
+/// The sprite sheet for each entity type, loaded from the sprites file.
+/// Types without one are drawn as a single frame covering their whole texture.
+#[derive(Clone, Debug, Default)]
+pub struct SpriteBook {
+    sheets: HashMap<EntityType, SpriteSheet>,
+}
+
+impl SpriteBook {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, DataError> {
+        Self::from_document(&Document::load(path)?)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, DataError> {
+        Self::from_document(&Document::parse(text)?)
+    }
+
+    fn from_document(document: &Document) -> Result<Self, DataError> {
+        let mut sheets = HashMap::new();
+        for section in document.sections_named("sprite") {
+            let entity_type: EntityType = section.parse("entity")?;
+            if sheets.contains_key(&entity_type) {
+                return Err(section.invalid("entity", section.require("entity")?));
+            }
+            sheets.insert(entity_type, SpriteSheet::from_section(section)?);
+        }
+        Ok(Self { sheets })
+    }
+
+    pub fn get(&self, entity_type: EntityType) -> Option<&SpriteSheet> {
+        self.sheets.get(&entity_type)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_sprite_sheets() {
+        let book = SpriteBook::parse(
+            "
+[sprite]
+entity = Go
+frame = 100x50
+fps = 4
+front_idle = 0, 1
+front_hurt = 2
+back_idle = 3
+",
+        )
+        .unwrap();
+
+        let sheet = book.get(EntityType::Go).unwrap();
+        assert_eq!(
+            sheet.clip(View::Front, SpriteAction::Idle),
+            (&[0, 1][..], false)
+        );
+        // missing actions fall back to idle in the same view
+        assert_eq!(
+            sheet.clip(View::Front, SpriteAction::Attack),
+            (&[0, 1][..], false)
+        );
+        assert_eq!(
+            sheet.clip(View::Back, SpriteAction::Hurt),
+            (&[3][..], false)
+        );
+        assert_eq!(sheet.duration(View::Front, SpriteAction::Idle), 0.5);
+        assert_eq!(
+            sheet.frame_rect(3, 200.0),
+            Rect::new(100.0, 50.0, 100.0, 50.0)
+        );
+        assert!(book.get(EntityType::Rust).is_none());
+
+        let mirrored =
+            SpriteBook::parse("[sprite]\nentity = Js\nframe = 10x10\nfront_idle = 0").unwrap();
+        let sheet = mirrored.get(EntityType::Js).unwrap();
+        assert_eq!(sheet.clip(View::Back, SpriteAction::Hurt), (&[0][..], true));
+
+        for text in [
+            "[sprite]\nentity = Go\nframe = 10x10",
+            "[sprite]\nentity = Go\nframe = 10\nfront_idle = 0",
+            "[sprite]\nentity = Go\nframe = 10x10\nfps = 0\nfront_idle = 0",
+            "[sprite]\nentity = Go\nframe = 10x10\nfront_idle = a",
+        ] {
+            assert!(SpriteBook::parse(text).is_err(), "{}", text);
+        }
+
+        // the shipped sheets draw every action from both sides
+        let shipped = SpriteBook::load("assets/sprites.data").unwrap();
+        for entity_type in EntityType::ALL {
+            let sheet = shipped.get(entity_type).unwrap();
+            for view in View::ALL {
+                let clips: Vec<_> = SpriteAction::ALL
+                    .into_iter()
+                    .map(|action| sheet.clip(view, action))
+                    .collect();
+                assert!(clips.iter().all(|(_, flip)| !flip));
+                assert!(clips[1..].iter().all(|clip| *clip != clips[0]));
+            }
+        }
+    }
+}
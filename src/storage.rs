@@ -0,0 +1,294 @@
+use crate::entity::{Entity, EntityType};
+use crate::{Team, TeamError};
+
+use std::fmt;
+
+/// Ways to order the entities in storage.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum SortKey {
+    Type,
+    Level,
+    Health,
+    Attack,
+    Defense,
+    Accuracy,
+}
+
+impl SortKey {
+    pub const ALL: [SortKey; 6] = [
+        SortKey::Type,
+        SortKey::Level,
+        SortKey::Health,
+        SortKey::Attack,
+        SortKey::Defense,
+        SortKey::Accuracy,
+    ];
+
+    /// The key after this one, wrapping back to the first.
+    pub fn next(&self) -> Self {
+        let i = Self::ALL.iter().position(|key| key == self).unwrap_or(0);
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+}
+
+impl fmt::Display for SortKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            SortKey::Type => "Type",
+            SortKey::Level => "Level",
+            SortKey::Health => "HP",
+            SortKey::Attack => "Attack",
+            SortKey::Defense => "Defense",
+            SortKey::Accuracy => "Accuracy",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Which stored entities to list, every field that is set has to match.
+#[derive(PartialEq, Clone, Debug, Default)]
+pub struct Filter {
+    pub entity_type: Option<EntityType>,
+    pub min_level: Option<u32>,
+}
+
+impl Filter {
+    pub fn matches(&self, entity: &Entity) -> bool {
+        self.entity_type
+            .is_none_or(|entity_type| entity.entity_type == entity_type)
+            && self.min_level.is_none_or(|level| entity.level >= level)
+    }
+}
+
+/// The package registry, where entities that aren't in the team are kept.
+#[derive(Default)]
+pub struct Storage {
+    pub entities: Vec<Entity>,
+}
+
+impl Storage {
+    pub fn new() -> Self {
+        Self {
+            entities: Vec::new(),
+        }
+    }
+
+    pub fn deposit(&mut self, entity: Entity) {
+        self.entities.push(entity);
+    }
+
+    pub fn withdraw(&mut self, index: usize) -> Result<Entity, TeamError> {
+        if index >= self.entities.len() {
+            return Err(TeamError::InvalidIndex(index));
+        }
+        Ok(self.entities.remove(index))
+    }
+
+    /// Move a team member into storage, the team has to keep at least one entity and one that can battle.
+    /// The first healthy member leads afterwards.
+    pub fn deposit_from(&mut self, team: &mut Team, index: usize) -> Result<(), TeamError> {
+        if team.entities.len() == 1 && index == 0 {
+            return Err(TeamError::LastEntity);
+        }
+        if index < team.entities.len() && !healthy_besides(team, index) {
+            return Err(TeamError::NoHealthyEntity);
+        }
+        let entity = team.remove(index)?;
+        self.deposit(entity);
+        team.lead_with_first_healthy();
+        Ok(())
+    }
+
+    /// Move a stored entity to the end of the team.
+    pub fn withdraw_to(&mut self, team: &mut Team, index: usize) -> Result<(), TeamError> {
+        if team.entities.len() >= Team::CAPACITY {
+            return Err(TeamError::Full);
+        }
+        let entity = self.withdraw(index)?;
+        team.push(entity)
+    }
+
+    /// Swap a team member with a stored entity, the stored entity takes its place in the team.
+    /// The team has to keep one entity that can battle, and the first healthy member leads afterwards.
+    pub fn swap_with(
+        &mut self,
+        team: &mut Team,
+        team_index: usize,
+        index: usize,
+    ) -> Result<(), TeamError> {
+        if index >= self.entities.len() {
+            return Err(TeamError::InvalidIndex(index));
+        }
+        if team_index >= team.entities.len() {
+            return Err(TeamError::InvalidIndex(team_index));
+        }
+        if self.entities[index].health == 0 && !healthy_besides(team, team_index) {
+            return Err(TeamError::NoHealthyEntity);
+        }
+        let member = &mut team.entities[team_index];
+        std::mem::swap(member, &mut self.entities[index]);
+        team.lead_with_first_healthy();
+        Ok(())
+    }
+
+    /// Order the stored entities, types in their usual order and everything else highest first.
+    pub fn sort(&mut self, key: SortKey) {
+        match key {
+            SortKey::Type => self.entities.sort_by(|a, b| {
+                a.entity_type
+                    .cmp(&b.entity_type)
+                    .then(b.level.cmp(&a.level))
+            }),
+            SortKey::Level => self.entities.sort_by_key(|e| std::cmp::Reverse(e.level)),
+            SortKey::Health => self.entities.sort_by_key(|e| std::cmp::Reverse(e.health)),
+            SortKey::Attack => self.entities.sort_by_key(|e| std::cmp::Reverse(e.attack)),
+            SortKey::Defense => self.entities.sort_by_key(|e| std::cmp::Reverse(e.defense)),
+            SortKey::Accuracy => self.entities.sort_by_key(|e| std::cmp::Reverse(e.accuracy)),
+        }
+    }
+
+    /// Indexes of the stored entities that match a filter.
+    pub fn filtered(&self, filter: &Filter) -> Vec<usize> {
+        (0..self.entities.len())
+            .filter(|&i| filter.matches(&self.entities[i]))
+            .collect()
+    }
+}
+
+/// True if a team member other than the one at `index` can battle.
+fn healthy_besides(team: &Team, index: usize) -> bool {
+    team.entities
+        .iter()
+        .enumerate()
+        .any(|(i, entity)| i != index && entity.health > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn storage(entities: &[(EntityType, u32)]) -> Storage {
+        let mut storage = Storage::new();
+        for (entity_type, level) in entities {
+            storage.deposit(entity_type.build(*level, None));
+        }
+        storage
+    }
+
+    #[test]
+    fn move_between_team_and_storage() {
+        let mut team = Team::new();
+        team.push(EntityType::Rust.build(0, None)).unwrap();
+        let mut storage = storage(&[(EntityType::Go, 1), (EntityType::Js, 2)]);
+
+        assert_eq!(
+            storage.deposit_from(&mut team, 0),
+            Err(TeamError::LastEntity)
+        );
+
+        storage.withdraw_to(&mut team, 0).unwrap();
+        assert_eq!(team.entities[1].entity_type, EntityType::Go);
+        assert_eq!(storage.entities.len(), 1);
+
+        storage.swap_with(&mut team, 0, 0).unwrap();
+        assert_eq!(team.entities[0].entity_type, EntityType::Js);
+        assert_eq!(storage.entities[0].entity_type, EntityType::Rust);
+
+        storage.deposit_from(&mut team, 1).unwrap();
+        assert_eq!(team.entities.len(), 1);
+        assert_eq!(storage.entities.len(), 2);
+        assert_eq!(
+            storage.withdraw(5).map(|_| ()),
+            Err(TeamError::InvalidIndex(5))
+        );
+
+        while team.push(EntityType::Cpp.build(0, None)).is_ok() {}
+        assert_eq!(storage.withdraw_to(&mut team, 0), Err(TeamError::Full));
+        assert_eq!(storage.entities.len(), 2);
+    }
+
+    #[test]
+    fn fainted_entities_never_lead() {
+        let mut team = Team::new();
+        for entity_type in [EntityType::Rust, EntityType::Go, EntityType::Python] {
+            team.push(entity_type.build(1, None)).unwrap();
+        }
+        let mut storage = storage(&[(EntityType::Js, 2)]);
+        storage.entities[0].health = 0;
+
+        storage.swap_with(&mut team, 0, 0).unwrap();
+        assert_eq!(team.get_active_index(), 1);
+
+        // depositing the lead leaves the fainted entity in front
+        team.swap(0, 1).unwrap();
+        team.set_active(0).unwrap();
+        storage.deposit_from(&mut team, 0).unwrap();
+        assert_eq!(team.entities[0].health, 0);
+        assert_eq!(team.peek_active().unwrap().entity_type, EntityType::Python);
+    }
+
+    #[test]
+    fn depositing_the_last_healthy_entity() {
+        let mut team = Team::new();
+        team.push(EntityType::Rust.build(1, None)).unwrap();
+        team.push(EntityType::Go.build(1, None)).unwrap();
+        team.entities[1].health = 0;
+        let mut storage = Storage::new();
+
+        assert_eq!(
+            storage.deposit_from(&mut team, 0),
+            Err(TeamError::NoHealthyEntity)
+        );
+        assert_eq!(team.entities.len(), 2);
+        assert!(storage.entities.is_empty());
+
+        // the fainted one can still go
+        storage.deposit_from(&mut team, 1).unwrap();
+        assert_eq!(team.entities[0].entity_type, EntityType::Rust);
+    }
+
+    #[test]
+    fn swapping_out_the_last_healthy_entity() {
+        let mut team = Team::new();
+        team.push(EntityType::Rust.build(1, None)).unwrap();
+        team.push(EntityType::Go.build(1, None)).unwrap();
+        team.entities[1].health = 0;
+        let mut storage = storage(&[(EntityType::Js, 2), (EntityType::Cpp, 2)]);
+        storage.entities[0].health = 0;
+
+        assert_eq!(
+            storage.swap_with(&mut team, 0, 0),
+            Err(TeamError::NoHealthyEntity)
+        );
+        assert_eq!(team.entities[0].entity_type, EntityType::Rust);
+        assert_eq!(storage.entities[0].entity_type, EntityType::Js);
+
+        // a healthy entity can take its place
+        storage.swap_with(&mut team, 0, 1).unwrap();
+        assert_eq!(team.peek_active().unwrap().entity_type, EntityType::Cpp);
+    }
+
+    #[test]
+    fn sort_and_filter() {
+        let mut storage = storage(&[
+            (EntityType::Go, 1),
+            (EntityType::Rust, 5),
+            (EntityType::Go, 3),
+        ]);
+
+        storage.sort(SortKey::Level);
+        let levels: Vec<u32> = storage.entities.iter().map(|e| e.level).collect();
+        assert_eq!(levels, vec![5, 3, 1]);
+
+        storage.sort(SortKey::Type);
+        assert_eq!(storage.entities[0].entity_type, EntityType::Rust);
+        assert_eq!(storage.entities[1].level, 3);
+
+        let filter = Filter {
+            entity_type: Some(EntityType::Go),
+            min_level: Some(2),
+        };
+        assert_eq!(storage.filtered(&filter), vec![1]);
+        assert_eq!(storage.filtered(&Filter::default()).len(), 3);
+    }
+}
@@ -1,36 +1,134 @@
 use crate::entity::Entity;
 
+use std::fmt;
+
+#[derive(PartialEq, Debug)]
+pub enum TeamError {
+    Empty,
+    Full,
+    InvalidIndex(usize),
+    /// The entity is the only one left in the team.
+    LastEntity,
+    /// Every entity left in the team would have fainted.
+    NoHealthyEntity,
+}
+
+impl fmt::Display for TeamError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TeamError::Empty => write!(f, "the team has no entities"),
+            TeamError::Full => write!(f, "the team already has {} entities", Team::CAPACITY),
+            TeamError::InvalidIndex(index) => write!(f, "there is no entity at position {}", index),
+            TeamError::LastEntity => write!(f, "the team needs at least one entity"),
+            TeamError::NoHealthyEntity => {
+                write!(f, "the team needs at least one entity that can battle")
+            }
+        }
+    }
+}
+
+impl std::error::Error for TeamError {}
+
 pub struct Team {
     pub entities: Vec<Entity>,
     active: usize,
 }
 
+impl Default for Team {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Team {
+    /// The most entities a team can hold.
+    pub const CAPACITY: usize = 6;
+
     pub fn new() -> Self {
         Team {
-            entities: Vec::with_capacity(6),
+            entities: Vec::with_capacity(Self::CAPACITY),
             active: 0,
         }
     }
 
-    pub fn set_active(&mut self, index: usize) -> Result<(), ()> {
+    pub fn set_active(&mut self, index: usize) -> Result<(), TeamError> {
         if index < self.entities.len() {
             self.active = index;
             return Ok(());
         }
-        Err(())
+        Err(TeamError::InvalidIndex(index))
     }
 
-    pub fn get_active(&mut self) -> Option<&mut Entity> {
-        Some(&mut self.entities[self.active])
+    pub fn get_active(&mut self) -> Result<&mut Entity, TeamError> {
+        self.entities.get_mut(self.active).ok_or(TeamError::Empty)
+    }
+
+    /// Look at the active entity without changing it.
+    pub fn peek_active(&self) -> Result<&Entity, TeamError> {
+        self.entities.get(self.active).ok_or(TeamError::Empty)
     }
 
     pub fn get_active_index(&self) -> usize {
         self.active
     }
 
-    pub fn push(&mut self, entity: Entity) {
+    /// True if the entity at `index` can be switched in, it has to be another member that hasn't fainted.
+    pub fn can_switch_to(&self, index: usize) -> bool {
+        index != self.active && self.entities.get(index).is_some_and(|e| e.health > 0)
+    }
+
+    pub fn push(&mut self, entity: Entity) -> Result<(), TeamError> {
+        if self.entities.len() >= Self::CAPACITY {
+            return Err(TeamError::Full);
+        }
         self.entities.push(entity);
+        Ok(())
+    }
+
+    /// Restore every entity to full health and make the first one active again.
+    pub fn heal_all(&mut self) {
+        for entity in self.entities.iter_mut() {
+            entity.heal(entity.max_health);
+        }
+        self.active = 0;
+    }
+
+    /// Swap the positions of two entities, keeping the same entity active.
+    pub fn swap(&mut self, a: usize, b: usize) -> Result<(), TeamError> {
+        for index in [a, b] {
+            if index >= self.entities.len() {
+                return Err(TeamError::InvalidIndex(index));
+            }
+        }
+
+        self.entities.swap(a, b);
+        if self.active == a {
+            self.active = b;
+        } else if self.active == b {
+            self.active = a;
+        }
+        Ok(())
+    }
+
+    /// Make the first entity that can still battle lead, used after the team is rearranged.
+    /// Nothing changes if every entity has fainted.
+    pub fn lead_with_first_healthy(&mut self) {
+        if let Some(lead) = self.entities.iter().position(|entity| entity.health > 0) {
+            self.active = lead;
+        }
+    }
+
+    /// Remove an entity, keeping the same entity active where possible.
+    pub fn remove(&mut self, index: usize) -> Result<Entity, TeamError> {
+        if index >= self.entities.len() {
+            return Err(TeamError::InvalidIndex(index));
+        }
+
+        let entity = self.entities.remove(index);
+        if self.active > index || self.active >= self.entities.len() {
+            self.active = self.active.saturating_sub(1);
+        }
+        Ok(entity)
     }
 }
 
@@ -45,11 +143,73 @@ mod tests {
         let rust = RustEntity::build(0, None);
         let python = PythonEntity::build(0, None);
 
-        team.push(rust);
-        team.push(python);
+        team.push(rust).unwrap();
+        team.push(python).unwrap();
 
         assert_eq!(EntityType::Rust, team.get_active().unwrap().entity_type);
         let _ = team.set_active(1);
         assert_eq!(EntityType::Python, team.get_active().unwrap().entity_type);
     }
+
+    #[test]
+    fn reorder_keeps_active() {
+        let mut team = Team::new();
+        team.push(RustEntity::build(0, None)).unwrap();
+        team.push(PythonEntity::build(0, None)).unwrap();
+        team.push(RustEntity::build(0, None)).unwrap();
+        team.set_active(1).unwrap();
+
+        team.swap(0, 1).unwrap();
+        assert_eq!(team.get_active_index(), 0);
+        assert_eq!(EntityType::Python, team.get_active().unwrap().entity_type);
+
+        team.remove(2).unwrap();
+        assert_eq!(EntityType::Python, team.get_active().unwrap().entity_type);
+        team.swap(0, 1).unwrap();
+        team.remove(0).unwrap();
+        assert_eq!(team.get_active_index(), 0);
+        assert_eq!(EntityType::Python, team.get_active().unwrap().entity_type);
+    }
+
+    #[test]
+    fn heal_all_test() {
+        let mut team = Team::new();
+        team.push(RustEntity::build(0, None)).unwrap();
+        team.push(PythonEntity::build(0, None)).unwrap();
+        team.entities[0].health = 0;
+        team.entities[1].health = 3;
+        team.set_active(1).unwrap();
+
+        team.heal_all();
+        assert!(team.entities.iter().all(|e| e.health == e.max_health));
+        assert_eq!(team.get_active_index(), 0);
+    }
+
+    #[test]
+    fn switch_targets() {
+        let mut team = Team::new();
+        team.push(RustEntity::build(0, None)).unwrap();
+        team.push(PythonEntity::build(0, None)).unwrap();
+        team.push(RustEntity::build(0, None)).unwrap();
+        team.entities[2].health = 0;
+
+        assert!(team.can_switch_to(1));
+        assert!(!team.can_switch_to(0));
+        assert!(!team.can_switch_to(2));
+        assert!(!team.can_switch_to(3));
+    }
+
+    #[test]
+    fn errors_instead_of_panics() {
+        let mut team = Team::new();
+        assert_eq!(team.get_active().err(), Some(TeamError::Empty));
+        assert_eq!(team.set_active(0), Err(TeamError::InvalidIndex(0)));
+        assert_eq!(team.remove(0).err(), Some(TeamError::InvalidIndex(0)));
+        assert_eq!(team.swap(0, 1), Err(TeamError::InvalidIndex(0)));
+
+        for _ in 0..Team::CAPACITY {
+            team.push(RustEntity::build(0, None)).unwrap();
+        }
+        assert_eq!(team.push(RustEntity::build(0, None)), Err(TeamError::Full));
+    }
 }
@@ -0,0 +1,250 @@
+use crate::entity::{Entity, EntityType};
+use crate::rules::{RuleViolation, Ruleset};
+use crate::ui::{draw_entity_details, Button, ButtonLink};
+use crate::Team;
+
+use ::rand::Rng;
+use macroquad::prelude::*;
+
+use std::collections::HashMap;
+
+/// Number of entities in a randomly generated team.
+const RANDOM_TEAM_SIZE: usize = 3;
+
+/// Actions for the team member currently selected in the builder.
+#[derive(PartialEq, Clone, Copy)]
+enum MemberAction {
+    Up,
+    Down,
+    Remove,
+}
+
+/// Screen where a player picks and orders the entities for a team before a battle.
+pub struct TeamBuilder<'a> {
+    title: String,
+    team: Team,
+    selected: Option<usize>,
+    allow_random: bool,
+    ruleset: Ruleset,
+    violations: Vec<RuleViolation>,
+    textures: &'a HashMap<EntityType, Texture2D>,
+    empty_button_texture: &'a Texture2D,
+}
+
+impl<'a> TeamBuilder<'a> {
+    pub fn new(
+        title: &str,
+        textures: &'a HashMap<EntityType, Texture2D>,
+        empty_button_texture: &'a Texture2D,
+    ) -> Self {
+        Self {
+            title: String::from(title),
+            team: Team::new(),
+            selected: None,
+            allow_random: false,
+            ruleset: Ruleset::default(),
+            violations: Vec::new(),
+            textures,
+            empty_button_texture,
+        }
+    }
+
+    /// Offer a button that finishes with a randomly generated team.
+    pub fn allow_random(&mut self) {
+        self.allow_random = true;
+    }
+
+    /// Set the rules the finished team has to follow.
+    pub fn set_ruleset(&mut self, ruleset: Ruleset) {
+        self.ruleset = ruleset;
+    }
+
+    fn build(&self, entity_type: EntityType) -> Entity {
+        entity_type.build(0, self.textures.get(&entity_type).cloned())
+    }
+
+    /// Generate a team of random entities that follows the ruleset.
+    pub fn random_team(&self) -> Team {
+        let mut rng = ::rand::thread_rng();
+        let mut choices = EntityType::ALL.to_vec();
+        let mut team = Team::new();
+
+        while team.entities.len() < RANDOM_TEAM_SIZE.min(self.ruleset.max_team_size) {
+            if choices.is_empty() {
+                break;
+            }
+            let i = rng.gen_range(0..choices.len());
+            let entity_type = match self.ruleset.species_clause {
+                true => choices.remove(i),
+                false => choices[i],
+            };
+            if team.push(self.build(entity_type)).is_err() {
+                break;
+            }
+        }
+        team
+    }
+
+    /// Draw and update the builder.
+    /// Returns the finished team once the player is done picking.
+    pub fn update(&mut self) -> Option<Team> {
+        draw_text(self.title.as_str(), 100.0, 100.0, 40.0, WHITE);
+
+        let mut previewed: Option<Entity> = None;
+
+        // buttons for adding each entity type
+        let full = self.team.entities.len() >= self.ruleset.max_team_size;
+        let mut links: Vec<ButtonLink<EntityType>> = Vec::new();
+        for (i, entity_type) in EntityType::ALL.iter().enumerate() {
+            let mut button = Button::text(
+                self.empty_button_texture,
+                entity_type.to_string(),
+                100.0,
+                150.0 + (75.0 * i as f32),
+            );
+            button.set_disabled(full);
+            links.push(ButtonLink {
+                link: *entity_type,
+                button,
+            });
+        }
+
+        for link in links.iter_mut() {
+            if link.button.hovered() {
+                previewed = Some(self.build(link.link));
+            }
+            if link.button.clicked() && self.team.push(self.build(link.link)).is_ok() {
+                self.selected = Some(self.team.entities.len() - 1);
+                self.violations.clear();
+            }
+            link.button.draw();
+        }
+
+        // buttons for each team member, clicking one selects it
+        let mut members: Vec<ButtonLink<usize>> = Vec::new();
+        for (i, entity) in self.team.entities.iter().enumerate() {
+            let marker = match self.selected == Some(i) {
+                true => ">",
+                false => "",
+            };
+            members.push(ButtonLink {
+                link: i,
+                button: Button::text(
+                    self.empty_button_texture,
+                    format!("{}{}. {}", marker, i + 1, entity),
+                    450.0,
+                    150.0 + (75.0 * i as f32),
+                ),
+            });
+        }
+
+        for link in members.iter_mut() {
+            if link.button.hovered() {
+                previewed = Some(self.team.entities[link.link].clone());
+            }
+            if link.button.clicked() {
+                self.selected = Some(link.link);
+            }
+            link.button.draw();
+        }
+
+        if let Some(selected) = self.selected {
+            self.member_actions(selected);
+        }
+
+        // show the details of whatever is hovered, falling back to the selected member
+        let details = match (previewed, self.selected) {
+            (Some(entity), _) => Some(entity),
+            (None, Some(selected)) => self.team.entities.get(selected).cloned(),
+            (None, None) => None,
+        };
+        if let Some(entity) = details {
+            draw_entity_details(&entity, 1150.0, 170.0);
+        }
+
+        if self.allow_random {
+            let mut random_button =
+                Button::text(self.empty_button_texture, "Random team", 800.0, 650.0);
+            random_button.draw();
+            if random_button.clicked() {
+                return Some(self.random_team());
+            }
+        }
+
+        if self.team.entities.is_empty() {
+            return None;
+        }
+
+        let mut done_button = Button::text(self.empty_button_texture, "Done", 450.0, 650.0);
+        done_button.draw();
+
+        // list anything stopping the team from being used
+        for (i, violation) in self.violations.iter().enumerate() {
+            draw_text(
+                format!("{}", violation).as_str(),
+                450.0,
+                750.0 + (35.0 * i as f32),
+                30.0,
+                RED,
+            );
+        }
+
+        if done_button.clicked() {
+            match self.ruleset.validate(&self.team) {
+                Ok(()) => {
+                    self.selected = None;
+                    return Some(std::mem::take(&mut self.team));
+                }
+                Err(violations) => self.violations = violations,
+            }
+        }
+        None
+    }
+
+    /// Draw the buttons to reorder or remove the selected team member.
+    fn member_actions(&mut self, selected: usize) {
+        let mut links = [
+            (MemberAction::Up, "Move up"),
+            (MemberAction::Down, "Move down"),
+            (MemberAction::Remove, "Remove"),
+        ]
+        .into_iter()
+        .enumerate()
+        .map(|(i, (action, label))| ButtonLink {
+            link: action,
+            button: Button::text(
+                self.empty_button_texture,
+                label,
+                800.0,
+                150.0 + (75.0 * i as f32),
+            ),
+        })
+        .collect::<Vec<_>>();
+
+        let mut clicked = None;
+        for link in links.iter_mut() {
+            if link.button.clicked() {
+                clicked = Some(link.link);
+            }
+            link.button.draw();
+        }
+
+        let result = match clicked {
+            Some(MemberAction::Up) if selected > 0 => self
+                .team
+                .swap(selected, selected - 1)
+                .map(|_| Some(selected - 1)),
+            Some(MemberAction::Down) => self
+                .team
+                .swap(selected, selected + 1)
+                .map(|_| Some(selected + 1)),
+            Some(MemberAction::Remove) => self.team.remove(selected).map(|_| None),
+            _ => return,
+        };
+
+        if let Ok(selected) = result {
+            self.selected = selected;
+            self.violations.clear();
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use crate::data::{DataError, Document};
+
+use macroquad::color::{colors, Color};
+
+use std::path::{Path, PathBuf};
+
+/// The fonts, colours, sizes and button skin the UI is drawn with, loaded from the theme file.
+#[derive(PartialEq, Clone, Debug)]
+pub struct Theme {
+    /// TTF file for every themed piece of text, the built in font is used without one.
+    pub font: Option<PathBuf>,
+    pub text_color: Color,
+    /// Hints and anything that can't be used right now.
+    pub muted_color: Color,
+    pub highlight_color: Color,
+    pub error_color: Color,
+    /// Background of the dialogue box.
+    pub panel_color: Color,
+    /// Asset key of the texture buttons are drawn with.
+    pub button: String,
+    /// Pixels at each edge of a button's texture that keep their size when the button is stretched.
+    pub button_slice: f32,
+    /// Space between the edge of a button and its label.
+    pub padding: f32,
+    pub small_size: u16,
+    /// Button labels and most other text.
+    pub text_size: u16,
+    /// Names above the health bars.
+    pub name_size: u16,
+    pub dialogue_size: u16,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            font: None,
+            text_color: colors::WHITE,
+            muted_color: colors::GRAY,
+            highlight_color: colors::GOLD,
+            error_color: colors::RED,
+            panel_color: colors::BLACK,
+            button: String::from("button/empty"),
+            button_slice: 0.0,
+            padding: 10.0,
+            small_size: 25,
+            text_size: 30,
+            name_size: 35,
+            dialogue_size: 40,
+        }
+    }
+}
+
+impl Theme {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, DataError> {
+        Self::from_document(&Document::load(path)?)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, DataError> {
+        Self::from_document(&Document::parse(text)?)
+    }
+
+    fn from_document(document: &Document) -> Result<Self, DataError> {
+        let defaults = Self::default();
+        let section = match document.section("theme") {
+            Some(section) => section,
+            None => return Ok(defaults),
+        };
+
+        let size = |key: &str, default: u16| match section.parse_or(key, default)? {
+            0 => Err(section.invalid(key, "0")),
+            size => Ok(size),
+        };
+        let length = |key: &str, default: f32| {
+            let value: f32 = section.parse_or(key, default)?;
+            match value.is_nan() || value < 0.0 {
+                true => Err(section.invalid(key, section.require(key)?)),
+                false => Ok(value),
+            }
+        };
+
+        Ok(Self {
+            font: section.get("font").map(PathBuf::from),
+            text_color: section.color_or("text_color", defaults.text_color)?,
+            muted_color: section.color_or("muted_color", defaults.muted_color)?,
+            highlight_color: section.color_or("highlight_color", defaults.highlight_color)?,
+            error_color: section.color_or("error_color", defaults.error_color)?,
+            panel_color: section.color_or("panel_color", defaults.panel_color)?,
+            button: section.get("button").map_or(defaults.button, String::from),
+            button_slice: length("button_slice", defaults.button_slice)?,
+            padding: length("padding", defaults.padding)?,
+            small_size: size("small_size", defaults.small_size)?,
+            text_size: size("text_size", defaults.text_size)?,
+            name_size: size("name_size", defaults.name_size)?,
+            dialogue_size: size("dialogue_size", defaults.dialogue_size)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_theme() {
+        let theme = Theme::parse(
+            "
+[theme]
+font = assets/fonts/mono.ttf
+text_color = #ffeedd
+panel_color = #10203080
+button_slice = 12
+text_size = 28
+",
+        )
+        .unwrap();
+
+        assert_eq!(theme.font, Some(PathBuf::from("assets/fonts/mono.ttf")));
+        assert_eq!(theme.text_color, Color::from_hex(0xffeedd));
+        assert_eq!(theme.panel_color, Color::from_rgba(0x10, 0x20, 0x30, 0x80));
+        assert_eq!(theme.button_slice, 12.0);
+        assert_eq!(theme.text_size, 28);
+        // anything left out keeps the default look
+        assert_eq!(theme.dialogue_size, 40);
+        assert_eq!(theme.muted_color, colors::GRAY);
+        assert_eq!(Theme::parse("").unwrap(), Theme::default());
+
+        for text in [
+            "[theme]\ntext_color = white",
+            "[theme]\ntext_size = 0",
+            "[theme]\npadding = -4",
+            "[theme]\npanel_color = #1020",
+        ] {
+            assert!(Theme::parse(text).is_err(), "{}", text);
+        }
+    }
+}
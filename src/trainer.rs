@@ -0,0 +1,274 @@
+use crate::battle::Strategy;
+use crate::data::{DataError, Document};
+use crate::entity::{Entity, EntityType};
+use crate::moves::{parse_moves, Move};
+use crate::{Team, TeamError};
+
+use macroquad::texture::Texture2D;
+
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::str::FromStr;
+
+/// An entity on a trainer's team, written as `Type:level:Move/Move` such as `Go:4:Async/IntParse`.
+/// The moves are optional and default to the entity's usual moves.
+#[derive(PartialEq, Clone, Debug)]
+pub struct TrainerMember {
+    pub entity_type: EntityType,
+    pub level: u32,
+    pub moves: Vec<Move>,
+}
+
+impl TrainerMember {
+    pub fn build(&self, textures: &HashMap<EntityType, Texture2D>) -> Entity {
+        let mut entity = self
+            .entity_type
+            .build(self.level, textures.get(&self.entity_type).cloned());
+        entity.set_moves(self.moves.clone());
+        entity
+    }
+}
+
+impl FromStr for TrainerMember {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split(':').map(str::trim);
+        let entity_type = parts.next().ok_or(())?.parse()?;
+        let level = match parts.next() {
+            Some(level) => level.parse().map_err(|_| ())?,
+            None => 0,
+        };
+        let moves = match parts.next() {
+            Some(moves) => parse_moves(moves).ok_or(())?,
+            None => Vec::new(),
+        };
+
+        if parts.next().is_some() {
+            return Err(());
+        }
+        Ok(Self {
+            entity_type,
+            level,
+            moves,
+        })
+    }
+}
+
+/// A trainer the player can battle, loaded from the trainers file.
+#[derive(PartialEq, Clone, Debug)]
+pub struct Trainer {
+    pub id: String,
+    pub name: String,
+    /// Path to an image drawn for the trainer on the map.
+    pub sprite: Option<String>,
+    /// Lines said before the battle.
+    pub intro: Vec<String>,
+    /// Lines said after the player wins, and when spoken to again.
+    pub defeat: Vec<String>,
+    pub team: Vec<TrainerMember>,
+    pub strategy: Strategy,
+    /// Money given to the player for winning.
+    pub prize: u32,
+    /// Badge given by framework leaders when they are beaten.
+    pub badge: Option<String>,
+    /// The programming paradigm a leader specialises in.
+    pub paradigm: Option<String>,
+}
+
+impl Trainer {
+    pub fn build_team(&self, textures: &HashMap<EntityType, Texture2D>) -> Result<Team, TeamError> {
+        let mut team = Team::new();
+        for member in self.team.iter() {
+            team.push(member.build(textures))?;
+        }
+        Ok(team)
+    }
+}
+
+/// Every trainer in the game and the order the campaign trainers have to be beaten in.
+#[derive(Clone, Debug, Default)]
+pub struct TrainerBook {
+    trainers: Vec<Trainer>,
+    /// Trainer ids in campaign order, the last one is the final boss.
+    campaign: Vec<String>,
+}
+
+impl TrainerBook {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, DataError> {
+        Self::from_document(&Document::load(path)?)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, DataError> {
+        Self::from_document(&Document::parse(text)?)
+    }
+
+    fn from_document(document: &Document) -> Result<Self, DataError> {
+        let mut trainers: Vec<Trainer> = Vec::new();
+        for section in document.sections_named("trainer") {
+            let id = section.require("id")?;
+            if trainers.iter().any(|trainer| trainer.id == id) {
+                return Err(section.invalid("id", id));
+            }
+
+            trainers.push(Trainer {
+                id: String::from(id),
+                name: String::from(section.require("name")?),
+                sprite: section.get("sprite").map(String::from),
+                intro: section
+                    .get_all("intro")
+                    .into_iter()
+                    .map(String::from)
+                    .collect(),
+                defeat: section
+                    .get_all("defeat")
+                    .into_iter()
+                    .map(String::from)
+                    .collect(),
+                team: section.parse_list("team")?,
+                strategy: section.parse_or("strategy", Strategy::Random)?,
+                prize: section.parse_or("prize", 0)?,
+                badge: section.get("badge").map(String::from),
+                paradigm: section.get("paradigm").map(String::from),
+            });
+        }
+
+        let campaign: Vec<String> = match document.section("campaign") {
+            Some(section) => {
+                let order: Vec<String> = section.parse_list("order")?;
+                for id in order.iter() {
+                    if !trainers.iter().any(|trainer| &trainer.id == id) {
+                        return Err(section.invalid("order", id));
+                    }
+                }
+                order
+            }
+            None => Vec::new(),
+        };
+
+        Ok(Self { trainers, campaign })
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Trainer> {
+        self.trainers.iter().find(|trainer| trainer.id == id)
+    }
+
+    pub fn trainers(&self) -> &[Trainer] {
+        &self.trainers
+    }
+
+    /// The earliest campaign trainer that has to be beaten before this one, if any.
+    pub fn blocker(&self, id: &str, defeated: &HashSet<String>) -> Option<&Trainer> {
+        let position = self.campaign.iter().position(|other| other == id)?;
+        self.campaign[..position]
+            .iter()
+            .find(|other| !defeated.contains(*other))
+            .and_then(|other| self.get(other))
+    }
+
+    /// True for the last trainer of the campaign.
+    pub fn is_final_boss(&self, id: &str) -> bool {
+        self.campaign.last().is_some_and(|last| last == id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::overworld::Map;
+
+    const TEXT: &str = "
+[campaign]
+order = junior, boss
+
+[trainer]
+id = junior
+name = Junior Dev
+intro = Hi!
+intro = Let's battle.
+defeat = Well played.
+team = Js:2, Go:3:Async/IntParse
+prize = 50
+
+[trainer]
+id = boss
+name = CTO
+strategy = smart
+team = Rust:10
+
+[trainer]
+id = intern
+name = Intern
+team = Python
+";
+
+    #[test]
+    fn parse_trainers() {
+        let book = TrainerBook::parse(TEXT).unwrap();
+        let junior = book.get("junior").unwrap();
+        assert_eq!(junior.intro, vec!["Hi!", "Let's battle."]);
+        assert_eq!(junior.prize, 50);
+        assert_eq!(junior.strategy, Strategy::Random);
+        assert_eq!(junior.team[1].moves, vec![Move::Async, Move::IntParse]);
+
+        let team = junior.build_team(&HashMap::new()).unwrap();
+        assert_eq!(
+            team.entities[1].get_moves(),
+            &vec![Move::Async, Move::IntParse]
+        );
+        assert_eq!(
+            team.entities[0].get_moves(),
+            EntityType::Js.build(2, None).get_moves()
+        );
+
+        assert_eq!(book.get("boss").unwrap().strategy, Strategy::Smart);
+        assert!("Go:3:Fly".parse::<TrainerMember>().is_err());
+    }
+
+    #[test]
+    fn too_many_or_repeated_moves() {
+        // an entity can't know more moves than learning allows, or the same move twice
+        for moves in ["Async/IntParse/Math/Speed/Deadline", "Async/Async"] {
+            let text = TEXT.replace("Go:3:Async/IntParse", &format!("Go:3:{}", moves));
+            assert!(
+                matches!(
+                    TrainerBook::parse(&text),
+                    Err(DataError::Invalid { line: 11, .. })
+                ),
+                "{}",
+                moves
+            );
+        }
+    }
+
+    #[test]
+    fn campaign_order() {
+        let book = TrainerBook::parse(TEXT).unwrap();
+        let mut defeated = HashSet::new();
+
+        assert!(book.blocker("junior", &defeated).is_none());
+        assert_eq!(book.blocker("boss", &defeated).unwrap().id, "junior");
+        assert!(book.blocker("intern", &defeated).is_none());
+
+        defeated.insert(String::from("junior"));
+        assert!(book.blocker("boss", &defeated).is_none());
+        assert!(book.is_final_boss("boss"));
+
+        let unknown = TEXT.replace("order = junior, boss", "order = junior, ceo");
+        assert!(matches!(
+            TrainerBook::parse(&unknown),
+            Err(DataError::Invalid { .. })
+        ));
+    }
+
+    #[test]
+    fn maps_use_known_trainers() {
+        let book = TrainerBook::load("assets/trainers.data").unwrap();
+        for id in ["town", "route", "office"] {
+            let map = Map::load("assets/maps", id).unwrap();
+            for spot in map.trainers.iter() {
+                assert!(book.get(&spot.id).is_some(), "unknown trainer {}", spot.id);
+            }
+        }
+    }
+}
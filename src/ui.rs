@@ -1,66 +1,933 @@
+use crate::animation::{AnimationStep, MoveAnimation, StepKind};
+use crate::audio::{self, SoundEffect};
+use crate::entity::Entity;
+use crate::gamepad;
+use crate::layout::{self, Anchor};
+use crate::moves::Move;
+use crate::sprite::{SpriteAction, SpriteSheet, View};
+use crate::theme::Theme;
+
 use macroquad::prelude::*;
 
+use std::cell::RefCell;
+use std::fmt;
+
 pub struct EntityImageParams<'a> {
     pub texture: &'a Option<Texture2D>,
     pub x: f32,
     pub y: f32,
+    /// Tint for the sprite, white draws it as it is.
+    pub color: Color,
+    /// Draws one frame of a sprite sheet instead of the whole texture.
+    pub sprite: Option<(&'a AnimatedSprite, View)>,
 }
 
+/// Height of the dialogue box along the bottom of the screen.
+pub const DIALOGUE_HEIGHT: f32 = 200.0;
+
 pub struct EntityStats<'a> {
     health_bar_texture: &'a Texture2D,
-    xpos: f32,
-    ypos: f32,
+    anchor: Anchor,
+    offset: Vec2,
 }
 
 impl<'a> EntityStats<'a> {
     pub fn new(xpos: f32, ypos: f32, health_bar_texture: &'a Texture2D) -> Self {
+        Self::anchored(Anchor::TopLeft, vec2(xpos, ypos), health_bar_texture)
+    }
+
+    /// Stats drawn at an offset from a point on the screen.
+    pub fn anchored(anchor: Anchor, offset: Vec2, health_bar_texture: &'a Texture2D) -> Self {
         Self {
             health_bar_texture,
-            xpos,
-            ypos,
+            anchor,
+            offset,
         }
     }
 
+    /// Draw the name, health and sprite of an entity.
+    /// The health can be partway between two values while a bar is sliding.
     pub fn update(
         &self,
-        health: u32,
+        health: f32,
         max_health: u32,
         image_params: EntityImageParams,
         name: &str,
     ) {
-        let bar_y = self.ypos + 6.0;
+        let Vec2 { x: xpos, y: ypos } = self.anchor.at(self.offset);
+        let bar_y = ypos + 6.0;
+        let fraction = (health / max_health.max(1) as f32).clamp(0.0, 1.0);
+        let width = self.health_bar_texture.width() * fraction;
         let health_bar_params = DrawTextureParams {
             source: Some(Rect {
                 x: 0.0,
                 y: 0.0,
-                w: self.health_bar_texture.width() * (health as f32 / max_health as f32),
+                w: width,
                 h: self.health_bar_texture.height(),
             }),
             ..Default::default()
         };
 
-        draw_text(
-            format!("{}  {}/{}", name, health, max_health).as_str(),
-            self.xpos,
-            self.ypos,
-            35.0,
-            WHITE,
+        let theme = theme();
+        draw_themed_text(
+            format!("{}  {}/{}", name, health.round(), max_health).as_str(),
+            xpos,
+            ypos,
+            theme.name_size,
+            theme.text_color,
         );
 
         if let Some(ref texture) = image_params.texture {
-            draw_texture(texture, image_params.x, image_params.y, WHITE);
+            let (source, flip_x) = match image_params.sprite {
+                Some((sprite, view)) => {
+                    let (source, flip_x) = sprite.frame(view, texture);
+                    (Some(source), flip_x)
+                }
+                None => (None, false),
+            };
+            draw_texture_ex(
+                texture,
+                image_params.x,
+                image_params.y,
+                image_params.color,
+                DrawTextureParams {
+                    source,
+                    flip_x,
+                    ..Default::default()
+                },
+            );
+        }
+
+        match health_color(fraction) {
+            Some(color) => {
+                draw_rectangle(xpos, bar_y, width, self.health_bar_texture.height(), color)
+            }
+            None => draw_texture_ex(
+                self.health_bar_texture,
+                xpos,
+                bar_y,
+                WHITE,
+                health_bar_params,
+            ),
+        }
+    }
+}
+
+/// The colour of a health bar that is running low, or None for the normal bar.
+fn health_color(fraction: f32) -> Option<Color> {
+    if fraction <= 0.2 {
+        Some(RED)
+    } else if fraction <= 0.5 {
+        Some(ORANGE)
+    } else {
+        None
+    }
+}
+
+/// An entity's sprite sheet playing one clip at a time.
+/// Idle loops, attacking and getting hurt play once and then go back to idle.
+#[derive(Clone, Debug)]
+pub struct AnimatedSprite {
+    /// Without a sheet the whole texture is one frame.
+    sheet: Option<SpriteSheet>,
+    action: SpriteAction,
+    time: f32,
+}
+
+impl AnimatedSprite {
+    pub fn new(sheet: Option<&SpriteSheet>) -> Self {
+        Self {
+            sheet: sheet.cloned(),
+            action: SpriteAction::Idle,
+            time: 0.0,
+        }
+    }
+
+    pub fn action(&self) -> SpriteAction {
+        self.action
+    }
+
+    /// Start playing an action from its first frame.
+    pub fn play(&mut self, action: SpriteAction) {
+        self.action = action;
+        self.time = 0.0;
+    }
+
+    pub fn update(&mut self, delta: f32, view: View) {
+        self.time += delta;
+        let duration = match self.sheet {
+            Some(ref sheet) => sheet.duration(view, self.action),
+            None => 0.0,
+        };
+        if self.action != SpriteAction::Idle && self.time >= duration {
+            self.play(SpriteAction::Idle);
+        }
+    }
+
+    /// The part of the texture to draw this frame, and whether to mirror it.
+    pub fn frame(&self, view: View, texture: &Texture2D) -> (Rect, bool) {
+        let sheet = match self.sheet {
+            Some(ref sheet) => sheet,
+            None => {
+                return (
+                    Rect::new(0.0, 0.0, texture.width(), texture.height()),
+                    false,
+                )
+            }
+        };
+        let (frames, flip) = sheet.clip(view, self.action);
+        let step = (self.time * sheet.fps) as usize;
+        let index = match self.action {
+            SpriteAction::Idle => step % frames.len(),
+            _ => step.min(frames.len() - 1),
+        };
+        (sheet.frame_rect(frames[index], texture.width()), flip)
+    }
+}
+
+/// Seconds a health bar takes to slide to a new value.
+const HEALTH_SLIDE_TIME: f32 = 0.6;
+
+/// Health shown on a bar, sliding from the old value to the new one rather than jumping.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct HealthTween {
+    from: f32,
+    to: u32,
+    elapsed: f32,
+}
+
+impl HealthTween {
+    pub fn new(health: u32) -> Self {
+        Self {
+            from: health as f32,
+            to: health,
+            elapsed: HEALTH_SLIDE_TIME,
+        }
+    }
+
+    /// The health the bar is sliding towards.
+    pub fn target(&self) -> u32 {
+        self.to
+    }
+
+    /// Start sliding from wherever the bar is now to a new value.
+    pub fn set(&mut self, health: u32) {
+        if health == self.to {
+            return;
+        }
+        self.from = self.value();
+        self.to = health;
+        self.elapsed = 0.0;
+    }
+
+    pub fn update(&mut self, delta: f32) {
+        self.elapsed = (self.elapsed + delta).min(HEALTH_SLIDE_TIME);
+    }
+
+    /// The health to draw this frame, easing out so the bar slows as it arrives.
+    pub fn value(&self) -> f32 {
+        let t = self.elapsed / HEALTH_SLIDE_TIME;
+        let eased = 1.0 - (1.0 - t) * (1.0 - t);
+        self.from + (self.to as f32 - self.from) * eased
+    }
+}
+
+/// Seconds a hit is shown for.
+const HIT_TIME: f32 = 0.9;
+
+/// The shake, flash and floating number shown when an entity's health changes.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct HitEffect {
+    /// Health gained, negative for damage.
+    change: i64,
+    elapsed: f32,
+}
+
+impl HitEffect {
+    pub fn new(change: i64) -> Self {
+        Self {
+            change,
+            elapsed: 0.0,
         }
+    }
+
+    pub fn update(&mut self, delta: f32) {
+        self.elapsed += delta;
+    }
+
+    pub fn finished(&self) -> bool {
+        self.elapsed >= HIT_TIME
+    }
 
+    /// How far to move the sprite this frame, only damage shakes it.
+    pub fn shake(&self) -> Vec2 {
+        if self.change >= 0 || self.elapsed >= HIT_TIME / 2.0 {
+            return Vec2::ZERO;
+        }
+        let strength = 12.0 * (1.0 - self.elapsed / (HIT_TIME / 2.0));
+        vec2((self.elapsed * 60.0).sin() * strength, 0.0)
+    }
+
+    /// Tint for the sprite this frame, damage flashes red and healing green.
+    pub fn tint(&self) -> Color {
+        let flashing =
+            self.elapsed < HIT_TIME / 2.0 && ((self.elapsed * 10.0) as u32).is_multiple_of(2);
+        match (flashing, self.change < 0) {
+            (false, _) => WHITE,
+            (true, true) => Color::new(1.0, 0.4, 0.4, 1.0),
+            (true, false) => Color::new(0.6, 1.0, 0.6, 1.0),
+        }
+    }
+
+    /// Draw the change as a number rising and fading above a point.
+    pub fn draw_number(&self, x: f32, y: f32) {
+        let t = (self.elapsed / HIT_TIME).min(1.0);
+        let (text, color) = match self.change < 0 {
+            true => (format!("{}", self.change), RED),
+            false => (format!("+{}", self.change), GREEN),
+        };
+        draw_text(
+            text.as_str(),
+            x,
+            y - 60.0 * t,
+            50.0,
+            Color {
+                a: 1.0 - t,
+                ..color
+            },
+        );
+    }
+}
+
+/// How dialogue is shown.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct DialogueSettings {
+    /// Characters revealed per second.
+    pub text_speed: f32,
+    /// Seconds a finished message stays up before moving on by itself, or None to wait for the player.
+    pub auto_advance: Option<f32>,
+}
+
+impl Default for DialogueSettings {
+    fn default() -> Self {
+        Self {
+            text_speed: 60.0,
+            auto_advance: None,
+        }
+    }
+}
+
+/// A message box along the bottom of the screen that types its text out a character at a time.
+pub struct DialogueBox {
+    settings: DialogueSettings,
+    /// Characters shown so far, counted in fractions between frames.
+    revealed: f32,
+    /// Seconds since the whole message was shown.
+    waited: f32,
+}
+
+impl DialogueBox {
+    const MARGIN: f32 = 50.0;
+
+    pub fn new(settings: DialogueSettings) -> Self {
+        Self {
+            settings,
+            revealed: 0.0,
+            waited: 0.0,
+        }
+    }
+
+    pub fn set_settings(&mut self, settings: DialogueSettings) {
+        self.settings = settings;
+    }
+
+    /// Draw a message and return true once the player has moved past it.
+    /// The first click or key press shows the rest of the message and the next one moves on.
+    pub fn update(&mut self, text: &str) -> bool {
+        let theme = theme();
+        let font_size = theme.dialogue_size;
+        let line_height = font_size as f32 * 1.5;
+        let top = layout::height() - DIALOGUE_HEIGHT;
+        let width = layout::width() - 2.0 * Self::MARGIN;
+        let lines = wrap_text(text, width, |line| {
+            measure_themed_text(line, font_size).width
+        });
+        let length = lines.iter().map(|line| line.chars().count()).sum();
+
+        let done = self.step(length, get_frame_time(), advance_pressed());
+
+        draw_rectangle(
+            0.0,
+            top,
+            layout::width(),
+            DIALOGUE_HEIGHT,
+            theme.panel_color,
+        );
+        let mut remaining = self.revealed as usize;
+        for (i, line) in lines.iter().enumerate() {
+            let shown: String = line.chars().take(remaining).collect();
+            remaining = remaining.saturating_sub(line.chars().count());
+            draw_themed_text(
+                shown.as_str(),
+                Self::MARGIN,
+                top + Self::MARGIN + line_height * i as f32,
+                font_size,
+                theme.text_color,
+            );
+        }
+        if self.revealed as usize >= length && self.settings.auto_advance.is_none() {
+            draw_themed_text(
+                ">",
+                layout::width() - Self::MARGIN,
+                layout::height() - 30.0,
+                font_size,
+                theme.text_color,
+            );
+        }
+        done
+    }
+
+    /// Reveal more of a message with the given number of characters.
+    /// Returns true when it is time for the next message, ready to type that one out from the start.
+    fn step(&mut self, length: usize, delta: f32, pressed: bool) -> bool {
+        let complete = self.revealed as usize >= length;
+        let auto = self
+            .settings
+            .auto_advance
+            .is_some_and(|delay| complete && self.waited >= delay);
+
+        if (complete && pressed) || auto {
+            self.revealed = 0.0;
+            self.waited = 0.0;
+            return true;
+        }
+
+        if pressed {
+            self.revealed = length as f32;
+        } else if complete {
+            self.waited += delta;
+        } else {
+            self.revealed = (self.revealed + self.settings.text_speed * delta).min(length as f32);
+        }
+        false
+    }
+}
+
+impl Default for DialogueBox {
+    fn default() -> Self {
+        Self::new(DialogueSettings::default())
+    }
+}
+
+/// Split text into lines no wider than `max_width`, breaking between words.
+/// Line breaks already in the text are kept, and a word too long for a line gets one to itself.
+pub fn wrap_text(text: &str, max_width: f32, measure: impl Fn(&str) -> f32) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in text.split('\n') {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            let longer = match line.is_empty() {
+                true => String::from(word),
+                false => format!("{} {}", line, word),
+            };
+            if measure(&longer) > max_width && !line.is_empty() {
+                lines.push(std::mem::replace(&mut line, String::from(word)));
+            } else {
+                line = longer;
+            }
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+/// Width of the box drawn by `draw_tooltip`.
+pub const TOOLTIP_WIDTH: f32 = 500.0;
+
+/// Draw lines of text in a box with its top right corner at a point, wrapping any that are too long.
+pub fn draw_tooltip(lines: &[(String, Color)], right: f32, top: f32) {
+    const PADDING: f32 = 15.0;
+    let theme = theme();
+    let font_size = theme.small_size;
+    let line_height = font_size as f32 * 1.2;
+    let wrapped: Vec<(String, Color)> = lines
+        .iter()
+        .flat_map(|(line, color)| {
+            wrap_text(line, TOOLTIP_WIDTH - 2.0 * PADDING, |text| {
+                measure_themed_text(text, font_size).width
+            })
+            .into_iter()
+            .map(move |line| (line, *color))
+        })
+        .collect();
+
+    let left = right - TOOLTIP_WIDTH;
+    let height = 2.0 * PADDING + line_height * wrapped.len() as f32;
+    draw_rectangle(left, top, TOOLTIP_WIDTH, height, theme.panel_color);
+    draw_rectangle_lines(left, top, TOOLTIP_WIDTH, height, 2.0, theme.text_color);
+    for (i, (line, color)) in wrapped.iter().enumerate() {
+        draw_themed_text(
+            line.as_str(),
+            left + PADDING,
+            top + PADDING + font_size as f32 + line_height * i as f32,
+            font_size,
+            *color,
+        );
+    }
+}
+
+/// Lines of a tooltip and the area of the button it belongs to.
+type Tooltip = (Vec<(String, Color)>, Rect);
+
+thread_local! {
+    /// Tooltip of the button under the mouse or with focus, drawn over everything else.
+    static TOOLTIP: RefCell<Option<Tooltip>> = const { RefCell::new(None) };
+}
+
+/// Draw the tooltip a button left for the end of the frame.
+/// Called once after everything else is drawn so nothing covers it.
+pub fn end_frame() {
+    if let Some((lines, rect)) = TOOLTIP.take() {
+        // to the left of the button, or to its right if there is no room
+        let right = match rect.x - 20.0 - TOOLTIP_WIDTH < 0.0 {
+            true => rect.right() + 20.0 + TOOLTIP_WIDTH,
+            false => rect.x - 20.0,
+        };
+        draw_tooltip(&lines, right, rect.y);
+    }
+}
+
+/// Draw the stats, moves, weaknesses and strengths of an entity as a column of text.
+pub fn draw_entity_details(entity: &Entity, x: f32, y: f32) {
+    let list = |moves: &[Move]| {
+        moves
+            .iter()
+            .map(|mv| format!("{}", mv))
+            .collect::<Vec<String>>()
+            .join(", ")
+    };
+
+    let mut lines = vec![
+        format!("{}  Lv. {}", entity, entity.level),
+        format!("XP: {}/{}", entity.xp, entity.xp_to_next_level()),
+        format!("HP: {}/{}", entity.health, entity.max_health),
+        format!("Attack: {}", entity.attack),
+        format!("Defense: {}", entity.defense),
+        format!("Accuracy: {}", entity.accuracy),
+        format!("Error handling: {}", entity.error_handling),
+        String::from("Moves:"),
+    ];
+    for mv in entity.get_moves() {
+        lines.push(format!("  {}", mv));
+    }
+    lines.push(format!("Weak to: {}", list(&entity.weaknesses)));
+    lines.push(format!("Resists: {}", list(&entity.strengths)));
+
+    for (i, line) in lines.iter().enumerate() {
+        draw_text(line.as_str(), x, y + (35.0 * i as f32), 30.0, WHITE);
+    }
+}
+
+/// Plays a move's animation from the attacker's sprite to the target's.
+pub struct AnimationPlayer {
+    steps: Vec<AnimationStep>,
+    from: Vec2,
+    to: Vec2,
+    elapsed: f32,
+}
+
+impl AnimationPlayer {
+    /// Start an animation between the centres of two sprites.
+    pub fn new(animation: &MoveAnimation, from: Vec2, to: Vec2) -> Self {
+        Self {
+            steps: animation.steps.clone(),
+            from,
+            to,
+            elapsed: 0.0,
+        }
+    }
+
+    pub fn update(&mut self, delta: f32) {
+        self.elapsed += delta;
+    }
+
+    /// Jump to the end, for players who don't want to watch.
+    pub fn skip(&mut self) {
+        self.elapsed = self.steps.iter().map(|step| step.seconds).sum();
+    }
+
+    pub fn finished(&self) -> bool {
+        self.current().is_none()
+    }
+
+    /// The step playing now and how far through it is, from 0 to 1.
+    fn current(&self) -> Option<(&AnimationStep, f32)> {
+        let mut start = 0.0;
+        for step in self.steps.iter() {
+            if self.elapsed < start + step.seconds {
+                return Some((step, (self.elapsed - start) / step.seconds));
+            }
+            start += step.seconds;
+        }
+        None
+    }
+
+    /// Colour for the target's sprite this frame.
+    pub fn target_tint(&self) -> Color {
+        match self.current() {
+            Some((step, t)) if step.kind == StepKind::Tint => {
+                // fade in and back out
+                let strength = 1.0 - (2.0 * t - 1.0).abs();
+                Color::new(
+                    1.0 + (step.color.r - 1.0) * strength,
+                    1.0 + (step.color.g - 1.0) * strength,
+                    1.0 + (step.color.b - 1.0) * strength,
+                    1.0,
+                )
+            }
+            _ => WHITE,
+        }
+    }
+
+    /// Draw the step playing now over the battle.
+    pub fn draw(&self) {
+        let (step, t) = match self.current() {
+            Some(current) => current,
+            None => return,
+        };
+
+        match step.kind {
+            StepKind::Projectile => {
+                let position = self.from.lerp(self.to, t);
+                draw_circle(position.x, position.y, 18.0, step.color);
+            }
+            StepKind::Burst => {
+                // particles spread evenly by the golden angle so no randomness is needed
+                for i in 0..24 {
+                    let angle = i as f32 * 2.4;
+                    let distance = (40.0 + 6.0 * (i % 5) as f32) * t * 4.0;
+                    let position = self.to + Vec2::from_angle(angle) * distance;
+                    let color = Color {
+                        a: 1.0 - t,
+                        ..step.color
+                    };
+                    draw_circle(position.x, position.y, 8.0 * (1.0 - t) + 2.0, color);
+                }
+            }
+            StepKind::Flash => {
+                let color = Color {
+                    a: 0.6 * (1.0 - t),
+                    ..step.color
+                };
+                draw_rectangle(0.0, 0.0, layout::width(), layout::height(), color);
+            }
+            StepKind::Tint => {}
+        }
+    }
+}
+
+/// Menu controls that don't need a mouse, from the keyboard or a gamepad's d-pad and face buttons.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum NavInput {
+    Up,
+    Down,
+    Left,
+    Right,
+    Confirm,
+    Back,
+}
+
+impl NavInput {
+    pub const ALL: [NavInput; 6] = [
+        NavInput::Up,
+        NavInput::Down,
+        NavInput::Left,
+        NavInput::Right,
+        NavInput::Confirm,
+        NavInput::Back,
+    ];
+
+    const DIRECTIONS: [NavInput; 4] = [
+        NavInput::Up,
+        NavInput::Down,
+        NavInput::Left,
+        NavInput::Right,
+    ];
+
+    /// The key that always works, so the player can't lose a control by rebinding it.
+    fn fixed_key(&self) -> KeyCode {
+        match self {
+            NavInput::Up => KeyCode::Up,
+            NavInput::Down => KeyCode::Down,
+            NavInput::Left => KeyCode::Left,
+            NavInput::Right => KeyCode::Right,
+            NavInput::Confirm => KeyCode::Enter,
+            NavInput::Back => KeyCode::Escape,
+        }
+    }
+
+    pub fn pressed(&self) -> bool {
+        let bound = BINDINGS.with_borrow(|bindings| bindings.get(*self));
+        is_key_pressed(self.fixed_key()) || is_key_pressed(bound) || gamepad::pressed(*self)
+    }
+
+    /// True for as long as one of the control's keys or gamepad buttons is down.
+    pub fn held(&self) -> bool {
+        let bound = BINDINGS.with_borrow(|bindings| bindings.get(*self));
+        is_key_down(self.fixed_key()) || is_key_down(bound) || gamepad::held(*self)
+    }
+}
+
+impl fmt::Display for NavInput {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NavInput::Up => write!(f, "Up"),
+            NavInput::Down => write!(f, "Down"),
+            NavInput::Left => write!(f, "Left"),
+            NavInput::Right => write!(f, "Right"),
+            NavInput::Confirm => write!(f, "Confirm"),
+            NavInput::Back => write!(f, "Back"),
+        }
+    }
+}
+
+/// A second key for each menu control, on top of the arrow keys, Enter and Escape.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct KeyBindings {
+    keys: [KeyCode; 6],
+}
+
+impl KeyBindings {
+    pub fn get(&self, input: NavInput) -> KeyCode {
+        self.keys[input as usize]
+    }
+
+    pub fn set(&mut self, input: NavInput, key: KeyCode) {
+        self.keys[input as usize] = key;
+    }
+
+    /// The control a key is bound to, if any.
+    pub fn bound_to(&self, key: KeyCode) -> Option<NavInput> {
+        NavInput::ALL
+            .into_iter()
+            .find(|input| self.get(*input) == key)
+    }
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            keys: [
+                KeyCode::W,
+                KeyCode::S,
+                KeyCode::A,
+                KeyCode::D,
+                KeyCode::Space,
+                KeyCode::Backspace,
+            ],
+        }
+    }
+}
+
+thread_local! {
+    static BINDINGS: RefCell<KeyBindings> = RefCell::new(KeyBindings::default());
+}
+
+/// Use different keys for the menu controls from now on.
+pub fn set_key_bindings(bindings: KeyBindings) {
+    BINDINGS.with_borrow_mut(|current| *current = bindings);
+}
+
+thread_local! {
+    static THEME: RefCell<(Theme, Option<Font>)> = RefCell::new((Theme::default(), None));
+}
+
+/// Draw the UI with a different theme from now on, and the font it names once that is loaded.
+pub fn set_theme(theme: Theme, font: Option<Font>) {
+    THEME.with_borrow_mut(|current| *current = (theme, font));
+}
+
+/// The theme the UI is drawn with.
+pub fn theme() -> Theme {
+    THEME.with_borrow(|(theme, _)| theme.clone())
+}
+
+/// Draw text in the theme's font.
+pub fn draw_themed_text(text: &str, x: f32, y: f32, font_size: u16, color: Color) {
+    THEME.with_borrow(|(_, font)| {
+        draw_text_ex(
+            text,
+            x,
+            y,
+            TextParams {
+                font: font.as_ref(),
+                font_size,
+                color,
+                ..Default::default()
+            },
+        );
+    });
+}
+
+/// Measure text drawn in the theme's font.
+pub fn measure_themed_text(text: &str, font_size: u16) -> TextDimensions {
+    THEME.with_borrow(|(_, font)| measure_text(text, font.as_ref(), font_size, 1.0))
+}
+
+/// Draw a texture stretched over a rectangle, keeping `slice` pixels at each edge their size
+/// so the corners and borders don't stretch with it.
+pub fn draw_nine_slice(texture: &Texture2D, dest: Rect, slice: f32, color: Color) {
+    let size = texture.size();
+    let slice = slice
+        .min(size.x / 2.0)
+        .min(size.y / 2.0)
+        .min(dest.w / 2.0)
+        .min(dest.h / 2.0);
+    if slice <= 0.0 {
         draw_texture_ex(
-            self.health_bar_texture,
-            self.xpos,
-            bar_y,
-            WHITE,
-            health_bar_params,
+            texture,
+            dest.x,
+            dest.y,
+            color,
+            DrawTextureParams {
+                dest_size: Some(dest.size()),
+                ..Default::default()
+            },
         );
+        return;
+    }
+
+    // the left, middle and right columns, then the top, middle and bottom rows
+    let source_columns = [
+        (0.0, slice),
+        (slice, size.x - 2.0 * slice),
+        (size.x - slice, slice),
+    ];
+    let dest_columns = [
+        (dest.x, slice),
+        (dest.x + slice, dest.w - 2.0 * slice),
+        (dest.x + dest.w - slice, slice),
+    ];
+    let source_rows = [
+        (0.0, slice),
+        (slice, size.y - 2.0 * slice),
+        (size.y - slice, slice),
+    ];
+    let dest_rows = [
+        (dest.y, slice),
+        (dest.y + slice, dest.h - 2.0 * slice),
+        (dest.y + dest.h - slice, slice),
+    ];
+    for ((sy, sh), (dy, dh)) in source_rows.into_iter().zip(dest_rows) {
+        for ((sx, sw), (dx, dw)) in source_columns.into_iter().zip(dest_columns) {
+            if sw <= 0.0 || sh <= 0.0 || dw <= 0.0 || dh <= 0.0 {
+                continue;
+            }
+            draw_texture_ex(
+                texture,
+                dx,
+                dy,
+                color,
+                DrawTextureParams {
+                    source: Some(Rect::new(sx, sy, sw, sh)),
+                    dest_size: Some(vec2(dw, dh)),
+                    ..Default::default()
+                },
+            );
+        }
     }
 }
 
+/// True when the player wants to move past a message, by clicking or with the keyboard.
+pub fn advance_pressed() -> bool {
+    is_mouse_button_pressed(MouseButton::Left) || NavInput::Confirm.pressed()
+}
+
+/// Which button has keyboard focus, buttons register themselves each time they are drawn.
+#[derive(Default)]
+struct Focus {
+    /// Buttons drawn last frame, in the order they were drawn.
+    last: Vec<Rect>,
+    current: Vec<Rect>,
+    /// Kept as the button's area since buttons are rebuilt every frame.
+    focused: Option<Rect>,
+    /// Set once the keyboard is used and cleared when the mouse moves, focus is only shown while set.
+    active: bool,
+    /// Set for the frame confirm is pressed on the focused button.
+    confirmed: bool,
+    mouse: (f32, f32),
+}
+
+thread_local! {
+    static FOCUS: RefCell<Focus> = RefCell::new(Focus::default());
+}
+
+/// Move keyboard focus between the buttons drawn last frame.
+/// Called once at the start of every frame, before anything is drawn.
+pub fn begin_frame() {
+    FOCUS.with_borrow_mut(|focus| {
+        focus.last = std::mem::take(&mut focus.current);
+        focus.confirmed = false;
+
+        let mouse = mouse_position();
+        if mouse != focus.mouse {
+            focus.mouse = mouse;
+            focus.active = false;
+        }
+
+        if focus.last.is_empty() {
+            return;
+        }
+        // a button that has gone away gives focus back to the first one
+        if !focus.focused.is_some_and(|rect| focus.last.contains(&rect)) {
+            focus.focused = Some(focus.last[0]);
+        }
+
+        for direction in NavInput::DIRECTIONS {
+            if !direction.pressed() {
+                continue;
+            }
+            if focus.active {
+                if let Some(next) = nearest(focus.focused, &focus.last, direction) {
+                    focus.focused = Some(next);
+                }
+            }
+            focus.active = true;
+        }
+
+        // the first press only shows where focus is
+        if NavInput::Confirm.pressed() {
+            focus.confirmed = focus.active;
+            focus.active = true;
+        }
+    });
+}
+
+/// The closest button in a direction from the focused one, preferring buttons in line with it.
+fn nearest(from: Option<Rect>, rects: &[Rect], direction: NavInput) -> Option<Rect> {
+    let from = from?.center();
+    rects
+        .iter()
+        .filter_map(|rect| {
+            let offset = rect.center() - from;
+            let (along, across) = match direction {
+                NavInput::Up => (-offset.y, offset.x),
+                NavInput::Down => (offset.y, offset.x),
+                NavInput::Left => (-offset.x, offset.y),
+                NavInput::Right => (offset.x, offset.y),
+                NavInput::Confirm | NavInput::Back => return None,
+            };
+            (along > 0.0).then_some((along + 2.0 * across.abs(), *rect))
+        })
+        .min_by(|a, b| a.0.total_cmp(&b.0))
+        .map(|(_, rect)| rect)
+}
+
+/// Size of a button that fits a label with padding around it, never smaller than its texture.
+fn fitted_size(texture: Vec2, label: Vec2, padding: f32) -> Vec2 {
+    texture.max(label + Vec2::splat(2.0 * padding))
+}
+
 pub struct ButtonLink<'a, T> {
     pub link: T,
     pub button: Button<'a>,
@@ -75,6 +942,14 @@ pub struct Button<'a> {
     hover_texture: Option<&'a Texture2D>,
     click_texture: Option<&'a Texture2D>,
     mouse_down: bool,
+    /// Set for buttons that follow a point on the screen, which moves when the window is resized.
+    anchor: Option<(Anchor, Vec2)>,
+    /// Text drawn on the button in the theme's font.
+    label: Option<String>,
+    /// Disabled buttons are greyed out and can't be clicked.
+    disabled: bool,
+    /// Shown while the button is hovered or has focus.
+    tooltip: Option<Vec<(String, Color)>>,
 }
 
 impl<'a> Button<'a> {
@@ -88,9 +963,68 @@ impl<'a> Button<'a> {
             hover_texture: None,
             click_texture: None,
             mouse_down: false,
+            anchor: None,
+            label: None,
+            disabled: false,
+            tooltip: None,
+        }
+    }
+
+    /// A button sized to fit a label, stretching its texture if the label is too long for it.
+    pub fn text(texture: &'a Texture2D, label: impl Into<String>, xpos: f32, ypos: f32) -> Self {
+        let mut button = Self::new(texture, xpos, ypos);
+        button.fit_label(label.into());
+        button
+    }
+
+    /// A button sized to fit a label, placed at an offset from a point on the screen.
+    pub fn text_anchored(
+        texture: &'a Texture2D,
+        label: impl Into<String>,
+        anchor: Anchor,
+        offset: Vec2,
+    ) -> Self {
+        let mut button = Self::anchored(texture, anchor, offset);
+        button.fit_label(label.into());
+        button
+    }
+
+    fn fit_label(&mut self, label: String) {
+        let theme = theme();
+        let text = measure_themed_text(&label, theme.text_size);
+        let size = fitted_size(
+            self.texture.size(),
+            vec2(text.width, theme.text_size as f32),
+            theme.padding,
+        );
+        (self.width, self.height) = (size.x, size.y);
+        self.label = Some(label);
+    }
+
+    /// A button placed at an offset from a point on the screen.
+    pub fn anchored(texture: &'a Texture2D, anchor: Anchor, offset: Vec2) -> Self {
+        let position = anchor.at(offset);
+        let mut button = Self::new(texture, position.x, position.y);
+        button.anchor = Some((anchor, offset));
+        button
+    }
+
+    /// Where the button is this frame.
+    fn position(&self) -> Vec2 {
+        match self.anchor {
+            Some((anchor, offset)) => anchor.at(offset),
+            None => vec2(self.xpos, self.ypos),
         }
     }
 
+    pub fn set_disabled(&mut self, disabled: bool) {
+        self.disabled = disabled;
+    }
+
+    pub fn set_tooltip(&mut self, lines: Vec<(String, Color)>) {
+        self.tooltip = Some(lines);
+    }
+
     pub fn attach_hover_texture(&mut self, texture: &'a Texture2D) {
         self.hover_texture = Some(texture);
     }
@@ -100,27 +1034,44 @@ impl<'a> Button<'a> {
     }
 
     pub fn clicked(&mut self) -> bool {
-        if self.hovered() {
-            if is_mouse_button_pressed(MouseButton::Left) {
-                self.mouse_down = true;
-                return true;
-            }
+        if self.disabled {
+            return false;
+        }
+        let confirmed = self.focused() && FOCUS.with_borrow(|focus| focus.confirmed);
+        if confirmed || (self.hovered() && is_mouse_button_pressed(MouseButton::Left)) {
+            self.mouse_down = true;
+            audio::queue(SoundEffect::Click);
+            return true;
         }
         false
     }
 
-    fn hovered(&self) -> bool {
-        let x = mouse_position().0;
-        let y = mouse_position().1;
-        if x > self.xpos && x < self.xpos + self.width {
-            if y > self.ypos && y < self.ypos + self.height {
-                return true;
-            }
-        }
-        false
+    fn rect(&self) -> Rect {
+        let position = self.position();
+        Rect::new(position.x, position.y, self.width, self.height)
+    }
+
+    /// True if the button has keyboard focus and the player is using the keyboard.
+    pub fn focused(&self) -> bool {
+        FOCUS.with_borrow(|focus| focus.active && focus.focused == Some(self.rect()))
+    }
+
+    pub fn hovered(&self) -> bool {
+        let Vec2 { x, y } = layout::mouse();
+        let Rect {
+            x: left,
+            y: top,
+            w,
+            h,
+        } = self.rect();
+        x > left && x < left + w && y > top && y < top + h
     }
 
     pub fn draw(&mut self) {
+        // keep the public position up to date for anything drawn next to the button
+        let position = self.position();
+        (self.xpos, self.ypos) = (position.x, position.y);
+
         let mut texture = self.texture;
 
         if self.mouse_down {
@@ -142,6 +1093,149 @@ impl<'a> Button<'a> {
             };
         }
 
-        draw_texture(texture, self.xpos, self.ypos, WHITE);
+        let theme = theme();
+        let (tint, text_color) = match self.disabled {
+            true => (theme.muted_color, theme.muted_color),
+            false => (WHITE, theme.text_color),
+        };
+        draw_nine_slice(texture, self.rect(), theme.button_slice, tint);
+        if let Some(ref label) = self.label {
+            let width = measure_themed_text(label, theme.text_size).width;
+            let left = ((self.width - width) / 2.0).max(theme.padding);
+            // roughly centred on the height of a capital letter
+            let baseline = self.ypos + (self.height + theme.text_size as f32 * 2.0 / 3.0) / 2.0;
+            draw_themed_text(
+                label,
+                self.xpos + left,
+                baseline,
+                theme.text_size,
+                text_color,
+            );
+        }
+        if let Some(ref lines) = self.tooltip {
+            if self.hovered() || self.focused() {
+                TOOLTIP.set(Some((lines.clone(), self.rect())));
+            }
+        }
+
+        if self.focused() {
+            draw_rectangle_lines(
+                self.xpos - 4.0,
+                self.ypos - 4.0,
+                self.width + 8.0,
+                self.height + 8.0,
+                4.0,
+                YELLOW,
+            );
+        }
+        FOCUS.with_borrow_mut(|focus| focus.current.push(self.rect()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn health_slides_to_new_values() {
+        let mut health = HealthTween::new(100);
+        health.set(40);
+        assert_eq!(health.value(), 100.0);
+        assert_eq!(health.target(), 40);
+
+        health.update(HEALTH_SLIDE_TIME / 2.0);
+        assert!(health.value() < 100.0 && health.value() > 40.0);
+
+        // changing again mid slide starts from where the bar is
+        let halfway = health.value();
+        health.set(80);
+        assert_eq!(health.value(), halfway);
+        health.update(HEALTH_SLIDE_TIME * 2.0);
+        assert_eq!(health.value(), 80.0);
+
+        assert_eq!(health_color(0.6), None);
+        assert_eq!(health_color(0.4), Some(ORANGE));
+        assert_eq!(health_color(0.1), Some(RED));
+    }
+
+    #[test]
+    fn dialogue_types_then_advances() {
+        let mut dialogue = DialogueBox::new(DialogueSettings {
+            text_speed: 10.0,
+            auto_advance: None,
+        });
+
+        // the first press finishes the message, the second moves on
+        assert!(!dialogue.step(20, 0.5, false));
+        assert_eq!(dialogue.revealed, 5.0);
+        assert!(!dialogue.step(20, 0.0, true));
+        assert_eq!(dialogue.revealed, 20.0);
+        assert!(!dialogue.step(20, 5.0, false));
+        assert!(dialogue.step(20, 0.0, true));
+        assert_eq!(dialogue.revealed, 0.0);
+
+        // auto advance waits once the message is out
+        dialogue.set_settings(DialogueSettings {
+            text_speed: 100.0,
+            auto_advance: Some(1.0),
+        });
+        assert!(!dialogue.step(10, 0.5, false));
+        assert!(!dialogue.step(10, 0.5, false));
+        assert!(!dialogue.step(10, 0.6, false));
+        assert!(dialogue.step(10, 0.0, false));
+    }
+
+    #[test]
+    fn wrap_long_lines() {
+        let measure = |line: &str| line.len() as f32;
+        assert_eq!(
+            wrap_text("the quick brown fox jumps", 10.0, measure),
+            vec!["the quick", "brown fox", "jumps"]
+        );
+        assert_eq!(
+            wrap_text("a\nsupercalifragilistic b", 10.0, measure),
+            vec!["a", "supercalifragilistic", "b"]
+        );
+    }
+
+    #[test]
+    fn focus_moves_to_the_nearest_button() {
+        // a column of buttons with one off to the right
+        let column = |i: usize| Rect::new(100.0, 150.0 + 75.0 * i as f32, 300.0, 60.0);
+        let right = Rect::new(800.0, 240.0, 300.0, 60.0);
+        let rects = [column(0), column(1), column(2), right];
+
+        assert_eq!(
+            nearest(Some(column(0)), &rects, NavInput::Down),
+            Some(column(1))
+        );
+        assert_eq!(
+            nearest(Some(column(1)), &rects, NavInput::Up),
+            Some(column(0))
+        );
+        assert_eq!(
+            nearest(Some(column(1)), &rects, NavInput::Right),
+            Some(right)
+        );
+        assert_eq!(
+            nearest(Some(right), &rects, NavInput::Left),
+            Some(column(1))
+        );
+        assert_eq!(nearest(Some(column(0)), &rects, NavInput::Up), None);
+    }
+
+    #[test]
+    fn text_buttons_fit_their_label() {
+        let texture = vec2(240.0, 60.0);
+        // short labels keep the texture's size
+        assert_eq!(fitted_size(texture, vec2(100.0, 30.0), 10.0), texture);
+        assert_eq!(
+            fitted_size(texture, vec2(300.0, 30.0), 10.0),
+            vec2(320.0, 60.0)
+        );
+        assert_eq!(
+            fitted_size(texture, vec2(300.0, 50.0), 10.0),
+            vec2(320.0, 70.0)
+        );
     }
 }